@@ -0,0 +1,433 @@
+use crate::core::audit::AuditConfig;
+use crate::core::database::Database;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+/// `prev_hash` of the first record in the chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const VERIFY_PAGE_SIZE: i64 = 500;
+
+/// Last link of the chain: the next record gets `seq + 1` and `prev_hash = hash`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChainHead {
+    pub seq: i64,
+    pub hash: String,
+}
+
+impl Default for ChainHead {
+    fn default() -> Self {
+        Self { seq: 0, hash: GENESIS_HASH.to_string() }
+    }
+}
+
+/// The hashed contents of an `mcp_audit_log` record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainedRecord {
+    /// Record ID (not hashed; only present when read back)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub seq: i64,
+    #[serde(default)]
+    pub method: Option<String>,
+    pub tool_name: String,
+    pub project_id: Option<String>,
+    pub arguments: Value,
+    pub status: String,
+    pub message: String,
+    pub duration_ms: i64,
+    pub executed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub principal: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub client_name: Option<String>,
+    #[serde(default)]
+    pub client_version: Option<String>,
+    #[serde(default)]
+    pub client_ip: Option<String>,
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    pub prev_hash: String,
+    #[serde(default)]
+    pub hash: String,
+    /// Assigned when the record is queued, so a replayed write can be recognised (not hashed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+/// Serialize JSON with object keys sorted at every level, independent of map ordering features
+pub fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys.iter()
+                .map(|k| format!("{}:{}", Value::String((*k).clone()), canonical_json(&map[*k])))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+impl ChainedRecord {
+    fn canonical(&self) -> String {
+        let mut fields = serde_json::json!({
+            "seq": self.seq,
+            "tool_name": self.tool_name,
+            "project_id": self.project_id,
+            "arguments": self.arguments,
+            "status": self.status,
+            "message": self.message,
+            "duration_ms": self.duration_ms,
+            "executed_at": self.executed_at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            "prev_hash": self.prev_hash,
+        });
+
+        // Attribution fields are only hashed when present, so records written before they existed still verify
+        let attribution = [
+            ("method", &self.method),
+            ("principal", &self.principal),
+            ("session_id", &self.session_id),
+            ("client_name", &self.client_name),
+            ("client_version", &self.client_version),
+            ("client_ip", &self.client_ip),
+            ("correlation_id", &self.correlation_id),
+        ];
+        if let Some(map) = fields.as_object_mut() {
+            for (key, value) in attribution {
+                if let Some(v) = value {
+                    map.insert(key.to_string(), Value::String(v.clone()));
+                }
+            }
+        }
+
+        canonical_json(&fields)
+    }
+
+    /// SHA-256 over the canonical contents, which include the previous record's hash
+    pub fn compute_hash(&self) -> String {
+        hex::encode(Sha256::digest(self.canonical().as_bytes()))
+    }
+}
+
+/// Latest link, which may have been archived already (then only its tombstone remains)
+pub async fn load_head(db: &Database) -> Result<ChainHead> {
+    let mut result = db.query("
+        SELECT seq, hash FROM mcp_audit_log WHERE seq != NONE ORDER BY seq DESC LIMIT 1;
+        SELECT seq, hash FROM mcp_audit_tombstone ORDER BY seq DESC LIMIT 1;
+    ").await?;
+    let live: Vec<ChainHead> = result.take(0)?;
+    let archived: Vec<ChainHead> = result.take(1)?;
+    Ok(live.into_iter().chain(archived).max_by_key(|h| h.seq).unwrap_or_default())
+}
+
+/// Chain link of an archived record
+#[derive(Debug, Clone, Deserialize)]
+struct Tombstone {
+    seq: i64,
+    prev_hash: String,
+    hash: String,
+}
+
+/// Follow tombstones from `from` up to (not including) `to_seq`.
+/// Returns the last bridged link, or why the gap cannot be bridged.
+async fn bridge_gap(db: &Database, from: &ChainHead, to_seq: i64) -> Result<std::result::Result<(ChainHead, u64), String>> {
+    let mut result = db.query("SELECT seq, prev_hash, hash FROM mcp_audit_tombstone WHERE seq > $from AND seq < $to ORDER BY seq ASC")
+        .bind(("from", from.seq))
+        .bind(("to", to_seq))
+        .await?;
+    let tombstones: Vec<Tombstone> = result.take(0)?;
+
+    let mut link = from.clone();
+    let mut bridged = 0;
+    for t in tombstones {
+        if t.seq != link.seq + 1 || t.prev_hash != link.hash {
+            break;
+        }
+        link = ChainHead { seq: t.seq, hash: t.hash };
+        bridged += 1;
+    }
+    if link.seq + 1 != to_seq {
+        return Ok(Err(format!("missing records between seq {} and {}", link.seq, to_seq)));
+    }
+    Ok(Ok((link, bridged)))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenLink {
+    pub seq: i64,
+    pub id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChainReport {
+    /// First sequence number that was checked (older records may have been archived)
+    pub first_seq: Option<i64>,
+    pub last_seq: Option<i64>,
+    pub verified: u64,
+    /// Archived records whose links were checked through their tombstones
+    pub archived: u64,
+    pub broken: Option<BrokenLink>,
+}
+
+/// Walk the chain in `seq` order from `after_seq`, recomputing every hash, and stop at the first broken link.
+/// Gaps left by retention are bridged through `mcp_audit_tombstone`.
+pub async fn verify_chain(db: &Database, after_seq: i64) -> Result<ChainReport> {
+    let mut report = ChainReport::default();
+    let mut prev: Option<ChainHead> = None;
+    let mut cursor = after_seq;
+
+    loop {
+        let mut result = db.query("
+            SELECT type::string(id) AS id, seq, method, tool_name,
+                (IF project_id != NONE THEN type::string(project_id) END) AS project_id,
+                arguments, status, message, duration_ms, type::string(executed_at) AS executed_at,
+                principal, session_id, client_name, client_version, client_ip, correlation_id, prev_hash, hash
+            FROM mcp_audit_log WHERE seq > $after ORDER BY seq ASC LIMIT $limit
+        ")
+        .bind(("after", cursor))
+        .bind(("limit", VERIFY_PAGE_SIZE))
+        .await?;
+        let page: Vec<ChainedRecord> = result.take(0)?;
+        if page.is_empty() {
+            break;
+        }
+
+        for record in page {
+            cursor = record.seq;
+            report.first_seq.get_or_insert(record.seq);
+            report.last_seq = Some(record.seq);
+
+            let broken = |reason: String| BrokenLink { seq: record.seq, id: record.id.clone().unwrap_or_default(), reason };
+
+            let start = match prev.take() {
+                Some(p) => Some(p),
+                // A full verification starts at genesis; an archived prefix is bridged through tombstones
+                None if after_seq == 0 => Some(ChainHead::default()),
+                None => None,
+            };
+            if let Some(mut p) = start {
+                if record.seq != p.seq + 1 {
+                    match bridge_gap(db, &p, record.seq).await? {
+                        Ok((link, bridged)) => {
+                            p = link;
+                            report.archived += bridged;
+                        }
+                        Err(reason) => {
+                            report.broken = Some(broken(reason));
+                            return Ok(report);
+                        }
+                    }
+                }
+                if record.prev_hash != p.hash {
+                    report.broken = Some(broken(format!("prev_hash {} does not match hash {} of seq {}", record.prev_hash, p.hash, p.seq)));
+                    return Ok(report);
+                }
+            }
+
+            let expected = record.compute_hash();
+            if expected != record.hash {
+                report.broken = Some(broken(format!("content hash mismatch (stored {}, computed {})", record.hash, expected)));
+                return Ok(report);
+            }
+
+            report.verified += 1;
+            prev = Some(ChainHead { seq: record.seq, hash: record.hash });
+        }
+    }
+
+    Ok(report)
+}
+
+/// A signed snapshot of the chain head, appended to the local checkpoint file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub seq: i64,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    pub signature: String,
+}
+
+impl Checkpoint {
+    fn signing_payload(seq: i64, hash: &str, created_at: &DateTime<Utc>) -> String {
+        format!("{}|{}|{}", seq, hash, created_at.to_rfc3339_opts(SecondsFormat::Nanos, true))
+    }
+
+    fn sign(key: &str, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        format!("hmac-sha256:{}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    pub fn new(head: &ChainHead, key: &str) -> Self {
+        let created_at = Utc::now();
+        let signature = Self::sign(key, &Self::signing_payload(head.seq, &head.hash, &created_at));
+        Self { seq: head.seq, hash: head.hash.clone(), created_at, signature }
+    }
+
+    pub fn signature_valid(&self, key: &str) -> bool {
+        Self::sign(key, &Self::signing_payload(self.seq, &self.hash, &self.created_at)) == self.signature
+    }
+}
+
+pub fn read_checkpoints(config: &AuditConfig) -> Result<Vec<Checkpoint>> {
+    let content = match std::fs::read_to_string(&config.checkpoint_path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    content.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).map_err(|e| anyhow!("Invalid checkpoint line: {}", e)))
+        .collect()
+}
+
+/// Append a checkpoint for the current head unless it was already checkpointed
+pub async fn write_checkpoint(db: &Database, config: &AuditConfig) -> Result<Option<Checkpoint>> {
+    let key = config.signing_key.as_deref()
+        .ok_or_else(|| anyhow!("AUDIT_SIGNING_KEY is not set; refusing to write checkpoints"))?;
+    let head = load_head(db).await?;
+    if head.seq == 0 {
+        return Ok(None);
+    }
+    if read_checkpoints(config)?.last().is_some_and(|cp| cp.seq == head.seq) {
+        return Ok(None);
+    }
+
+    let checkpoint = Checkpoint::new(&head, key);
+    if let Some(dir) = config.checkpoint_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&config.checkpoint_path)?;
+    writeln!(file, "{}", serde_json::to_string(&checkpoint)?)?;
+    Ok(Some(checkpoint))
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckpointReport {
+    pub checked: u64,
+    pub failures: Vec<String>,
+}
+
+/// Check every checkpoint's signature and that the stored record at its seq still has the checkpointed hash
+pub async fn verify_checkpoints(db: &Database, config: &AuditConfig) -> Result<CheckpointReport> {
+    let mut report = CheckpointReport::default();
+    for cp in read_checkpoints(config)? {
+        report.checked += 1;
+        let Some(key) = config.signing_key.as_deref() else {
+            report.failures.push(format!("seq {}: cannot check the signature, AUDIT_SIGNING_KEY is not set", cp.seq));
+            continue;
+        };
+        if !cp.signature_valid(key) {
+            report.failures.push(format!("seq {}: invalid signature", cp.seq));
+            continue;
+        }
+        let mut result = db.query("
+            SELECT VALUE hash FROM mcp_audit_log WHERE seq = $seq LIMIT 1;
+            SELECT VALUE hash FROM mcp_audit_tombstone WHERE seq = $seq LIMIT 1;
+        ")
+            .bind(("seq", cp.seq))
+            .await?;
+        let mut hashes: Vec<String> = result.take(0)?;
+        hashes.extend(result.take::<Vec<String>>(1)?);
+        match hashes.first() {
+            Some(h) if *h == cp.hash => {}
+            Some(h) => report.failures.push(format!("seq {}: hash changed from {} to {}", cp.seq, cp.hash, h)),
+            None => report.failures.push(format!("seq {}: record and tombstone are both missing", cp.seq)),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(seq: i64, prev_hash: &str) -> ChainedRecord {
+        let mut r = ChainedRecord {
+            id: None,
+            seq,
+            method: None,
+            tool_name: "list-projects".to_string(),
+            project_id: Some("mcp_projects:governance".to_string()),
+            arguments: json!({ "b": 1, "a": { "y": [1, 2], "x": "z" } }),
+            status: "success".to_string(),
+            message: "ok".to_string(),
+            duration_ms: 6,
+            executed_at: Utc::now(),
+            principal: None,
+            session_id: None,
+            client_name: None,
+            client_version: None,
+            client_ip: None,
+            correlation_id: None,
+            prev_hash: prev_hash.to_string(),
+            hash: String::new(),
+            idempotency_key: None,
+        };
+        r.hash = r.compute_hash();
+        r
+    }
+
+    #[test]
+    fn test_canonical_json_sorts_keys() {
+        assert_eq!(canonical_json(&json!({ "b": 1, "a": { "d": null, "c": [true] } })), r#"{"a":{"c":[true],"d":null},"b":1}"#);
+    }
+
+    #[test]
+    fn test_hash_covers_contents_and_link() {
+        let first = record(1, GENESIS_HASH);
+        let second = record(2, &first.hash);
+        assert_eq!(second.prev_hash, first.hash);
+        assert_eq!(first.compute_hash(), first.hash);
+
+        let mut tampered = first.clone();
+        tampered.message = "edited".to_string();
+        assert_ne!(tampered.compute_hash(), first.hash);
+
+        let mut relinked = second.clone();
+        relinked.prev_hash = GENESIS_HASH.to_string();
+        assert_ne!(relinked.compute_hash(), second.hash);
+    }
+
+    #[test]
+    fn test_attribution_hashed_only_when_present() {
+        let legacy = record(1, GENESIS_HASH);
+        assert!(!legacy.canonical().contains("principal"));
+
+        let mut attributed = legacy.clone();
+        attributed.principal = Some("ci-agent".to_string());
+        attributed.correlation_id = Some("req-1".to_string());
+        assert_ne!(attributed.compute_hash(), legacy.hash);
+
+        let mut reassigned = attributed.clone();
+        reassigned.principal = Some("someone-else".to_string());
+        assert_ne!(reassigned.compute_hash(), attributed.compute_hash());
+    }
+
+    #[test]
+    fn test_checkpoint_signature() {
+        let head = ChainHead { seq: 42, hash: "ab".repeat(32) };
+        let cp = Checkpoint::new(&head, "secret");
+        assert!(cp.signature_valid("secret"));
+        assert!(!cp.signature_valid("other"));
+
+        let mut forged = cp.clone();
+        forged.seq = 43;
+        assert!(!forged.signature_valid("secret"));
+    }
+}
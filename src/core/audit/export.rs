@@ -0,0 +1,246 @@
+use crate::core::audit::AuditConfig;
+use crate::core::audit::retention::{self, AFTER_CURSOR, Cursor, RECORD_FIELDS};
+use crate::core::database::Database;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_EXPORT_LIMIT: usize = 1000;
+pub const MAX_EXPORT_LIMIT: usize = 10_000;
+const PAGE_SIZE: usize = 1000;
+
+/// Which audit records `export-audit` returns
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Inclusive lower bound on `executed_at`
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `executed_at`
+    pub to: Option<DateTime<Utc>>,
+    pub tool_name: Option<String>,
+    pub status: Option<String>,
+    pub principal: Option<String>,
+    /// Project record ID (e.g. `mcp_projects:governance`)
+    pub project_id: Option<String>,
+    /// Also read records already moved to archive files
+    pub include_archived: bool,
+    pub limit: usize,
+}
+
+impl ExportFilter {
+    fn matches(&self, row: &Value) -> bool {
+        let field = |name: &str| row.get(name).and_then(|v| v.as_str());
+        let executed_at = retention::parse_time(&row["executed_at"]);
+
+        self.from.is_none_or(|from| executed_at.is_some_and(|t| t >= from))
+            && self.to.is_none_or(|to| executed_at.is_some_and(|t| t < to))
+            && self.tool_name.as_deref().is_none_or(|v| field("tool_name") == Some(v))
+            && self.status.as_deref().is_none_or(|v| field("status") == Some(v))
+            && self.principal.as_deref().is_none_or(|v| field("principal") == Some(v))
+            && self.project_id.as_deref().is_none_or(|v| field("project_id") == Some(v))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Export {
+    /// The records, one JSON object per line, when they fit in `AUDIT_EXPORT_INLINE_BYTES`
+    pub jsonl: Option<String>,
+    /// JSONL file in `AUDIT_EXPORT_DIR` holding the records when they did not
+    pub file: Option<PathBuf>,
+    pub bytes: usize,
+    pub count: usize,
+    /// `executed_at` of the last exported record when more records matched than `limit`
+    pub next_from: Option<String>,
+}
+
+/// Collects export lines in memory until they outgrow the inline limit, then moves them to a file
+struct Sink<'a> {
+    dir: &'a Path,
+    inline_bytes: usize,
+    limit: usize,
+    buffer: String,
+    file: Option<(PathBuf, BufWriter<File>)>,
+    /// `executed_at` of the last record taken
+    last_from: Option<String>,
+    export: Export,
+}
+
+impl<'a> Sink<'a> {
+    fn new(config: &'a AuditConfig, limit: usize) -> Self {
+        Self::with_dir(&config.export_dir, config.export_inline_bytes, limit)
+    }
+
+    fn with_dir(dir: &'a Path, inline_bytes: usize, limit: usize) -> Self {
+        Self {
+            dir,
+            inline_bytes,
+            limit,
+            buffer: String::new(),
+            file: None,
+            last_from: None,
+            export: Export::default(),
+        }
+    }
+
+    /// Whether more records are wanted; the first one past `limit` only marks the export as cut short
+    fn push(&mut self, mut row: Value) -> Result<bool> {
+        if self.export.count == self.limit {
+            self.export.next_from = self.last_from.take();
+            return Ok(false);
+        }
+        // Drop NONE columns so each line only carries what was recorded
+        if let Some(map) = row.as_object_mut() {
+            map.retain(|_, v| !v.is_null());
+        }
+        let line = format!("{}\n", serde_json::to_string(&row)?);
+        if self.file.is_none() && self.buffer.len() + line.len() > self.inline_bytes {
+            std::fs::create_dir_all(self.dir)?;
+            let path = self.dir.join(format!("audit-export-{}.jsonl", uuid::Uuid::new_v4()));
+            let mut writer = BufWriter::new(File::create(&path)?);
+            writer.write_all(std::mem::take(&mut self.buffer).as_bytes())?;
+            self.file = Some((path, writer));
+        }
+        match self.file.as_mut() {
+            Some((_, writer)) => writer.write_all(line.as_bytes())?,
+            None => self.buffer.push_str(&line),
+        }
+        self.export.bytes += line.len();
+        self.export.count += 1;
+        self.last_from = row["executed_at"].as_str().map(String::from);
+        Ok(true)
+    }
+
+    fn finish(mut self) -> Result<Export> {
+        match self.file {
+            Some((path, writer)) => {
+                writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+                self.export.file = Some(path);
+            }
+            None => self.export.jsonl = Some(self.buffer),
+        }
+        Ok(self.export)
+    }
+}
+
+/// Matching records in `executed_at` order, archived ones first, one JSON object per line.
+/// Archives and live records are read a page or a line at a time.
+pub async fn export_jsonl(db: &Database, config: &AuditConfig, filter: &ExportFilter) -> Result<Export> {
+    let mut sink = Sink::new(config, filter.limit.clamp(1, MAX_EXPORT_LIMIT));
+
+    if filter.include_archived {
+        for path in retention::archive_files(&config.archive_dir)? {
+            for row in retention::read_archive(&path)? {
+                let row = row?;
+                if filter.matches(&row) && !sink.push(row)? {
+                    return sink.finish();
+                }
+            }
+        }
+    }
+
+    let mut conditions = Vec::new();
+    if filter.from.is_some() { conditions.push("executed_at >= $from"); }
+    if filter.to.is_some() { conditions.push("executed_at < $to"); }
+    if filter.tool_name.is_some() { conditions.push("tool_name = $tool_name"); }
+    if filter.status.is_some() { conditions.push("status = $status"); }
+    if filter.principal.is_some() { conditions.push("principal = $principal"); }
+    if filter.project_id.is_some() { conditions.push("type::string(project_id) = $project_id"); }
+
+    let mut after: Option<Cursor> = None;
+    loop {
+        let mut page_conditions = conditions.clone();
+        if after.is_some() { page_conditions.push(AFTER_CURSOR); }
+        let where_clause = if page_conditions.is_empty() { String::new() } else { format!("WHERE {}", page_conditions.join(" AND ")) };
+        let sql = format!("SELECT {} FROM mcp_audit_log {} ORDER BY executed_at ASC, id ASC LIMIT $limit", RECORD_FIELDS, where_clause);
+        let mut query = db.query(sql).bind(("limit", PAGE_SIZE as i64));
+        if let Some(from) = filter.from { query = query.bind(("from", surrealdb::sql::Datetime::from(from))); }
+        if let Some(to) = filter.to { query = query.bind(("to", surrealdb::sql::Datetime::from(to))); }
+        if let Some(v) = &filter.tool_name { query = query.bind(("tool_name", v.clone())); }
+        if let Some(v) = &filter.status { query = query.bind(("status", v.clone())); }
+        if let Some(v) = &filter.principal { query = query.bind(("principal", v.clone())); }
+        if let Some(v) = &filter.project_id { query = query.bind(("project_id", v.clone())); }
+        if let Some(cursor) = &after {
+            query = query
+                .bind(("after", surrealdb::sql::Datetime::from(cursor.executed_at)))
+                .bind(("after_id", cursor.id.clone()));
+        }
+        let mut result = query.await?;
+        let page: Vec<Value> = result.take(0)?;
+        let full = page.len() == PAGE_SIZE;
+        for row in page {
+            after = Some(Cursor::of(&row)?);
+            if !sink.push(row)? {
+                return sink.finish();
+            }
+        }
+        if !full {
+            return sink.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_matches_archived_rows() {
+        let row = json!({
+            "tool_name": "update-document",
+            "status": "success",
+            "principal": "ci-agent",
+            "project_id": "mcp_projects:governance",
+            "executed_at": "2026-03-01T12:00:00.5Z"
+        });
+
+        let mut filter = ExportFilter {
+            from: Some(Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap()),
+            to: Some(Utc.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap()),
+            principal: Some("ci-agent".to_string()),
+            project_id: Some("mcp_projects:governance".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&row));
+
+        filter.status = Some("error".to_string());
+        assert!(!filter.matches(&row));
+
+        filter.status = None;
+        filter.to = Some(Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap());
+        assert!(!filter.matches(&row));
+    }
+
+    #[test]
+    fn test_sink_moves_large_exports_to_a_file() {
+        let row = |n: u32| json!({ "id": format!("mcp_audit_log:{}", n), "executed_at": format!("2026-03-01T12:00:0{}Z", n), "principal": null });
+
+        let mut sink = Sink::with_dir(Path::new("unused"), 1024, 2);
+        assert!(sink.push(row(1)).unwrap());
+        assert!(sink.push(row(2)).unwrap());
+        assert!(!sink.push(row(3)).unwrap());
+        let export = sink.finish().unwrap();
+        assert_eq!(export.count, 2);
+        assert_eq!(export.next_from.as_deref(), Some("2026-03-01T12:00:02Z"));
+        let jsonl = export.jsonl.unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(!jsonl.contains("principal"));
+
+        let dir = std::env::temp_dir().join(format!("kyx-audit-export-{}", uuid::Uuid::new_v4()));
+        let mut sink = Sink::with_dir(&dir, 80, 10);
+        for n in 1..=3 {
+            assert!(sink.push(row(n)).unwrap());
+        }
+        let export = sink.finish().unwrap();
+        assert!(export.jsonl.is_none());
+        assert_eq!(export.next_from, None);
+        let written = std::fs::read_to_string(export.file.unwrap()).unwrap();
+        assert_eq!(written.lines().count(), 3);
+        assert_eq!(written.len(), export.bytes);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
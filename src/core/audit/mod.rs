@@ -0,0 +1,228 @@
+use crate::core::database::Database;
+use crate::core::metrics;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+pub mod chain;
+pub mod export;
+pub mod retention;
+pub mod stats;
+mod writer;
+
+use writer::{AuditWriter, WriterMessage};
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What `AuditLog::record` does when the writer queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /// Drop the record (counted in `mcp_audit_dropped_total`) so requests never wait
+    Drop,
+    /// Wait for room in the queue so no record is lost
+    Block,
+}
+
+impl OverflowPolicy {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "drop" => Some(Self::Drop),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditConfig {
+    /// Arguments larger than this are stored as a truncated preview
+    pub max_argument_bytes: usize,
+    /// Key used to sign chain checkpoints (HMAC-SHA256); without one no checkpoints are written
+    pub signing_key: Option<String>,
+    /// JSON Lines file that signed checkpoints are appended to
+    pub checkpoint_path: PathBuf,
+    /// Interval between checkpoints; 0 disables them
+    pub checkpoint_secs: u64,
+    /// Records buffered between request handlers and the writer
+    pub queue_capacity: usize,
+    /// Maximum records written per transaction
+    pub batch_size: usize,
+    /// Partial batches are written at least this often
+    pub flush_interval_ms: u64,
+    pub overflow: OverflowPolicy,
+    /// JSON Lines file holding records that could not be written while SurrealDB was unavailable
+    pub spool_path: PathBuf,
+    /// Directory for gzip-compressed JSONL archives of expired records
+    pub archive_dir: PathBuf,
+    /// Interval between retention runs; 0 disables them
+    pub retention_secs: u64,
+    /// Directory for `export-audit` results too large to return inline
+    pub export_dir: PathBuf,
+    /// Largest `export-audit` result returned inline
+    pub export_inline_bytes: usize,
+    /// Longest range `audit-stats` aggregates in one call
+    pub stats_max_days: i64,
+}
+
+/// Who made the request and over which connection
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub principal: Option<String>,
+    pub session_id: Option<String>,
+    pub client_name: Option<String>,
+    pub client_version: Option<String>,
+    pub client_ip: Option<String>,
+    /// Correlates audit records with server logs and the HTTP response
+    pub correlation_id: Option<String>,
+}
+
+/// A tool execution or resource read to be recorded in `mcp_audit_log`
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// MCP method: `tools/call`, `tools/list` or `resources/read`
+    pub method: String,
+    /// Tool name, or the method itself for non-tool requests
+    pub tool_name: String,
+    /// Project owning the tool definition, preferred over the `project` argument
+    pub tool_project_id: Option<Value>,
+    pub arguments: Value,
+    pub status: String,
+    pub message: String,
+    pub duration_ms: i64,
+    pub context: AuditContext,
+}
+
+/// Writes redacted, hash-chained records to `mcp_audit_log` through a background writer
+pub struct AuditLog {
+    db: Database,
+    config: AuditConfig,
+    sender: mpsc::Sender<WriterMessage>,
+}
+
+impl AuditLog {
+    /// Create the audit log and start its writer task (requires a running Tokio runtime)
+    pub fn new(db: Database, config: AuditConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        AuditWriter::spawn(db.clone(), config.clone(), receiver);
+        Self { db, config, sender }
+    }
+
+    pub fn config(&self) -> &AuditConfig {
+        &self.config
+    }
+
+    pub fn db(&self) -> &Database {
+        &self.db
+    }
+
+    /// Queue one entry for the writer. Never fails; when the queue is full the entry is
+    /// dropped or the caller waits, depending on `AUDIT_OVERFLOW_POLICY`.
+    pub async fn record(&self, entry: AuditEntry) {
+        let tool_name = entry.tool_name.clone();
+        let message = WriterMessage::Record(Box::new(entry), Utc::now());
+        let queued = match self.config.overflow {
+            OverflowPolicy::Block => self.sender.send(message).await.is_ok(),
+            OverflowPolicy::Drop => match self.sender.try_send(message) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    metrics::AUDIT_DROPPED_TOTAL.inc();
+                    log::warn!("⚠️ Audit queue full, record for {} dropped", tool_name);
+                    return;
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            },
+        };
+        if queued {
+            metrics::AUDIT_QUEUE_DEPTH.inc();
+        } else {
+            log::error!("🔥 Audit writer stopped, record for {} lost", tool_name);
+        }
+    }
+
+    /// Wait until everything queued so far has been written (or spooled)
+    pub async fn flush(&self) {
+        let (ack, done) = oneshot::channel();
+        if self.sender.send(WriterMessage::Flush(ack)).await.is_ok() {
+            let _ = done.await;
+        }
+    }
+
+    /// Flush pending records and stop the writer; called on graceful shutdown
+    pub async fn shutdown(&self) {
+        let (ack, done) = oneshot::channel();
+        if self.sender.send(WriterMessage::Shutdown(ack)).await.is_err() {
+            return;
+        }
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, done).await {
+            Ok(_) => log::info!("📝 Audit writer flushed and stopped"),
+            Err(_) => log::warn!("⚠️ Audit writer did not finish flushing within {:?}", SHUTDOWN_TIMEOUT),
+        }
+    }
+
+    /// Verify the whole chain once in the background and report the result in the logs
+    pub fn spawn_startup_verification(self: &Arc<Self>) {
+        let audit = Arc::clone(self);
+        tokio::spawn(async move {
+            match chain::verify_chain(&audit.db, 0).await {
+                Ok(report) if report.broken.is_none() => {
+                    log::info!("🔗 Audit chain verified: {} records intact", report.verified);
+                }
+                Ok(report) => {
+                    if let Some(broken) = report.broken {
+                        log::error!("🚨 Audit chain BROKEN at seq {} ({}): {}", broken.seq, broken.id, broken.reason);
+                    }
+                }
+                Err(e) => log::warn!("⚠️ Audit chain verification failed to run: {}", e),
+            }
+        });
+    }
+
+    /// Periodically archive and delete records past their retention period
+    pub fn spawn_retention(self: &Arc<Self>) {
+        if self.config.retention_secs == 0 {
+            return;
+        }
+        let audit = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(audit.config.retention_secs));
+            loop {
+                interval.tick().await;
+                match retention::run_retention(&audit.db, &audit.config).await {
+                    Ok(report) => match report.archive {
+                        Some(path) => log::info!("🗄️ Archived {} expired audit records to {}", report.archived, path.display()),
+                        None => log::debug!("🗄️ Audit retention: nothing expired"),
+                    },
+                    Err(e) => log::error!("🔥 Audit retention run failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Periodically append a signed checkpoint of the chain head to the checkpoint file
+    pub fn spawn_checkpoints(self: &Arc<Self>) {
+        if self.config.checkpoint_secs == 0 {
+            return;
+        }
+        if self.config.signing_key.is_none() {
+            log::error!("🔥 AUDIT_SIGNING_KEY is not set: audit checkpoints are disabled");
+            return;
+        }
+        let audit = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(audit.config.checkpoint_secs));
+            loop {
+                interval.tick().await;
+                match chain::write_checkpoint(&audit.db, &audit.config).await {
+                    Ok(Some(cp)) => log::info!("🔏 Audit checkpoint written at seq {}", cp.seq),
+                    Ok(None) => log::debug!("🔏 Audit checkpoint skipped: chain is empty"),
+                    Err(e) => log::error!("🔥 Failed to write audit checkpoint: {}", e),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,311 @@
+use crate::core::audit::AuditConfig;
+use crate::core::database::Database;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Columns of an archived or exported audit record (record links and datetimes as strings)
+pub const RECORD_FIELDS: &str = "
+    type::string(id) AS id, seq, method, tool_name,
+    (IF project_id != NONE THEN type::string(project_id) END) AS project_id,
+    arguments, status, message, duration_ms, type::string(executed_at) AS executed_at,
+    principal, session_id, client_name, client_version, client_ip, correlation_id, prev_hash, hash
+";
+
+const PAGE_SIZE: i64 = 1000;
+const DELETE_CHUNK: usize = 500;
+
+/// Rows after `$after` / `$after_id` in `(executed_at, id)` order. A batch shares one
+/// timestamp, so paging on `executed_at` alone would skip the rest of it
+pub const AFTER_CURSOR: &str = "(executed_at > $after OR (executed_at = $after AND type::string(id) > $after_id))";
+
+/// Where the previous page ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub executed_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn of(row: &Value) -> Result<Self> {
+        Ok(Self {
+            executed_at: parse_time(&row["executed_at"]).ok_or_else(|| anyhow!("audit record without executed_at"))?,
+            id: row["id"].as_str().ok_or_else(|| anyhow!("audit record without id"))?.to_string(),
+        })
+    }
+}
+
+/// How long audit records are kept, from `mcp_audit_retention`.
+/// Unset `tool_name`/`status` match anything; the most specific matching rule wins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionRule {
+    pub tool_name: Option<String>,
+    pub status: Option<String>,
+    pub keep_days: i64,
+}
+
+impl RetentionRule {
+    fn matches(&self, tool_name: &str, status: &str) -> bool {
+        self.tool_name.as_deref().is_none_or(|t| t == tool_name)
+            && self.status.as_deref().is_none_or(|s| s == status)
+    }
+
+    /// Tool rules beat status rules, and rules naming both beat either
+    fn specificity(&self) -> u8 {
+        (self.tool_name.is_some() as u8) * 2 + self.status.is_some() as u8
+    }
+}
+
+/// Days to keep a record; `None` when no rule applies (kept forever)
+pub fn keep_days(rules: &[RetentionRule], tool_name: &str, status: &str) -> Option<i64> {
+    rules.iter()
+        .filter(|r| r.matches(tool_name, status))
+        .max_by_key(|r| r.specificity())
+        .map(|r| r.keep_days)
+}
+
+pub async fn load_rules(db: &Database) -> Result<Vec<RetentionRule>> {
+    let mut result = db.query("SELECT tool_name, status, keep_days FROM mcp_audit_retention WHERE active = true").await?;
+    let rules: Vec<RetentionRule> = result.take(0)?;
+    Ok(rules)
+}
+
+/// An archived record's chain link, kept in `mcp_audit_tombstone` so verification can bridge the gap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Expired {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seq: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct RetentionReport {
+    pub archived: usize,
+    pub archive: Option<PathBuf>,
+}
+
+pub fn parse_time(value: &Value) -> Option<DateTime<Utc>> {
+    value.as_str()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Records already written to an archive but not yet deleted, kept in `mcp_audit_pending_delete`
+#[derive(Debug, Deserialize)]
+struct PendingDelete {
+    id: String,
+    archive: String,
+    expired: Vec<Expired>,
+}
+
+/// Delete archived records and leave their tombstones. Records already gone (a chunk that
+/// committed before a failure) are skipped, so a retry can run over the whole list again.
+async fn delete_archived(db: &Database, archive: &str, expired: &[Expired]) -> Result<()> {
+    for chunk in expired.chunks(DELETE_CHUNK) {
+        db.query("
+            BEGIN TRANSACTION;
+            FOR $e IN $expired {
+                IF record::exists(type::thing($e.id)) {
+                    IF $e.seq != NONE {
+                        CREATE mcp_audit_tombstone SET seq = $e.seq, prev_hash = $e.prev_hash, hash = $e.hash, archive = $archive, archived_at = time::now();
+                    };
+                    DELETE type::thing($e.id);
+                };
+            };
+            COMMIT TRANSACTION;
+        ")
+        .bind(("expired", chunk.to_vec()))
+        .bind(("archive", archive.to_string()))
+        .await?
+        .check()?;
+    }
+    Ok(())
+}
+
+/// Finish deletions a previous run left behind, so their records are not archived a second time
+async fn finish_pending(db: &Database) -> Result<()> {
+    let mut result = db.query("SELECT type::string(id) AS id, archive, expired FROM mcp_audit_pending_delete").await?;
+    let pending: Vec<PendingDelete> = result.take(0)?;
+    for run in pending {
+        log::warn!("⚠️ Finishing deletion of {} audit records archived to {}", run.expired.len(), run.archive);
+        delete_archived(db, &run.archive, &run.expired).await?;
+        db.query("DELETE type::thing($id)").bind(("id", run.id)).await?.check()?;
+    }
+    Ok(())
+}
+
+/// Archive every expired record to one gzip-compressed JSONL file, then delete them.
+/// Nothing is deleted unless the archive was completely written and synced.
+pub async fn run_retention(db: &Database, config: &AuditConfig) -> Result<RetentionReport> {
+    finish_pending(db).await?;
+    let rules = load_rules(db).await?;
+    let Some(min_days) = rules.iter().map(|r| r.keep_days).min() else {
+        return Ok(RetentionReport::default());
+    };
+
+    let now = Utc::now();
+    let cutoff = now - Duration::days(min_days);
+    // Runs in the same second (after a restart, or replicas sharing the directory) get their own file
+    let path = config.archive_dir.join(format!("audit-{}-{}.jsonl.gz", now.format("%Y%m%dT%H%M%SZ"), uuid::Uuid::new_v4().simple()));
+    let mut encoder: Option<GzEncoder<std::fs::File>> = None;
+    let mut expired = Vec::new();
+    let mut after: Option<Cursor> = None;
+
+    loop {
+        let resume = if after.is_some() { format!("AND {}", AFTER_CURSOR) } else { String::new() };
+        let query = format!(
+            "SELECT {} FROM mcp_audit_log WHERE executed_at < $cutoff {} ORDER BY executed_at ASC, id ASC LIMIT $limit",
+            RECORD_FIELDS, resume
+        );
+        let mut query = db.query(query)
+            .bind(("cutoff", surrealdb::sql::Datetime::from(cutoff)))
+            .bind(("limit", PAGE_SIZE));
+        if let Some(after) = &after {
+            query = query
+                .bind(("after", surrealdb::sql::Datetime::from(after.executed_at)))
+                .bind(("after_id", after.id.clone()));
+        }
+        let mut result = query.await?;
+        let page: Vec<Value> = result.take(0)?;
+        if page.is_empty() {
+            break;
+        }
+
+        for row in &page {
+            let cursor = Cursor::of(row)?;
+            let executed_at = cursor.executed_at;
+            after = Some(cursor);
+
+            let tool_name = row["tool_name"].as_str().unwrap_or_default();
+            let status = row["status"].as_str().unwrap_or_default();
+            let Some(days) = keep_days(&rules, tool_name, status) else { continue };
+            if executed_at >= now - Duration::days(days) {
+                continue;
+            }
+
+            if encoder.is_none() {
+                std::fs::create_dir_all(&config.archive_dir)?;
+                let file = std::fs::OpenOptions::new().write(true).create_new(true).open(&path)?;
+                encoder = Some(GzEncoder::new(file, Compression::default()));
+            }
+            if let Some(enc) = encoder.as_mut() {
+                writeln!(enc, "{}", serde_json::to_string(row)?)?;
+            }
+            expired.push(Expired {
+                id: row["id"].as_str().unwrap_or_default().to_string(),
+                seq: row["seq"].as_i64(),
+                prev_hash: row["prev_hash"].as_str().map(|s| s.to_string()),
+                hash: row["hash"].as_str().map(|s| s.to_string()),
+            });
+        }
+    }
+
+    let Some(encoder) = encoder else {
+        return Ok(RetentionReport::default());
+    };
+    encoder.finish()?.sync_all()?;
+
+    // Journal the archived records first: if a delete fails midway, the next run finishes the
+    // list instead of archiving what is left into another file
+    let archive_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let mut result = db.query("CREATE mcp_audit_pending_delete SET archive = $archive, expired = $expired RETURN VALUE type::string(id)")
+        .bind(("archive", archive_name.clone()))
+        .bind(("expired", expired.clone()))
+        .await?;
+    let pending: Option<String> = result.take(0)?;
+    delete_archived(db, &archive_name, &expired).await?;
+    if let Some(id) = pending {
+        db.query("DELETE type::thing($id)").bind(("id", id)).await?.check()?;
+    }
+
+    Ok(RetentionReport { archived: expired.len(), archive: Some(path) })
+}
+
+/// Archive files in the order they were written
+pub fn archive_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.to_string_lossy().ends_with(".jsonl.gz"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Records of an archive, decompressed one line at a time
+pub fn read_archive(path: &Path) -> Result<impl Iterator<Item = Result<Value>>> {
+    let reader = BufReader::new(GzDecoder::new(std::fs::File::open(path)?));
+    Ok(reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(serde_json::from_str(&line).map_err(Into::into)),
+        Err(e) => Some(Err(e.into())),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(tool_name: Option<&str>, status: Option<&str>, keep_days: i64) -> RetentionRule {
+        RetentionRule { tool_name: tool_name.map(String::from), status: status.map(String::from), keep_days }
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let rules = vec![
+            rule(None, Some("error"), 365),
+            rule(None, Some("success"), 30),
+            rule(Some("update-document"), None, 730),
+            rule(Some("update-document"), Some("error"), 1825),
+        ];
+        assert_eq!(keep_days(&rules, "list-projects", "success"), Some(30));
+        assert_eq!(keep_days(&rules, "list-projects", "error"), Some(365));
+        assert_eq!(keep_days(&rules, "update-document", "success"), Some(730));
+        assert_eq!(keep_days(&rules, "update-document", "error"), Some(1825));
+        assert_eq!(keep_days(&rules[..1], "list-projects", "success"), None);
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("kyx-audit-archive-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit-20260101T000000Z.jsonl.gz");
+
+        let mut enc = GzEncoder::new(std::fs::File::create(&path).unwrap(), Compression::default());
+        writeln!(enc, "{}", json!({ "id": "mcp_audit_log:a", "tool_name": "x" })).unwrap();
+        writeln!(enc, "{}", json!({ "id": "mcp_audit_log:b", "tool_name": "y" })).unwrap();
+        enc.finish().unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        assert_eq!(archive_files(&dir).unwrap(), vec![path.clone()]);
+        let rows: Vec<Value> = read_archive(&path).unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["tool_name"], "y");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cursor_of_row() {
+        let row = json!({ "id": "mcp_audit_log:b", "executed_at": "2026-03-01T12:00:00Z" });
+        let cursor = Cursor::of(&row).unwrap();
+        assert_eq!(cursor.id, "mcp_audit_log:b");
+        assert_eq!(cursor.executed_at, parse_time(&json!("2026-03-01T12:00:00Z")).unwrap());
+        assert!(Cursor::of(&json!({ "executed_at": "2026-03-01T12:00:00Z" })).is_err());
+    }
+}
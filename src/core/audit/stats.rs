@@ -0,0 +1,288 @@
+use crate::core::database::Database;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dimension {
+    Tool,
+    Project,
+    Principal,
+    Status,
+}
+
+impl Dimension {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "tool" | "tool_name" => Some(Self::Tool),
+            "project" => Some(Self::Project),
+            "principal" => Some(Self::Principal),
+            "status" => Some(Self::Status),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tool => "tool",
+            Self::Project => "project",
+            Self::Principal => "principal",
+            Self::Status => "status",
+        }
+    }
+
+    /// Projection grouped on, aliased to `as_str()`
+    fn field(&self) -> &'static str {
+        match self {
+            Self::Tool => "tool_name AS tool",
+            Self::Project => "(IF project_id != NONE THEN project_id.name END) AS project",
+            Self::Principal => "principal AS principal",
+            Self::Status => "status AS status",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Hour,
+    Day,
+    /// ISO week, starting Monday 00:00 UTC
+    Week,
+}
+
+impl Bucket {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+
+    /// Start of the bucket containing `executed_at`; weeks are shifted off the epoch's Thursday onto Monday
+    fn floor_expr(&self) -> &'static str {
+        match self {
+            Self::Hour => "time::floor(executed_at, 1h)",
+            Self::Day => "time::floor(executed_at, 1d)",
+            Self::Week => "time::floor(executed_at - 4d, 1w) + 4d",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatsQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub group_by: Vec<Dimension>,
+    pub bucket: Option<Bucket>,
+    pub tool_name: Option<String>,
+    pub principal: Option<String>,
+    /// Project name
+    pub project: Option<String>,
+}
+
+/// Aggregates for one combination of bucket and dimension values
+#[derive(Debug, Clone)]
+pub struct GroupStats {
+    pub bucket: Option<DateTime<Utc>>,
+    /// Dimension values in `group_by` order; `None` where the record had no value (e.g. no project)
+    pub keys: Vec<Option<String>>,
+    pub calls: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub p50_ms: i64,
+    pub p95_ms: i64,
+    pub p99_ms: i64,
+}
+
+/// One `GROUP BY` row as returned by SurrealDB
+fn group_stats(row: &Value, query: &StatsQuery) -> Result<GroupStats> {
+    let bucket = match row.get("bucket").and_then(Value::as_str) {
+        Some(raw) => Some(DateTime::parse_from_rfc3339(raw)?.with_timezone(&Utc)),
+        None => None,
+    };
+    let keys = query.group_by.iter()
+        .map(|d| row.get(d.as_str()).and_then(Value::as_str).map(String::from))
+        .collect();
+    let calls = row["calls"].as_u64().unwrap_or_default();
+    let errors = row["errors"].as_u64().unwrap_or_default();
+    let millis = |key: &str| row[key].as_f64().unwrap_or_default().round() as i64;
+    Ok(GroupStats {
+        bucket,
+        keys,
+        calls,
+        errors,
+        error_rate: if calls == 0 { 0.0 } else { errors as f64 / calls as f64 },
+        p50_ms: millis("p50_ms"),
+        p95_ms: millis("p95_ms"),
+        p99_ms: millis("p99_ms"),
+    })
+}
+
+/// Counts and percentiles are computed by SurrealDB per group; ranges longer than
+/// `max_days` are rejected so one call cannot aggregate the whole log
+pub async fn audit_stats(db: &Database, query: &StatsQuery, max_days: i64) -> Result<Vec<GroupStats>> {
+    if query.from >= query.to {
+        return Err(anyhow!("'from' must be before 'to'"));
+    }
+    if query.to - query.from > Duration::days(max_days) {
+        return Err(anyhow!("Range is longer than {} days (AUDIT_STATS_MAX_DAYS); narrow 'from' / 'to'", max_days));
+    }
+
+    let mut conditions = vec!["executed_at >= $from", "executed_at < $to"];
+    if query.tool_name.is_some() { conditions.push("tool_name = $tool_name"); }
+    if query.principal.is_some() { conditions.push("principal = $principal"); }
+    if query.project.is_some() { conditions.push("project_id.name = $project"); }
+
+    let mut fields: Vec<String> = Vec::new();
+    let mut groups: Vec<&str> = Vec::new();
+    if let Some(bucket) = query.bucket {
+        fields.push(format!("type::string({}) AS bucket", bucket.floor_expr()));
+        groups.push("bucket");
+    }
+    for dimension in &query.group_by {
+        fields.push(dimension.field().to_string());
+        groups.push(dimension.as_str());
+    }
+    let sql = format!("
+        SELECT {}count() AS calls, count(status = 'error') AS errors,
+            math::percentile(duration_ms, 50) AS p50_ms, math::percentile(duration_ms, 95) AS p95_ms,
+            math::percentile(duration_ms, 99) AS p99_ms
+        FROM mcp_audit_log WHERE {} {}
+    ",
+        fields.iter().map(|f| format!("{}, ", f)).collect::<String>(),
+        conditions.join(" AND "),
+        if groups.is_empty() { "GROUP ALL".to_string() } else { format!("GROUP BY {}", groups.join(", ")) },
+    );
+
+    let mut q = db.query(sql)
+        .bind(("from", surrealdb::sql::Datetime::from(query.from)))
+        .bind(("to", surrealdb::sql::Datetime::from(query.to)));
+    if let Some(v) = &query.tool_name { q = q.bind(("tool_name", v.clone())); }
+    if let Some(v) = &query.principal { q = q.bind(("principal", v.clone())); }
+    if let Some(v) = &query.project { q = q.bind(("project", v.clone())); }
+    let mut result = q.await?;
+    let rows: Vec<Value> = result.take(0)?;
+
+    let mut stats = rows.iter().map(|row| group_stats(row, query)).collect::<Result<Vec<_>>>()?;
+    // Chronological, busiest groups first within a bucket
+    stats.sort_by(|a, b| a.bucket.cmp(&b.bucket).then(b.calls.cmp(&a.calls)).then(a.keys.cmp(&b.keys)));
+    Ok(stats)
+}
+
+fn bucket_label(bucket: Bucket, start: DateTime<Utc>) -> String {
+    match bucket {
+        Bucket::Hour => start.format("%Y-%m-%d %H:00").to_string(),
+        Bucket::Day => start.format("%Y-%m-%d").to_string(),
+        Bucket::Week => format!("{} (W{:02})", start.format("%Y-%m-%d"), start.iso_week().week()),
+    }
+}
+
+pub fn to_json(query: &StatsQuery, stats: &[GroupStats]) -> Value {
+    let groups: Vec<Value> = stats.iter().map(|g| {
+        let mut obj = Map::new();
+        if let Some(bucket) = g.bucket {
+            obj.insert("bucket".to_string(), json!(bucket.to_rfc3339()));
+        }
+        for (dimension, key) in query.group_by.iter().zip(&g.keys) {
+            obj.insert(dimension.as_str().to_string(), json!(key));
+        }
+        obj.insert("calls".to_string(), json!(g.calls));
+        obj.insert("errors".to_string(), json!(g.errors));
+        obj.insert("error_rate".to_string(), json!((g.error_rate * 10_000.0).round() / 10_000.0));
+        obj.insert("p50_ms".to_string(), json!(g.p50_ms));
+        obj.insert("p95_ms".to_string(), json!(g.p95_ms));
+        obj.insert("p99_ms".to_string(), json!(g.p99_ms));
+        Value::Object(obj)
+    }).collect();
+
+    json!({
+        "from": query.from.to_rfc3339(),
+        "to": query.to.to_rfc3339(),
+        "group_by": query.group_by,
+        "bucket": query.bucket,
+        "groups": groups,
+    })
+}
+
+pub fn to_markdown(query: &StatsQuery, stats: &[GroupStats]) -> String {
+    let mut output = format!("### Audit Stats ({} → {})\n\n", query.from.to_rfc3339(), query.to.to_rfc3339());
+    if stats.is_empty() {
+        output.push_str("No audit records in this range.\n");
+        return output;
+    }
+
+    let mut headers: Vec<&str> = Vec::new();
+    if query.bucket.is_some() {
+        headers.push("Bucket");
+    }
+    headers.extend(query.group_by.iter().map(|d| d.as_str()));
+    headers.extend(["Calls", "Errors", "Error Rate", "p50 (ms)", "p95 (ms)", "p99 (ms)"]);
+    output.push_str(&format!("| {} |\n", headers.join(" | ")));
+    output.push_str(&format!("|{}\n", "---|".repeat(headers.len())));
+
+    for g in stats {
+        let mut cells: Vec<String> = Vec::new();
+        if let (Some(bucket), Some(start)) = (query.bucket, g.bucket) {
+            cells.push(bucket_label(bucket, start));
+        }
+        cells.extend(g.keys.iter().map(|k| k.clone().unwrap_or_else(|| "-".to_string())));
+        cells.push(g.calls.to_string());
+        cells.push(g.errors.to_string());
+        cells.push(format!("{:.1}%", g.error_rate * 100.0));
+        cells.push(g.p50_ms.to_string());
+        cells.push(g.p95_ms.to_string());
+        cells.push(g.p99_ms.to_string());
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_group_stats_by_principal_and_day() {
+        let day1 = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap();
+        let query = StatsQuery {
+            from: day1,
+            to: day1 + Duration::days(2),
+            group_by: vec![Dimension::Principal],
+            bucket: Some(Bucket::Day),
+            tool_name: None,
+            principal: None,
+            project: None,
+        };
+        // As SurrealDB returns them: unordered, percentiles as floats, NONE keys left out
+        let rows = [
+            json!({ "bucket": "2026-10-02T00:00:00Z", "principal": "ci-agent", "calls": 1, "errors": 0, "p50_ms": 40.0, "p95_ms": 40.0, "p99_ms": 40.0 }),
+            json!({ "bucket": "2026-10-01T00:00:00Z", "calls": 1, "errors": 0, "p50_ms": 5.0, "p95_ms": 5.0, "p99_ms": 5.0 }),
+            json!({ "bucket": "2026-10-01T00:00:00Z", "principal": "ci-agent", "calls": 3, "errors": 1, "p50_ms": 20.0, "p95_ms": 29.0, "p99_ms": 29.8 }),
+        ];
+
+        let mut stats: Vec<GroupStats> = rows.iter().map(|r| group_stats(r, &query).unwrap()).collect();
+        stats.sort_by(|a, b| a.bucket.cmp(&b.bucket).then(b.calls.cmp(&a.calls)).then(a.keys.cmp(&b.keys)));
+
+        let first = &stats[0];
+        assert_eq!(first.bucket, Some(day1));
+        assert_eq!(first.keys, vec![Some("ci-agent".to_string())]);
+        assert_eq!(first.calls, 3);
+        assert_eq!(first.errors, 1);
+        assert!((first.error_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!((first.p50_ms, first.p95_ms, first.p99_ms), (20, 29, 30));
+
+        assert_eq!(stats[1].keys, vec![None]);
+        assert_eq!(stats[2].bucket, Some(Utc.with_ymd_and_hms(2026, 10, 2, 0, 0, 0).unwrap()));
+
+        let markdown = to_markdown(&query, &stats);
+        assert!(markdown.contains("| Bucket | principal | Calls |"));
+        assert!(markdown.contains("| 2026-10-01 | ci-agent | 3 | 1 | 33.3% | 20 | 29 | 30 |"));
+        assert_eq!(to_json(&query, &stats)["groups"][1]["principal"], Value::Null);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::core::audit::chain::{self, ChainHead, ChainedRecord};
+use crate::core::audit::{AuditConfig, AuditEntry};
+use crate::core::database::Database;
+use crate::core::metrics;
+use crate::core::redaction::{self, RedactionRule};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+
+/// How long redaction rules are cached before `mcp_tools` is read again
+const RULES_TTL: Duration = Duration::from_secs(60);
+
+/// Seals are assigned at write time, so one statement inserts a whole batch atomically
+const INSERT_BATCH: &str = "
+    BEGIN TRANSACTION;
+    FOR $r IN $records {
+        CREATE mcp_audit_log SET
+            seq = $r.seq, method = $r.method, tool_name = $r.tool_name,
+            project_id = IF $r.project_tb != NONE THEN type::thing($r.project_tb, $r.project_key) END,
+            arguments = $r.arguments, status = $r.status, message = $r.message, duration_ms = $r.duration_ms,
+            executed_at = <datetime> $r.executed_at,
+            principal = $r.principal, session_id = $r.session_id, client_name = $r.client_name,
+            client_version = $r.client_version, client_ip = $r.client_ip, correlation_id = $r.correlation_id,
+            prev_hash = $r.prev_hash, hash = $r.hash, idempotency_key = $r.idempotency_key;
+    };
+    COMMIT TRANSACTION;
+";
+
+pub(super) enum WriterMessage {
+    Record(Box<AuditEntry>, DateTime<Utc>),
+    Flush(oneshot::Sender<()>),
+    Shutdown(oneshot::Sender<()>),
+}
+
+/// A record waiting for the next flush, keyed before any write is attempted
+struct Queued {
+    entry: AuditEntry,
+    executed_at: DateTime<Utc>,
+    key: String,
+}
+
+/// Where a record's project comes from
+#[derive(Debug, PartialEq)]
+enum ProjectRef {
+    Id(String),
+    /// Looked up in `mcp_projects`, once per batch
+    Name(String),
+}
+
+/// The tool's own project, else `project` / `project_id` from the arguments (a record ID or a name)
+fn project_ref(tool_project_id: Option<&Value>, arguments: &Value) -> Option<ProjectRef> {
+    if let Some(pid_val) = tool_project_id {
+        if let Some(s) = pid_val.as_str() {
+            if let Ok(thing) = surrealdb::sql::thing(s) {
+                return Some(ProjectRef::Id(thing.to_string()));
+            }
+        } else if let Ok(thing) = serde_json::from_value::<surrealdb::sql::Thing>(pid_val.clone()) {
+            return Some(ProjectRef::Id(thing.to_string()));
+        }
+    }
+
+    let s = arguments.get("project").or_else(|| arguments.get("project_id"))?.as_str()?;
+    match surrealdb::sql::thing(s) {
+        Ok(thing) => Some(ProjectRef::Id(thing.to_string())),
+        Err(_) => Some(ProjectRef::Name(s.to_string())),
+    }
+}
+
+/// Correlation ID plus a sequence number unique to this writer run; correlation IDs may be
+/// caller-supplied and repeat, so the run ID keeps keys from colliding across restarts
+fn idempotency_key(correlation_id: Option<&str>, run: &str, n: u64) -> String {
+    format!("{}:{}.{}", correlation_id.unwrap_or("-"), run, n)
+}
+
+/// Background task that redacts, batches, chains and persists audit records.
+/// Being the only writer, it owns the chain head without any locking.
+pub(super) struct AuditWriter {
+    db: Database,
+    config: AuditConfig,
+    head: Option<ChainHead>,
+    buffer: Vec<Queued>,
+    spool_pending: bool,
+    run: String,
+    next_key: u64,
+    /// Redaction rules per tool name, and when they were read
+    rules: Option<(Instant, HashMap<String, Vec<RedactionRule>>)>,
+}
+
+impl AuditWriter {
+    pub(super) fn spawn(db: Database, config: AuditConfig, receiver: mpsc::Receiver<WriterMessage>) {
+        let spool_pending = std::fs::metadata(&config.spool_path).is_ok_and(|m| m.len() > 0);
+        if spool_pending {
+            log::warn!("📼 Audit spool {} has records awaiting replay", config.spool_path.display());
+        }
+        let writer = Self {
+            db,
+            config,
+            head: None,
+            buffer: Vec::new(),
+            spool_pending,
+            run: uuid::Uuid::new_v4().simple().to_string(),
+            next_key: 0,
+            rules: None,
+        };
+        tokio::spawn(writer.run(receiver));
+    }
+
+    async fn run(mut self, mut receiver: mpsc::Receiver<WriterMessage>) {
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.config.flush_interval_ms.max(10)));
+        loop {
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(WriterMessage::Record(entry, executed_at)) => {
+                        metrics::AUDIT_QUEUE_DEPTH.dec();
+                        self.queue(*entry, executed_at);
+                        if self.buffer.len() >= self.config.batch_size.max(1) {
+                            self.flush().await;
+                        }
+                    }
+                    Some(WriterMessage::Flush(ack)) => {
+                        self.flush().await;
+                        let _ = ack.send(());
+                    }
+                    Some(WriterMessage::Shutdown(ack)) => {
+                        receiver.close();
+                        // Drain whatever was queued before the shutdown request
+                        while let Some(message) = receiver.recv().await {
+                            if let WriterMessage::Record(entry, executed_at) = message {
+                                metrics::AUDIT_QUEUE_DEPTH.dec();
+                                self.queue(*entry, executed_at);
+                            }
+                        }
+                        self.flush().await;
+                        let _ = ack.send(());
+                        return;
+                    }
+                    None => {
+                        self.flush().await;
+                        return;
+                    }
+                },
+                _ = ticker.tick() => self.flush().await,
+            }
+        }
+    }
+
+    fn queue(&mut self, entry: AuditEntry, executed_at: DateTime<Utc>) {
+        self.next_key += 1;
+        let key = idempotency_key(entry.context.correlation_id.as_deref(), &self.run, self.next_key);
+        self.buffer.push(Queued { entry, executed_at, key });
+    }
+
+    /// Re-read redaction rules once they are older than `RULES_TTL`; on failure the last ones stay in use
+    async fn refresh_rules(&mut self) {
+        if self.rules.as_ref().is_some_and(|(loaded, _)| loaded.elapsed() < RULES_TTL) {
+            return;
+        }
+        match load_redaction_rules(&self.db).await {
+            Ok(rules) => self.rules = Some((Instant::now(), rules)),
+            Err(e) => log::debug!("Redaction rules unavailable: {}", e),
+        }
+    }
+
+    /// Record IDs of the named projects, in one query
+    async fn project_ids(&self, mut names: Vec<String>) -> HashMap<String, String> {
+        names.sort();
+        names.dedup();
+        if names.is_empty() {
+            return HashMap::new();
+        }
+        let rows: Vec<Value> = match self.db.query("SELECT name, type::string(id) AS id FROM mcp_projects WHERE name IN $names")
+            .bind(("names", names))
+            .await
+        {
+            Ok(mut result) => result.take(0).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        rows.iter()
+            .filter_map(|row| Some((row["name"].as_str()?.to_string(), row["id"].as_str()?.to_string())))
+            .collect()
+    }
+
+    /// Resolve projects and redact a batch; the records are sealed (seq/hash) only when written.
+    /// If SurrealDB is down, per-tool rules may be unavailable but the global secret scrubber still applies.
+    async fn prepare(&mut self, queued: Vec<Queued>) -> Vec<ChainedRecord> {
+        if queued.is_empty() {
+            return Vec::new();
+        }
+        self.refresh_rules().await;
+        let refs: Vec<Option<ProjectRef>> = queued.iter()
+            .map(|q| project_ref(q.entry.tool_project_id.as_ref(), &q.entry.arguments))
+            .collect();
+        let names = refs.iter()
+            .filter_map(|r| match r {
+                Some(ProjectRef::Name(name)) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        let ids = self.project_ids(names).await;
+
+        let no_rules = Vec::new();
+        queued.into_iter().zip(refs).map(|(Queued { entry, executed_at, key }, project)| {
+            let project_id = match project {
+                Some(ProjectRef::Id(id)) => Some(id),
+                Some(ProjectRef::Name(name)) => ids.get(&name).cloned(),
+                None => None,
+            };
+            let rules = self.rules.as_ref().and_then(|(_, rules)| rules.get(&entry.tool_name)).unwrap_or(&no_rules);
+            let context = entry.context;
+
+            ChainedRecord {
+                id: None,
+                seq: 0,
+                method: Some(entry.method),
+                project_id,
+                arguments: redaction::redact_arguments(&entry.arguments, rules, self.config.max_argument_bytes),
+                tool_name: entry.tool_name,
+                status: entry.status,
+                message: redaction::scrub_text(&entry.message),
+                duration_ms: entry.duration_ms,
+                executed_at,
+                principal: context.principal,
+                session_id: context.session_id,
+                client_name: context.client_name,
+                client_version: context.client_version,
+                client_ip: context.client_ip,
+                correlation_id: context.correlation_id,
+                prev_hash: String::new(),
+                hash: String::new(),
+                idempotency_key: Some(key),
+            }
+        }).collect()
+    }
+
+    /// Replay the spool first so the chain keeps execution order, then write the buffer.
+    /// Anything that cannot be written goes to the spool.
+    async fn flush(&mut self) {
+        if self.spool_pending {
+            if let Err(e) = self.replay_spool().await {
+                log::debug!("📼 Audit spool replay deferred: {}", e);
+                let queued = std::mem::take(&mut self.buffer);
+                let batch = self.prepare(queued).await;
+                self.spool(&batch);
+                return;
+            }
+        }
+
+        if self.buffer.is_empty() {
+            return;
+        }
+        let queued = std::mem::take(&mut self.buffer);
+        let batch = self.prepare(queued).await;
+        if let Err(e) = self.insert(&batch).await {
+            log::error!("🔥 Failed to write {} audit records, spooling: {}", batch.len(), e);
+            self.spool(&batch);
+        }
+    }
+
+    /// Seal the batch onto the chain head and insert it in one transaction
+    async fn insert(&mut self, batch: &[ChainedRecord]) -> Result<()> {
+        let mut head = match self.head.clone() {
+            Some(head) => head,
+            None => chain::load_head(&self.db).await?,
+        };
+
+        let mut rows = Vec::with_capacity(batch.len());
+        for record in batch {
+            let mut sealed = record.clone();
+            sealed.seq = head.seq + 1;
+            sealed.prev_hash = head.hash.clone();
+            sealed.hash = sealed.compute_hash();
+            head = ChainHead { seq: sealed.seq, hash: sealed.hash.clone() };
+            rows.push(insert_row(&sealed)?);
+        }
+
+        let result = match self.db.query(INSERT_BATCH).bind(("records", rows)).await {
+            Ok(response) => response.check().map(|_| ()),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => {
+                self.head = Some(head);
+                metrics::AUDIT_WRITTEN_TOTAL.inc_by(batch.len() as u64);
+                Ok(())
+            }
+            Err(e) => {
+                // Reload the head next time in case the write actually landed
+                self.head = None;
+                Err(e.into())
+            }
+        }
+    }
+
+    fn spool(&mut self, batch: &[ChainedRecord]) {
+        if batch.is_empty() {
+            return;
+        }
+        match append_spool(&self.config.spool_path, batch) {
+            Ok(()) => {
+                self.spool_pending = true;
+                metrics::AUDIT_SPOOLED_TOTAL.inc_by(batch.len() as u64);
+            }
+            Err(e) => log::error!("🔥 Failed to spool {} audit records, they are lost: {}", batch.len(), e),
+        }
+    }
+
+    async fn replay_spool(&mut self) -> Result<()> {
+        let records = read_spool(&self.config.spool_path)?;
+        let batch_size = self.config.batch_size.max(1);
+
+        for (i, chunk) in records.chunks(batch_size).enumerate() {
+            let written = match self.unwritten(chunk).await {
+                Ok(pending) if pending.is_empty() => Ok(()),
+                Ok(pending) => self.insert(&pending).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                // Keep only what has not been written yet
+                let remaining = &records[i * batch_size..];
+                rewrite_spool(&self.config.spool_path, remaining)?;
+                return Err(e);
+            }
+        }
+
+        if let Err(e) = std::fs::remove_file(&self.config.spool_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        self.spool_pending = false;
+        if !records.is_empty() {
+            log::info!("📼 Replayed {} spooled audit records", records.len());
+        }
+        Ok(())
+    }
+
+    /// Records of a spooled batch not in `mcp_audit_log` yet. A batch whose commit landed but whose
+    /// response was lost is spooled too; its records are skipped rather than written twice.
+    async fn unwritten(&self, batch: &[ChainedRecord]) -> Result<Vec<ChainedRecord>> {
+        let keys: Vec<String> = batch.iter().filter_map(|r| r.idempotency_key.clone()).collect();
+        if keys.is_empty() {
+            return Ok(batch.to_vec());
+        }
+        let mut result = self.db.query("SELECT VALUE idempotency_key FROM mcp_audit_log WHERE idempotency_key IN $keys")
+            .bind(("keys", keys))
+            .await?;
+        let written: HashSet<String> = result.take::<Vec<String>>(0)?.into_iter().collect();
+        if !written.is_empty() {
+            log::warn!("📼 Skipping {} spooled audit records that were already written", written.len());
+        }
+        Ok(batch.iter()
+            .filter(|r| r.idempotency_key.as_ref().is_none_or(|k| !written.contains(k)))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Redaction rules declared on tools in `mcp_tools.redaction`, by tool name
+async fn load_redaction_rules(db: &Database) -> Result<HashMap<String, Vec<RedactionRule>>> {
+    let mut result = db.query("SELECT name, redaction FROM mcp_tools WHERE redaction != NONE").await?;
+    let rows: Vec<Value> = result.take(0)?;
+    Ok(rows.into_iter()
+        .filter_map(|row| {
+            let name = row["name"].as_str()?.to_string();
+            match serde_json::from_value(row["redaction"].clone()) {
+                Ok(rules) => Some((name, rules)),
+                Err(e) => {
+                    log::warn!("⚠️ Invalid redaction rules for {}: {}", name, e);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+/// Row bound into `INSERT_BATCH`: NONE fields are omitted and the project link is split into table and key
+fn insert_row(record: &ChainedRecord) -> Result<Value> {
+    let mut row = serde_json::to_value(record)?;
+    let map = row.as_object_mut().ok_or_else(|| anyhow!("audit record is not an object"))?;
+    map.retain(|_, v| !v.is_null());
+    map.remove("project_id");
+    if let Some((table, key)) = record.project_id.as_deref().and_then(|p| p.split_once(':')) {
+        map.insert("project_tb".to_string(), Value::String(table.to_string()));
+        map.insert("project_key".to_string(), Value::String(key.trim_start_matches('⟨').trim_end_matches('⟩').to_string()));
+    }
+    Ok(row)
+}
+
+fn append_spool(path: &Path, records: &[ChainedRecord]) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+fn rewrite_spool(path: &Path, records: &[ChainedRecord]) -> Result<()> {
+    let tmp = path.with_extension("jsonl.tmp");
+    if tmp.exists() {
+        std::fs::remove_file(&tmp)?;
+    }
+    append_spool(&tmp, records)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn read_spool(path: &Path) -> Result<Vec<ChainedRecord>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content.lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| match serde_json::from_str(l) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                log::error!("🔥 Skipping corrupt audit spool line: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(tool_name: &str, project_id: Option<&str>) -> ChainedRecord {
+        ChainedRecord {
+            id: None,
+            seq: 0,
+            method: Some("tools/call".to_string()),
+            tool_name: tool_name.to_string(),
+            project_id: project_id.map(|p| p.to_string()),
+            arguments: json!({ "project": "kyx" }),
+            status: "success".to_string(),
+            message: "ok".to_string(),
+            duration_ms: 3,
+            executed_at: Utc::now(),
+            principal: Some("ci-agent".to_string()),
+            session_id: None,
+            client_name: None,
+            client_version: None,
+            client_ip: None,
+            correlation_id: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+            idempotency_key: Some("req-1:run.1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_spool_round_trip() {
+        let path = std::env::temp_dir().join(format!("kyx-audit-spool-{}.jsonl", uuid::Uuid::new_v4()));
+        append_spool(&path, &[record("a", None), record("b", None)]).unwrap();
+        append_spool(&path, &[record("c", None)]).unwrap();
+
+        let spooled = read_spool(&path).unwrap();
+        let names: Vec<&str> = spooled.iter().map(|r| r.tool_name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+
+        rewrite_spool(&path, &spooled[2..]).unwrap();
+        assert_eq!(read_spool(&path).unwrap().len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_spool(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_insert_row() {
+        let row = insert_row(&record("update-document", Some("mcp_projects:governance"))).unwrap();
+        assert_eq!(row["project_tb"], "mcp_projects");
+        assert_eq!(row["project_key"], "governance");
+        assert!(row.get("project_id").is_none());
+        assert!(row.get("session_id").is_none());
+        assert_eq!(row["principal"], "ci-agent");
+
+        assert_eq!(row["idempotency_key"], "req-1:run.1");
+
+        let row = insert_row(&record("list-projects", None)).unwrap();
+        assert!(row.get("project_tb").is_none());
+    }
+
+    #[test]
+    fn test_project_ref() {
+        let tool = json!("mcp_projects:governance");
+        assert_eq!(project_ref(Some(&tool), &json!({ "project": "other" })), Some(ProjectRef::Id("mcp_projects:governance".to_string())));
+        assert_eq!(project_ref(None, &json!({ "project_id": "mcp_projects:kyx" })), Some(ProjectRef::Id("mcp_projects:kyx".to_string())));
+        assert_eq!(project_ref(None, &json!({ "project": "kyx-governance" })), Some(ProjectRef::Name("kyx-governance".to_string())));
+        assert_eq!(project_ref(None, &json!({})), None);
+    }
+
+    #[test]
+    fn test_idempotency_keys_are_unique_per_run() {
+        assert_eq!(idempotency_key(Some("req-1"), "a1", 7), "req-1:a1.7");
+        assert_eq!(idempotency_key(None, "a1", 8), "-:a1.8");
+        // A caller reusing a correlation ID after a restart still gets a new key
+        assert_ne!(idempotency_key(Some("req-1"), "a1", 1), idempotency_key(Some("req-1"), "b2", 1));
+    }
+}
@@ -1,5 +1,20 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::net::IpAddr;
+use crate::core::rate_limiter::{PolicySpec, RateLimitConfig};
+use crate::core::audit::{AuditConfig, OverflowPolicy};
+use crate::core::mcp::quota::QuotaConfig;
+use crate::core::indexing::chunker::ChunkingConfig;
+use crate::core::indexing::watcher::AutoIndexConfig;
+use crate::core::search::rerank::RerankConfig;
+use crate::core::embedding::{EmbeddingConfig, ProviderKind};
+use crate::core::database::vector::{BackendKind, VectorConfig};
+
+/// Principal for callers authenticated with the shared `MCP_API_KEY`
+pub const DEFAULT_PRINCIPAL: &str = "default";
+/// Principal for callers when authentication is disabled
+pub const ANONYMOUS_PRINCIPAL: &str = "anonymous";
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -10,13 +25,26 @@ pub struct Config {
     pub surreal_ns: String,
     pub surreal_db: String,
     pub mcp_api_key: Option<String>,
+    /// Named API keys: token -> principal
+    pub mcp_api_keys: HashMap<String, String>,
+    /// Peers whose `X-Forwarded-For` header is believed
+    pub trusted_proxies: Vec<IpAddr>,
     #[allow(dead_code)]
     pub jwt_secret: String,
     
     // Vector Search (Phase 3)
-    pub qdrant_url: String,
-    pub openai_api_key: String,
-    pub embedding_model: String,
+    pub vector: VectorConfig,
+    pub embedding: EmbeddingConfig,
+    pub chunking: ChunkingConfig,
+    pub auto_index: AutoIndexConfig,
+    pub rerank: RerankConfig,
+
+    // Rate Limiting
+    pub rate_limits: RateLimitConfig,
+    pub quotas: QuotaConfig,
+
+    // Audit Logging
+    pub audit: AuditConfig,
 }
 
 impl Config {
@@ -36,25 +64,244 @@ impl Config {
             surreal_db: env::var("SURREAL_DATABASE").unwrap_or_else(|_| "governance".to_string()),
             // Optional: if set, requires Bearer token authentication
             mcp_api_key: env::var("MCP_API_KEY").ok().filter(|s| !s.is_empty()),
+            // Optional: "<principal>:<token>,..." to attribute usage to individual agents
+            mcp_api_keys: env::var("MCP_API_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|entry| {
+                    let (principal, token) = entry.split_once(':')?;
+                    let (principal, token) = (principal.trim(), token.trim());
+                    (!principal.is_empty() && !token.is_empty())
+                        .then(|| (token.to_string(), principal.to_string()))
+                })
+                .collect(),
+            // Optional: reverse proxies ("10.0.0.2,::1") allowed to set X-Forwarded-For
+            trusted_proxies: Self::trusted_proxies_from_env(),
             jwt_secret: env::var("JWT_SECRET").unwrap_or_else(|_| "dev-secret".to_string()),
             
             // Vector Search (Phase 3)
+            vector: Self::vector_from_env(),
+            embedding: Self::embedding_from_env(),
+            chunking: Self::chunking_from_env(),
+            auto_index: Self::auto_index_from_env(),
+            // Optional Cohere-compatible /rerank endpoint for search-semantic's `rerank` pass
+            rerank: RerankConfig {
+                url: env::var("RERANK_URL").ok().filter(|s| !s.is_empty()),
+                model: env::var("RERANK_MODEL").ok().filter(|s| !s.is_empty()),
+                api_key: env::var("RERANK_API_KEY").ok().filter(|s| !s.is_empty()),
+            },
+
+            // Rate Limiting: "<limit>/<period>" e.g. "300/60s", "100/1h"
+            rate_limits: Self::rate_limits_from_env(),
+            // Quotas that cannot be checked reject the call unless QUOTA_FAIL_OPEN=true
+            quotas: QuotaConfig {
+                fail_open: env::var("QUOTA_FAIL_OPEN").map(|s| s == "true" || s == "1").unwrap_or(false),
+            },
+
+            // Audit Logging
+            audit: Self::audit_from_env(),
+        }
+    }
+
+    fn trusted_proxies_from_env() -> Vec<IpAddr> {
+        env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| s.parse().map_err(|_| log::warn!("⚠️ Invalid TRUSTED_PROXIES entry '{}', ignoring it", s)).ok())
+            .collect()
+    }
+
+    fn vector_from_env() -> VectorConfig {
+        let backend = match env::var("VECTOR_BACKEND") {
+            Ok(raw) => BackendKind::parse(&raw).unwrap_or_else(|| {
+                log::warn!("⚠️ Invalid VECTOR_BACKEND='{}', using 'qdrant'", raw);
+                BackendKind::Qdrant
+            }),
+            Err(_) => BackendKind::Qdrant,
+        };
+        VectorConfig {
+            backend,
             qdrant_url: env::var("QDRANT_URL").unwrap_or_else(|_| "http://127.0.0.1:6333".to_string()),
-            openai_api_key: env::var("OPENAI_API_KEY").unwrap_or_else(|_| "sk-placeholder".to_string()),
-            embedding_model: env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+            local_path: env::var("VECTOR_INDEX_PATH")
+                .unwrap_or_else(|_| "data/vector-index.json".to_string())
+                .into(),
+        }
+    }
+
+    fn embedding_from_env() -> EmbeddingConfig {
+        let provider = match env::var("EMBEDDING_PROVIDER") {
+            Ok(raw) => ProviderKind::parse(&raw).unwrap_or_else(|| {
+                log::warn!("⚠️ Invalid EMBEDDING_PROVIDER='{}', using 'openai'", raw);
+                ProviderKind::OpenAi
+            }),
+            Err(_) => ProviderKind::OpenAi,
+        };
+        EmbeddingConfig {
+            provider,
+            model: env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+            // Self-hosted servers may use their own key; OpenAI falls back to OPENAI_API_KEY
+            api_key: env::var("EMBEDDING_API_KEY")
+                .or_else(|_| env::var("OPENAI_API_KEY"))
+                .ok()
+                .filter(|s| !s.is_empty()),
+            base_url: env::var("EMBEDDING_BASE_URL").ok().filter(|s| !s.is_empty()),
+            dimensions: env::var("EMBEDDING_DIMENSIONS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            batch_size: env::var("EMBEDDING_BATCH_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(64),
+            concurrency: env::var("EMBEDDING_CONCURRENCY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(4),
+            cache: env::var("EMBEDDING_CACHE")
+                .map(|s| s != "false" && s != "0")
+                .unwrap_or(true),
+        }
+    }
+
+    fn chunking_from_env() -> ChunkingConfig {
+        let defaults = ChunkingConfig::default();
+        ChunkingConfig {
+            max_tokens: env::var("INDEX_CHUNK_TOKENS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_tokens),
+            overlap_tokens: env::var("INDEX_CHUNK_OVERLAP_TOKENS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.overlap_tokens),
+        }
+    }
+
+    fn auto_index_from_env() -> AutoIndexConfig {
+        let defaults = AutoIndexConfig::default();
+        AutoIndexConfig {
+            enabled: env::var("INDEX_AUTO")
+                .map(|s| s != "false" && s != "0")
+                .unwrap_or(defaults.enabled),
+            debounce: env::var("INDEX_DEBOUNCE_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(std::time::Duration::from_millis)
+                .unwrap_or(defaults.debounce),
+        }
+    }
+
+    fn audit_from_env() -> AuditConfig {
+        AuditConfig {
+            // Arguments larger than this are stored as a truncated preview
+            max_argument_bytes: env::var("AUDIT_MAX_ARGUMENT_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(16 * 1024),
+            // Checkpoints are only written with a dedicated key; never shared with JWT_SECRET
+            signing_key: env::var("AUDIT_SIGNING_KEY").ok().filter(|s| !s.is_empty()),
+            checkpoint_path: env::var("AUDIT_CHECKPOINT_PATH")
+                .unwrap_or_else(|_| "audit/checkpoints.jsonl".to_string())
+                .into(),
+            checkpoint_secs: env::var("AUDIT_CHECKPOINT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(3600),
+            queue_capacity: env::var("AUDIT_QUEUE_CAPACITY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10_000),
+            batch_size: env::var("AUDIT_BATCH_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(100),
+            flush_interval_ms: env::var("AUDIT_FLUSH_INTERVAL_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1000),
+            // Block by default: losing audit records is worse than a slow response
+            overflow: match env::var("AUDIT_OVERFLOW_POLICY") {
+                Ok(raw) => OverflowPolicy::parse(&raw).unwrap_or_else(|| {
+                    log::warn!("⚠️ Invalid AUDIT_OVERFLOW_POLICY='{}', using 'block'", raw);
+                    OverflowPolicy::Block
+                }),
+                Err(_) => OverflowPolicy::Block,
+            },
+            spool_path: env::var("AUDIT_SPOOL_PATH")
+                .unwrap_or_else(|_| "audit/spool.jsonl".to_string())
+                .into(),
+            archive_dir: env::var("AUDIT_ARCHIVE_DIR")
+                .unwrap_or_else(|_| "audit/archive".to_string())
+                .into(),
+            retention_secs: env::var("AUDIT_RETENTION_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(86_400),
+            export_dir: env::var("AUDIT_EXPORT_DIR")
+                .unwrap_or_else(|_| "audit/exports".to_string())
+                .into(),
+            export_inline_bytes: env::var("AUDIT_EXPORT_INLINE_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1024 * 1024),
+            stats_max_days: env::var("AUDIT_STATS_MAX_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(90),
+        }
+    }
+
+    fn rate_limits_from_env() -> RateLimitConfig {
+        let defaults = RateLimitConfig::default();
+        let policy = |key: &str, default: PolicySpec| -> PolicySpec {
+            match env::var(key) {
+                Ok(raw) => PolicySpec::parse(&raw).unwrap_or_else(|| {
+                    log::warn!("⚠️ Invalid {}='{}', using default", key, raw);
+                    default
+                }),
+                Err(_) => default,
+            }
+        };
+
+        // RATE_LIMIT_TOOL_OVERRIDES="index-documents=5/1h,sync-snapshot=10/1m"
+        let tool_overrides = env::var("RATE_LIMIT_TOOL_OVERRIDES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (tool, spec) = entry.split_once('=')?;
+                PolicySpec::parse(spec).map(|spec| (tool.trim().to_string(), spec))
+            })
+            .collect();
+
+        RateLimitConfig {
+            http: policy("RATE_LIMIT_HTTP", defaults.http),
+            tool: policy("RATE_LIMIT_TOOL", defaults.tool),
+            embeddings: policy("RATE_LIMIT_EMBEDDINGS", defaults.embeddings),
+            database: policy("RATE_LIMIT_DATABASE", defaults.database),
+            tool_overrides,
+            housekeeping_secs: env::var("RATE_LIMIT_HOUSEKEEPING_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.housekeeping_secs),
         }
     }
     
     /// Check if authentication is required
     pub fn requires_auth(&self) -> bool {
-        self.mcp_api_key.is_some()
+        self.mcp_api_key.is_some() || !self.mcp_api_keys.is_empty()
     }
     
-    /// Validate the provided API key
-    pub fn validate_api_key(&self, key: &str) -> bool {
+    /// Validate the provided API key and resolve the principal it belongs to
+    pub fn authenticate(&self, key: &str) -> Option<String> {
+        if let Some(principal) = self.mcp_api_keys.get(key) {
+            return Some(principal.clone());
+        }
         match &self.mcp_api_key {
-            Some(expected) => key == expected,
-            None => true, // No auth required
+            Some(expected) if key == expected => Some(DEFAULT_PRINCIPAL.to_string()),
+            Some(_) => None,
+            None if !self.requires_auth() => Some(ANONYMOUS_PRINCIPAL.to_string()), // No auth required
+            None => None,
         }
     }
 }
@@ -57,13 +57,13 @@ pub async fn apply_migrations(db: &Database) -> std::io::Result<()> {
         
         let mut response = db.query(&check_query).await.map_err(|e| {
             log::error!("❌ Failed to check migration status for {}: {}", file_name, e);
-            std::io::Error::new(std::io::ErrorKind::Other, e.to_string())
+            std::io::Error::other(e.to_string())
         })?;
         
         // Use unchecked take because we know the schema
         let existing: Vec<MigrationRecord> = response.take(0).map_err(|e| {
              log::error!("❌ Failed to parse migration check response: {}", e);
-             std::io::Error::new(std::io::ErrorKind::Other, e.to_string())
+             std::io::Error::other(e.to_string())
         })?;
 
         if !existing.is_empty() {
@@ -85,7 +85,7 @@ pub async fn apply_migrations(db: &Database) -> std::io::Result<()> {
             Ok(resp) => resp,
             Err(e) => {
                 log::error!("❌ Transport error applying migration {}: {}", file_name, e);
-                return Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()));
+                return Err(std::io::Error::other(e.to_string()));
             }
         };
 
@@ -97,7 +97,7 @@ pub async fn apply_migrations(db: &Database) -> std::io::Result<()> {
             for (index, err) in errors.iter() {
                 log::error!("❌ Error in migration {} (stmt {}): {}", file_name, index, err);
             }
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Migration {} failed with {} errors", file_name, errors.len())));
+            return Err(std::io::Error::other(format!("Migration {} failed with {} errors", file_name, errors.len())));
         }
 
         // Record as applied
@@ -107,7 +107,7 @@ pub async fn apply_migrations(db: &Database) -> std::io::Result<()> {
         );
         if let Err(e) = db.query(&record_query).await {
             log::error!("❌ Failed to record migration {}: {}", file_name, e);
-             return Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()));
+             return Err(std::io::Error::other(e.to_string()));
         }
         
         log::info!("✅ Successfully applied migration: {}", file_name);
@@ -0,0 +1,244 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{RwLock, RwLockWriteGuard};
+use crate::core::database::vector::{ScoredPoint, SearchFilter, SourceFilter, VectorBackend, VectorPoint};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredVector {
+    /// L2-normalised, so cosine similarity is a dot product
+    vector: Vec<f32>,
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Collection {
+    dimensions: u64,
+    points: HashMap<String, StoredVector>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    collections: HashMap<String, Collection>,
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+impl Collection {
+    /// Exact (brute-force) cosine search; fine up to tens of thousands of chunks
+    fn search(&self, vector: &[f32], limit: usize, filter: &SearchFilter, with_vectors: bool) -> Vec<ScoredPoint> {
+        let query = normalize(vector.to_vec());
+        let mut hits: Vec<ScoredPoint> = self.points.iter()
+            .filter(|(_, p)| filter.matches(&p.payload))
+            .map(|(id, p)| ScoredPoint {
+                id: id.clone(),
+                score: p.vector.iter().zip(&query).map(|(a, b)| a * b).sum::<f32>() as f64,
+                payload: p.payload.clone(),
+                vector: with_vectors.then(|| p.vector.clone()),
+            })
+            .filter(|hit| filter.min_score.is_none_or(|min| hit.score >= min as f64))
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+fn load_index(path: &Path) -> Result<IndexFile> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(IndexFile::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write to a temporary file and rename, so a crash never leaves a truncated index
+fn write_index(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// In-process vector index persisted to a local JSON file, for deployments without Qdrant.
+/// Every mutation rewrites the file off the async runtime; searches never touch the disk.
+pub struct LocalBackend {
+    path: PathBuf,
+    index: RwLock<IndexFile>,
+    /// Bumped by every mutation, so a slow write never replaces a newer snapshot on disk
+    generation: AtomicU64,
+    /// Generation of the snapshot on disk; held while writing
+    saved: Arc<Mutex<u64>>,
+}
+
+impl LocalBackend {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let index = load_index(&path)
+            .map_err(|e| anyhow!("Failed to load vector index {}: {}", path.display(), e))?;
+        let points: usize = index.collections.values().map(|c| c.points.len()).sum();
+        log::info!("📂 Local vector index {} ({} points)", path.display(), points);
+        Ok(Self { path, index: RwLock::new(index), generation: AtomicU64::new(0), saved: Arc::new(Mutex::new(0)) })
+    }
+
+    /// Snapshot the index after a mutation and write it in a blocking task. Searches can run
+    /// again as soon as the snapshot is serialized.
+    async fn persist(&self, index: RwLockWriteGuard<'_, IndexFile>) -> Result<()> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let index = index.downgrade();
+        let bytes = serde_json::to_vec(&*index)?;
+        drop(index);
+
+        let (path, saved) = (self.path.clone(), Arc::clone(&self.saved));
+        tokio::task::spawn_blocking(move || {
+            let mut saved = saved.lock().unwrap();
+            if *saved >= generation {
+                return Ok(());
+            }
+            write_index(&path, &bytes)?;
+            *saved = generation;
+            Ok(())
+        }).await?
+    }
+
+    async fn mutate<T>(&self, collection_name: &str, f: impl FnOnce(&mut Collection) -> T) -> Result<T> {
+        let mut index = self.index.write().await;
+        let collection = index.collections.get_mut(collection_name)
+            .ok_or_else(|| anyhow!("Collection '{}' does not exist", collection_name))?;
+        let out = f(collection);
+        self.persist(index).await?;
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl VectorBackend for LocalBackend {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn health(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn ensure_collection(&self, collection_name: &str, size: u64) -> Result<()> {
+        let mut index = self.index.write().await;
+        match index.collections.get_mut(collection_name) {
+            Some(collection) if collection.dimensions == size => return Ok(()),
+            Some(collection) => {
+                log::warn!("⚠️ Collection '{}' has {} dimensions but the embedder produces {}; recreating it", collection_name, collection.dimensions, size);
+                *collection = Collection { dimensions: size, points: HashMap::new() };
+            }
+            None => {
+                log::info!("📦 Creating collection '{}'...", collection_name);
+                index.collections.insert(collection_name.to_string(), Collection { dimensions: size, points: HashMap::new() });
+            }
+        }
+        self.persist(index).await
+    }
+
+    async fn upsert(&self, collection_name: &str, points: Vec<VectorPoint>) -> Result<()> {
+        self.mutate(collection_name, |collection| {
+            if let Some(p) = points.iter().find(|p| p.vector.len() as u64 != collection.dimensions) {
+                return Err(anyhow!("Point {} has {} dimensions, collection expects {}", p.id, p.vector.len(), collection.dimensions));
+            }
+            for p in points {
+                collection.points.insert(p.id, StoredVector { vector: normalize(p.vector), payload: p.payload });
+            }
+            Ok(())
+        }).await?
+    }
+
+    async fn list_points(&self, collection_name: &str, fields: &[&str], sources: Option<SourceFilter<'_>>) -> Result<Vec<(String, serde_json::Value)>> {
+        let index = self.index.read().await;
+        let Some(collection) = index.collections.get(collection_name) else {
+            return Err(anyhow!("Collection '{}' does not exist", collection_name));
+        };
+        let points = collection.points.iter().filter(|(_, p)| sources.is_none_or(|s| s.matches(&p.payload)));
+        Ok(points.map(|(id, p)| {
+            let payload: serde_json::Map<String, serde_json::Value> = fields.iter()
+                .filter_map(|f| p.payload.get(*f).map(|v| (f.to_string(), v.clone())))
+                .collect();
+            (id.clone(), serde_json::Value::Object(payload))
+        }).collect())
+    }
+
+    async fn set_payloads(&self, collection_name: &str, payloads: Vec<(String, serde_json::Value)>) -> Result<()> {
+        let missing = self.mutate(collection_name, |collection| {
+            let mut missing = Vec::new();
+            for (id, payload) in payloads {
+                match collection.points.get_mut(&id) {
+                    Some(point) => point.payload = payload,
+                    None => missing.push(id),
+                }
+            }
+            missing
+        }).await?;
+        if !missing.is_empty() {
+            return Err(anyhow!("Points not found: {}", missing.join(", ")));
+        }
+        Ok(())
+    }
+
+    async fn delete_points(&self, collection_name: &str, ids: Vec<String>) -> Result<()> {
+        self.mutate(collection_name, |collection| {
+            for id in &ids {
+                collection.points.remove(id);
+            }
+        }).await
+    }
+
+    async fn search(&self, collection_name: &str, vector: Vec<f32>, limit: u64, filter: &SearchFilter, with_vectors: bool) -> Result<Vec<ScoredPoint>> {
+        let index = self.index.read().await;
+        let Some(collection) = index.collections.get(collection_name) else {
+            return Err(anyhow!("Collection '{}' does not exist", collection_name));
+        };
+        Ok(collection.search(&vector, limit as usize, filter, with_vectors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_brute_force_search_filters_and_persists() {
+        let mut collection = Collection { dimensions: 2, points: HashMap::new() };
+        for (id, vector, project) in [("a", [1.0, 0.0], "kyx"), ("b", [0.6, 0.8], "kyx"), ("c", [0.9, 0.1], "other")] {
+            collection.points.insert(id.to_string(), StoredVector {
+                vector: normalize(vector.to_vec()),
+                payload: json!({ "project_name": project, "sdlc_phase": "design" }),
+            });
+        }
+
+        let ids = |hits: Vec<ScoredPoint>| hits.into_iter().map(|h| h.id).collect::<Vec<_>>();
+        assert_eq!(ids(collection.search(&[2.0, 0.0], 10, &SearchFilter::default(), false)), ["a", "c", "b"]);
+
+        let filter = SearchFilter { project: Some("kyx".to_string()), ..Default::default() };
+        assert_eq!(ids(collection.search(&[1.0, 0.0], 10, &filter, false)), ["a", "b"]);
+        let filter = SearchFilter { min_score: Some(0.7), ..filter };
+        assert_eq!(ids(collection.search(&[1.0, 0.0], 10, &filter, false)), ["a"]);
+
+        let path = std::env::temp_dir().join(format!("kyx-vectors-{}.json", uuid::Uuid::new_v4()));
+        let mut index = IndexFile::default();
+        index.collections.insert("documentation".to_string(), collection);
+        write_index(&path, &serde_json::to_vec(&index).unwrap()).unwrap();
+        let loaded = load_index(&path).unwrap();
+        assert_eq!(loaded.collections["documentation"].points.len(), 3);
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_index(&path).unwrap().collections.is_empty());
+    }
+}
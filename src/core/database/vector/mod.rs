@@ -0,0 +1,293 @@
+pub mod local;
+pub mod qdrant;
+pub mod surreal;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::core::config::Config;
+use crate::core::database::Database;
+use crate::core::embedding::{Embedder, Embedding};
+use crate::core::rate_limiter::RateLimiter;
+use crate::core::search::rerank::{Reranker, build_reranker};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub use local::LocalBackend;
+pub use qdrant::QdrantBackend;
+pub use surreal::SurrealBackend;
+
+/// Restricts `search` to one project, SDLC phase and/or language, dropping weak matches
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub project: Option<String>,
+    pub phase: Option<String>,
+    /// Incident language, lowercase like the indexed `language` payload field
+    pub language: Option<String>,
+    pub min_score: Option<f32>,
+    /// Search the latest working copies of documents instead of their published editions
+    pub drafts: bool,
+}
+
+impl SearchFilter {
+    /// Payload flag a point must not have set to `false`: documentation points carry both,
+    /// points without the flag (e.g. incidents) always pass
+    pub fn edition_field(&self) -> &'static str {
+        if self.drafts { "latest" } else { "published" }
+    }
+
+    /// Payload fields with the values they must equal
+    pub fn required_fields(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [("project_name", &self.project), ("sdlc_phase", &self.phase), ("language", &self.language)]
+            .into_iter()
+            .filter_map(|(key, value)| value.as_deref().map(|v| (key, v)))
+    }
+
+    /// Whether a point's payload passes the project / phase / language / edition restriction
+    pub fn matches(&self, payload: &serde_json::Value) -> bool {
+        self.required_fields().all(|(key, value)| payload[key].as_str() == Some(value))
+            && payload[self.edition_field()].as_bool() != Some(false)
+    }
+}
+
+/// Points derived from these source records, found by the payload `field` that links them (e.g. `doc_id`)
+#[derive(Debug, Clone, Copy)]
+pub struct SourceFilter<'a> {
+    pub field: &'a str,
+    pub ids: &'a [String],
+}
+
+impl SourceFilter<'_> {
+    pub fn matches(&self, payload: &serde_json::Value) -> bool {
+        payload[self.field].as_str().is_some_and(|id| self.ids.iter().any(|s| s == id))
+    }
+}
+
+/// A vector with its ID and JSON payload
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorPoint {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub payload: serde_json::Value,
+}
+
+/// A search hit; `score` is cosine similarity
+#[derive(Debug, Clone)]
+pub struct ScoredPoint {
+    pub id: String,
+    pub score: f64,
+    pub payload: serde_json::Value,
+    /// Only returned when the search asked for vectors
+    pub vector: Option<Vec<f32>>,
+}
+
+/// Where vectors live. Collections use cosine distance.
+#[async_trait]
+pub trait VectorBackend: Send + Sync {
+    fn name(&self) -> &str;
+    /// Fails when the backend cannot serve requests right now
+    async fn health(&self) -> Result<()>;
+    /// Create the collection with `dimensions`-sized vectors. A collection of another size
+    /// (left by a different provider or model) is recreated, since its vectors are unusable.
+    async fn ensure_collection(&self, collection: &str, dimensions: u64) -> Result<()>;
+    /// Keyword index on a payload field so filtered searches stay fast
+    async fn ensure_payload_index(&self, _collection: &str, _field: &str) -> Result<()> {
+        Ok(())
+    }
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()>;
+    /// Point IDs with the requested payload fields: every point, or only those of `sources`
+    async fn list_points(&self, collection: &str, fields: &[&str], sources: Option<SourceFilter<'_>>) -> Result<Vec<(String, serde_json::Value)>>;
+    /// Replace the payloads of `(id, payload)` points without touching their vectors
+    async fn set_payloads(&self, collection: &str, payloads: Vec<(String, serde_json::Value)>) -> Result<()>;
+    async fn delete_points(&self, collection: &str, ids: Vec<String>) -> Result<()>;
+    /// Nearest points first, honouring `filter`
+    async fn search(&self, collection: &str, vector: Vec<f32>, limit: u64, filter: &SearchFilter, with_vectors: bool) -> Result<Vec<ScoredPoint>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum BackendKind {
+    Qdrant,
+    Local,
+    /// `mcp_doc_chunks` in SurrealDB itself
+    Surreal,
+    /// Qdrant when it is reachable at startup, otherwise the local index
+    Auto,
+}
+
+impl BackendKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "qdrant" => Some(Self::Qdrant),
+            "local" | "embedded" | "in-process" => Some(Self::Local),
+            "surreal" | "surrealdb" => Some(Self::Surreal),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorConfig {
+    pub backend: BackendKind,
+    pub qdrant_url: String,
+    /// File the local backend persists to
+    pub local_path: PathBuf,
+}
+
+/// Pick the configured backend. Qdrant being down is never fatal: `auto` falls back to the
+/// local index, `qdrant` starts anyway and searches fail until it is reachable.
+pub async fn build_backend(config: &VectorConfig, db: Database) -> Result<Arc<dyn VectorBackend>> {
+    let local = || -> Result<Arc<dyn VectorBackend>> { Ok(Arc::new(LocalBackend::open(config.local_path.clone())?)) };
+    match config.backend {
+        BackendKind::Local => return local(),
+        BackendKind::Surreal => return Ok(Arc::new(SurrealBackend::new(db))),
+        BackendKind::Qdrant | BackendKind::Auto => {}
+    }
+
+    let qdrant = QdrantBackend::new(&config.qdrant_url)?;
+    match qdrant.health().await {
+        Ok(()) => log::info!("✅ Connected to Qdrant: {}", config.qdrant_url),
+        Err(e) if config.backend == BackendKind::Auto => {
+            log::warn!("⚠️ {}; using the local vector index instead", e);
+            return local();
+        }
+        Err(e) => log::warn!("⚠️ {}; semantic search is unavailable until it comes up", e),
+    }
+    Ok(Arc::new(qdrant))
+}
+
+/// Embeds text with the configured provider and stores it in the configured backend
+#[derive(Clone)]
+pub struct VectorStore {
+    backend: Arc<dyn VectorBackend>,
+    embedder: Arc<Embedder>,
+    /// Optional second-pass scorer for search results
+    reranker: Option<Arc<dyn Reranker>>,
+}
+
+impl VectorStore {
+    pub async fn new(config: &Config, db: Database, rate_limiter: Arc<RateLimiter>) -> Result<Self> {
+        Ok(Self {
+            backend: build_backend(&config.vector, db.clone()).await?,
+            embedder: Arc::new(Embedder::new(&config.embedding, db, rate_limiter)?),
+            reranker: build_reranker(&config.rerank)?,
+        })
+    }
+
+    pub fn embedder(&self) -> &Arc<Embedder> {
+        &self.embedder
+    }
+
+    pub fn backend(&self) -> &Arc<dyn VectorBackend> {
+        &self.backend
+    }
+
+    pub fn reranker(&self) -> Option<&Arc<dyn Reranker>> {
+        self.reranker.as_ref()
+    }
+
+    /// Create the collection sized for the configured embedder
+    pub async fn ensure_collection(&self, collection_name: &str) -> Result<()> {
+        self.backend.ensure_collection(collection_name, self.embedder.dimensions()).await
+    }
+
+    pub async fn ensure_payload_index(&self, collection_name: &str, field: &str) -> Result<()> {
+        self.backend.ensure_payload_index(collection_name, field).await
+    }
+
+    pub async fn get_embedding(&self, text: &str) -> Result<Embedding> {
+        self.embedder.embed(text).await
+    }
+
+    /// Embed `(id, text, metadata)` documents in batches and upsert them; returns tokens spent
+    pub async fn upsert_documents(
+        &self,
+        collection_name: &str,
+        documents: Vec<(String, String, serde_json::Value)>,
+    ) -> Result<u32> {
+        if documents.is_empty() {
+            return Ok(0);
+        }
+        let texts: Vec<String> = documents.iter().map(|(_, text, _)| text.clone()).collect();
+        let embedded = self.embedder.embed_many(&texts).await?;
+        if embedded.vectors.len() != documents.len() {
+            return Err(anyhow!("Embedder returned {} vectors for {} documents", embedded.vectors.len(), documents.len()));
+        }
+
+        let points: Vec<VectorPoint> = documents.into_iter().zip(embedded.vectors)
+            .map(|((id, text, metadata), vector)| {
+                // Merge content into metadata, unless the caller already stored what to display
+                let payload = match metadata {
+                    serde_json::Value::Object(mut map) => {
+                        map.entry("content").or_insert_with(|| serde_json::Value::String(text));
+                        serde_json::Value::Object(map)
+                    },
+                    _ => serde_json::json!({
+                        "content": text
+                    }),
+                };
+                VectorPoint { id, vector, payload }
+            })
+            .collect();
+
+        self.backend.upsert(collection_name, points).await?;
+        Ok(embedded.tokens)
+    }
+
+    pub async fn list_points(&self, collection_name: &str, fields: &[&str], sources: Option<SourceFilter<'_>>) -> Result<Vec<(String, serde_json::Value)>> {
+        self.backend.list_points(collection_name, fields, sources).await
+    }
+
+    pub async fn set_payloads(&self, collection_name: &str, payloads: Vec<(String, serde_json::Value)>) -> Result<()> {
+        if payloads.is_empty() {
+            return Ok(());
+        }
+        self.backend.set_payloads(collection_name, payloads).await
+    }
+
+    pub async fn delete_points(&self, collection_name: &str, ids: Vec<String>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.backend.delete_points(collection_name, ids).await
+    }
+
+    /// Embed `query` and return the nearest points with the tokens spent
+    pub async fn search(
+        &self,
+        collection_name: &str,
+        query: &str,
+        limit: u64,
+        filter: &SearchFilter,
+        with_vectors: bool,
+    ) -> Result<(Vec<ScoredPoint>, u32)> {
+        let embedding = self.get_embedding(query).await?;
+        let hits = self.backend.search(collection_name, embedding.vector, limit, filter, with_vectors).await?;
+        Ok((hits, embedding.tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_search_filter_matches_payload() {
+        let payload = json!({ "project_name": "kyx-kernel", "sdlc_phase": "design" });
+        assert!(SearchFilter::default().matches(&payload));
+        assert!(SearchFilter { project: Some("kyx-kernel".to_string()), ..Default::default() }.matches(&payload));
+        assert!(!SearchFilter { phase: Some("testing".to_string()), ..Default::default() }.matches(&payload));
+        assert!(!SearchFilter { project: Some("kyx-kernel".to_string()), ..Default::default() }.matches(&json!({})));
+        let incident = json!({ "project_name": "kyx-kernel", "language": "rust" });
+        assert!(SearchFilter { language: Some("rust".to_string()), ..Default::default() }.matches(&incident));
+        assert!(!SearchFilter { language: Some("go".to_string()), ..Default::default() }.matches(&incident));
+
+        let draft = json!({ "published": false, "latest": true });
+        assert!(!SearchFilter::default().matches(&draft));
+        assert!(SearchFilter { drafts: true, ..Default::default() }.matches(&draft));
+
+        assert_eq!(BackendKind::parse("Embedded"), Some(BackendKind::Local));
+        assert_eq!(BackendKind::parse("pinecone"), None);
+    }
+}
@@ -0,0 +1,387 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::core::database::vector::{ScoredPoint, SearchFilter, SourceFilter, VectorBackend, VectorPoint};
+
+/// Points per Qdrant upsert request
+const UPSERT_CHUNK: usize = 256;
+
+// Qdrant REST API structures
+#[derive(Debug, Serialize)]
+struct QdrantCreateCollection {
+    vectors: QdrantVectorParams,
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantVectorParams {
+    size: u64,
+    distance: String, // "Cosine", "Euclid", "Dot"
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantUpsertPoints {
+    points: Vec<VectorPoint>,
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantSearchRequest {
+    vector: Vec<f32>,
+    limit: u64,
+    with_payload: bool,
+    with_vector: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score_threshold: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantSetPayload {
+    payload: serde_json::Value,
+    points: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QdrantOperation {
+    OverwritePayload(QdrantSetPayload),
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantBatch {
+    operations: Vec<QdrantOperation>,
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantPayloadIndex {
+    field_name: String,
+    field_schema: String,
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantScrollRequest {
+    limit: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<serde_json::Value>,
+    with_payload: Vec<String>,
+    with_vector: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantScrollResponse {
+    result: QdrantScrollResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantScrollResult {
+    points: Vec<QdrantScrollPoint>,
+    next_page_offset: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantScrollPoint {
+    id: serde_json::Value,
+    payload: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct QdrantDeletePoints {
+    points: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantSearchResponse {
+    result: Vec<QdrantSearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantSearchResult {
+    id: serde_json::Value,
+    score: f64,
+    payload: Option<serde_json::Value>,
+    vector: Option<Vec<f32>>,
+}
+
+/// Qdrant returns UUID point IDs as strings and numeric ones as numbers
+fn point_id(id: serde_json::Value) -> String {
+    match id {
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Qdrant payload filter over `project_name` / `sdlc_phase` / `language` and the edition flag
+fn to_qdrant_filter(filter: &SearchFilter) -> serde_json::Value {
+    let must: Vec<serde_json::Value> = filter.required_fields()
+        .map(|(key, value)| serde_json::json!({ "key": key, "match": { "value": value } }))
+        .collect();
+    // must_not rather than must, so points without the flag still match
+    let must_not = serde_json::json!([{ "key": filter.edition_field(), "match": { "value": false } }]);
+    if must.is_empty() {
+        serde_json::json!({ "must_not": must_not })
+    } else {
+        serde_json::json!({ "must": must, "must_not": must_not })
+    }
+}
+
+/// Collections in a Qdrant server, over its REST API
+pub struct QdrantBackend {
+    qdrant_url: String,
+    http_client: reqwest::Client,
+}
+
+impl QdrantBackend {
+    pub fn new(qdrant_url: &str) -> Result<Self> {
+        // Configure HTTP client with proper HTTP/2 support
+        let http_client = reqwest::Client::builder()
+            .pool_max_idle_per_host(10)
+            .pool_idle_timeout(std::time::Duration::from_secs(90))
+            .timeout(std::time::Duration::from_secs(60))
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            qdrant_url: qdrant_url.trim_end_matches('/').to_string(),
+            http_client,
+        })
+    }
+}
+
+#[async_trait]
+impl VectorBackend for QdrantBackend {
+    fn name(&self) -> &str {
+        "qdrant"
+    }
+
+    async fn health(&self) -> Result<()> {
+        let response = self.http_client
+            .get(format!("{}/collections", self.qdrant_url))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Qdrant is unreachable at {}: {}", self.qdrant_url, e))?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Qdrant at {} responded with {}", self.qdrant_url, response.status()));
+        }
+        Ok(())
+    }
+
+    async fn ensure_collection(&self, collection_name: &str, size: u64) -> Result<()> {
+        // Check if collection exists
+        let url = format!("{}/collections/{}", self.qdrant_url, collection_name);
+
+        let response = self.http_client
+            .get(&url)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let info: serde_json::Value = response.json().await?;
+            let existing = info["result"]["config"]["params"]["vectors"]["size"].as_u64();
+            if existing.is_none_or(|s| s == size) {
+                log::info!("✅ Collection '{}' already exists", collection_name);
+                return Ok(());
+            }
+
+            log::warn!("⚠️ Collection '{}' has {:?} dimensions but the embedder produces {}; recreating it", collection_name, existing, size);
+            let response = self.http_client.delete(&url).send().await?;
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                return Err(anyhow!("Failed to drop Qdrant collection: {}", error_text));
+            }
+        }
+
+        // Create collection
+        log::info!("📦 Creating collection '{}'...", collection_name);
+
+        let create_payload = QdrantCreateCollection {
+            vectors: QdrantVectorParams {
+                size,
+                distance: "Cosine".to_string(),
+            },
+        };
+
+        let response = self.http_client
+            .put(&url)
+            .json(&create_payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to create Qdrant collection: {}", error_text));
+        }
+
+        log::info!("✅ Collection '{}' created successfully", collection_name);
+        Ok(())
+    }
+
+    async fn ensure_payload_index(&self, collection_name: &str, field: &str) -> Result<()> {
+        let url = format!("{}/collections/{}/index?wait=true", self.qdrant_url, collection_name);
+        let response = self.http_client
+            .put(&url)
+            .json(&QdrantPayloadIndex { field_name: field.to_string(), field_schema: "keyword".to_string() })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to create Qdrant payload index on '{}': {}", field, error_text));
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, collection_name: &str, points: Vec<VectorPoint>) -> Result<()> {
+        let url = format!("{}/collections/{}/points?wait=true", self.qdrant_url, collection_name);
+        let mut points = points.into_iter().peekable();
+        while points.peek().is_some() {
+            let upsert_payload = QdrantUpsertPoints {
+                points: points.by_ref().take(UPSERT_CHUNK).collect(),
+            };
+
+            let response = self.http_client
+                .put(&url)
+                .json(&upsert_payload)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                return Err(anyhow!("Failed to upsert points to Qdrant: {}", error_text));
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_points(&self, collection_name: &str, fields: &[&str], sources: Option<SourceFilter<'_>>) -> Result<Vec<(String, serde_json::Value)>> {
+        let url = format!("{}/collections/{}/points/scroll", self.qdrant_url, collection_name);
+        let filter = sources.map(|s| serde_json::json!({ "must": [{ "key": s.field, "match": { "any": s.ids } }] }));
+        let mut points = Vec::new();
+        let mut offset = None;
+
+        loop {
+            let response = self.http_client
+                .post(&url)
+                .json(&QdrantScrollRequest {
+                    limit: 256,
+                    offset: offset.take(),
+                    with_payload: fields.iter().map(|f| f.to_string()).collect(),
+                    with_vector: false,
+                    filter: filter.clone(),
+                })
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                return Err(anyhow!("Failed to scroll Qdrant points: {}", error_text));
+            }
+
+            let page: QdrantScrollResponse = response.json().await?;
+            points.extend(page.result.points.into_iter().map(|p| {
+                (point_id(p.id), p.payload.unwrap_or(serde_json::json!({})))
+            }));
+
+            match page.result.next_page_offset {
+                Some(next) if !next.is_null() => offset = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(points)
+    }
+
+    async fn set_payloads(&self, collection_name: &str, payloads: Vec<(String, serde_json::Value)>) -> Result<()> {
+        let url = format!("{}/collections/{}/points/batch?wait=true", self.qdrant_url, collection_name);
+        let operations = payloads.into_iter()
+            .map(|(id, payload)| QdrantOperation::OverwritePayload(QdrantSetPayload { payload, points: vec![id] }))
+            .collect();
+        let response = self.http_client
+            .post(&url)
+            .json(&QdrantBatch { operations })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to set Qdrant payloads: {}", error_text));
+        }
+        Ok(())
+    }
+
+    async fn delete_points(&self, collection_name: &str, ids: Vec<String>) -> Result<()> {
+        let url = format!("{}/collections/{}/points/delete?wait=true", self.qdrant_url, collection_name);
+        let response = self.http_client
+            .post(&url)
+            .json(&QdrantDeletePoints { points: ids })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to delete Qdrant points: {}", error_text));
+        }
+        Ok(())
+    }
+
+    async fn search(&self, collection_name: &str, vector: Vec<f32>, limit: u64, filter: &SearchFilter, with_vectors: bool) -> Result<Vec<ScoredPoint>> {
+        let search_payload = QdrantSearchRequest {
+            vector,
+            limit,
+            with_payload: true,
+            with_vector: with_vectors,
+            filter: Some(to_qdrant_filter(filter)),
+            score_threshold: filter.min_score,
+        };
+
+        let url = format!("{}/collections/{}/points/search", self.qdrant_url, collection_name);
+
+        let response = self.http_client
+            .post(&url)
+            .json(&search_payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to search Qdrant: {}", error_text));
+        }
+
+        let search_response: QdrantSearchResponse = response.json().await?;
+        Ok(search_response.result.into_iter().map(|point| ScoredPoint {
+            id: point_id(point.id),
+            score: point.score,
+            payload: point.payload.unwrap_or(serde_json::json!({})),
+            vector: point.vector,
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_search_filter_to_qdrant() {
+        assert_eq!(to_qdrant_filter(&SearchFilter::default()), json!({
+            "must_not": [{ "key": "published", "match": { "value": false } }]
+        }));
+
+        let filter = SearchFilter { project: Some("kyx-kernel".to_string()), phase: Some("design".to_string()), min_score: Some(0.3), drafts: true, ..Default::default() };
+        assert_eq!(to_qdrant_filter(&filter), json!({
+            "must": [
+                { "key": "project_name", "match": { "value": "kyx-kernel" } },
+                { "key": "sdlc_phase", "match": { "value": "design" } }
+            ],
+            "must_not": [{ "key": "latest", "match": { "value": false } }]
+        }));
+
+        let filter = SearchFilter { language: Some("rust".to_string()), ..Default::default() };
+        assert_eq!(to_qdrant_filter(&filter), json!({
+            "must": [{ "key": "language", "match": { "value": "rust" } }],
+            "must_not": [{ "key": "published", "match": { "value": false } }]
+        }));
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use crate::core::database::Database;
+use crate::core::database::vector::{ScoredPoint, SearchFilter, SourceFilter, VectorBackend, VectorPoint};
+use crate::core::indexing::DOCS_COLLECTION;
+use crate::core::indexing::incidents::INCIDENTS_COLLECTION;
+
+/// Points per upsert query
+const UPSERT_CHUNK: usize = 256;
+
+/// Row fields derived from a point's payload; `$p` is `{ id, vector, payload }`.
+/// `OR NONE` keeps JSON nulls out of `option<>` fields.
+const PAYLOAD_FIELDS: &str = "
+    payload: $p.payload,
+    project_name: $p.payload.project_name OR NONE,
+    sdlc_phase: $p.payload.sdlc_phase OR NONE
+";
+
+/// Table backing a collection, and the field linking each row to its source record
+struct ChunkTable {
+    name: &'static str,
+    /// Column holding the source record link
+    column: &'static str,
+    /// Payload field carrying the source record id
+    source: &'static str,
+    /// Table of the source records
+    source_table: &'static str,
+}
+
+impl ChunkTable {
+    /// Every row field derived from a point
+    fn fields(&self) -> String {
+        format!(
+            "{column}: IF $p.payload.{source} THEN <record<{table}>> $p.payload.{source} END, {rest}",
+            column = self.column, source = self.source, table = self.source_table, rest = PAYLOAD_FIELDS,
+        )
+    }
+}
+
+fn chunk_table(collection: &str) -> Result<ChunkTable> {
+    match collection {
+        DOCS_COLLECTION => Ok(ChunkTable {
+            name: "mcp_doc_chunks",
+            column: "document",
+            source: "doc_id",
+            source_table: "mcp_documentation",
+        }),
+        INCIDENTS_COLLECTION => Ok(ChunkTable {
+            name: "mcp_incident_chunks",
+            column: "incident",
+            source: "incident_id",
+            source_table: "mcp_incident",
+        }),
+        other => Err(anyhow!("Collection '{}' has no SurrealDB chunk table", other)),
+    }
+}
+
+/// `DIMENSION n` from an index definition as printed by `INFO FOR TABLE`
+fn index_dimension(definition: &str) -> Option<u64> {
+    let mut words = definition.split_whitespace();
+    words.find(|w| *w == "DIMENSION")?;
+    words.next()?.parse().ok()
+}
+
+#[derive(Debug, Deserialize)]
+struct TableInfo {
+    /// Index name -> definition
+    indexes: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PointRow {
+    id: String,
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct HitRow {
+    id: String,
+    payload: serde_json::Value,
+    distance: f64,
+    vector: Option<Vec<f32>>,
+}
+
+/// Embeddings stored next to the documents in SurrealDB, searched with an HNSW index.
+/// Project / phase / language filters run inside the KNN query.
+pub struct SurrealBackend {
+    db: Database,
+}
+
+impl SurrealBackend {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl VectorBackend for SurrealBackend {
+    fn name(&self) -> &str {
+        "surreal"
+    }
+
+    async fn health(&self) -> Result<()> {
+        self.db.health().await?;
+        Ok(())
+    }
+
+    async fn ensure_collection(&self, collection: &str, dimensions: u64) -> Result<()> {
+        let table = chunk_table(collection)?.name;
+        let index = format!("{}_embedding", table);
+        let mut result = self.db.query(format!("INFO FOR TABLE {}", table)).await?;
+        let info: Option<TableInfo> = result.take(0)?;
+        let definition = info.and_then(|mut i| i.indexes.remove(&index));
+
+        match definition.as_deref().map(index_dimension) {
+            Some(Some(existing)) if existing == dimensions => return Ok(()),
+            Some(existing) => {
+                log::warn!("⚠️ Collection '{}' has {:?} dimensions but the embedder produces {}; recreating it", collection, existing, dimensions);
+                self.db.query(format!("REMOVE INDEX {} ON {}; DELETE {};", index, table, table)).await?.check()?;
+            }
+            None => log::info!("📦 Creating vector index '{}'...", index),
+        }
+
+        self.db
+            .query(format!("DEFINE INDEX OVERWRITE {} ON {} FIELDS embedding HNSW DIMENSION {} DIST COSINE", index, table, dimensions))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        let table = chunk_table(collection)?;
+        let sql = format!("
+            FOR $p IN $points {{
+                UPSERT type::thing($tb, $p.id) CONTENT {{ embedding: $p.vector, {} }};
+            }};
+        ", table.fields());
+        let mut points = points.into_iter().peekable();
+        while points.peek().is_some() {
+            let batch: Vec<VectorPoint> = points.by_ref().take(UPSERT_CHUNK).collect();
+            self.db.query(sql.as_str())
+                .bind(("tb", table.name))
+                .bind(("points", batch))
+                .await?
+                .check()?;
+        }
+        Ok(())
+    }
+
+    async fn list_points(&self, collection: &str, fields: &[&str], sources: Option<SourceFilter<'_>>) -> Result<Vec<(String, serde_json::Value)>> {
+        let table = chunk_table(collection)?;
+        let (condition, field, ids) = match sources {
+            // The link column is indexed, so scoped syncs stay off a table scan
+            Some(s) if s.field == table.source => (
+                format!("WHERE {} IN array::map($ids, |$id| <record<{}>> $id)", table.column, table.source_table),
+                s.field.to_string(),
+                s.ids.to_vec(),
+            ),
+            Some(s) => ("WHERE payload[$field] IN $ids".to_string(), s.field.to_string(), s.ids.to_vec()),
+            None => (String::new(), String::new(), Vec::new()),
+        };
+        let sql = format!("SELECT meta::id(id) AS id, payload FROM type::table($tb) {}", condition);
+        let mut result = self.db
+            .query(sql.as_str())
+            .bind(("tb", table.name))
+            .bind(("field", field))
+            .bind(("ids", ids))
+            .await?;
+        let rows: Vec<PointRow> = result.take(0)?;
+        Ok(rows.into_iter().map(|row| {
+            let payload: serde_json::Map<String, serde_json::Value> = fields.iter()
+                .filter_map(|f| row.payload.get(*f).map(|v| (f.to_string(), v.clone())))
+                .collect();
+            (row.id, serde_json::Value::Object(payload))
+        }).collect())
+    }
+
+    async fn set_payloads(&self, collection: &str, payloads: Vec<(String, serde_json::Value)>) -> Result<()> {
+        let table = chunk_table(collection)?;
+        let points: Vec<serde_json::Value> = payloads.into_iter()
+            .map(|(id, payload)| serde_json::json!({ "id": id, "payload": payload }))
+            .collect();
+        self.db
+            .query(format!("FOR $p IN $points {{ UPDATE type::thing($tb, $p.id) MERGE {{ {} }}; }};", table.fields()))
+            .bind(("tb", table.name))
+            .bind(("points", points))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn delete_points(&self, collection: &str, ids: Vec<String>) -> Result<()> {
+        let table = chunk_table(collection)?.name;
+        self.db
+            .query("FOR $id IN $ids { DELETE type::thing($tb, $id); };")
+            .bind(("tb", table))
+            .bind(("ids", ids))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn search(&self, collection: &str, vector: Vec<f32>, limit: u64, filter: &SearchFilter, with_vectors: bool) -> Result<Vec<ScoredPoint>> {
+        let table = chunk_table(collection)?.name;
+        let mut conditions = Vec::new();
+        if filter.project.is_some() { conditions.push("project_name = $project".to_string()); }
+        if filter.phase.is_some() { conditions.push("sdlc_phase = $phase".to_string()); }
+        if filter.language.is_some() { conditions.push("payload.language = $language".to_string()); }
+        conditions.push(format!("payload.{} != false", filter.edition_field()));
+        // KNN size and search breadth (ef) must be literals
+        let limit = limit.max(1);
+        conditions.push(format!("embedding <|{},{}|> $vector", limit, (limit * 2).max(40)));
+        let sql = format!("
+            SELECT meta::id(id) AS id, payload, vector::distance::knn() AS distance{}
+            FROM type::table($tb)
+            WHERE {}
+            ORDER BY distance
+        ", if with_vectors { ", embedding AS vector" } else { "" }, conditions.join(" AND "));
+
+        let mut q = self.db.query(sql)
+            .bind(("tb", table))
+            .bind(("vector", vector));
+        if let Some(project) = &filter.project { q = q.bind(("project", project.clone())); }
+        if let Some(phase) = &filter.phase { q = q.bind(("phase", phase.clone())); }
+        if let Some(language) = &filter.language { q = q.bind(("language", language.clone())); }
+
+        let mut result = q.await?;
+        let rows: Vec<HitRow> = result.take(0)?;
+        Ok(rows.into_iter()
+            // Cosine distance -> similarity, comparable with Qdrant scores
+            .map(|row| ScoredPoint { id: row.id, score: 1.0 - row.distance, payload: row.payload, vector: row.vector })
+            .filter(|hit| filter.min_score.is_none_or(|min| hit.score >= min as f64))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_dimension_and_tables() {
+        let definition = "DEFINE INDEX mcp_doc_chunks_embedding ON mcp_doc_chunks FIELDS embedding HNSW DIMENSION 1536 DIST COSINE TYPE F32 EFC 150 M 12";
+        assert_eq!(index_dimension(definition), Some(1536));
+        assert_eq!(index_dimension("DEFINE INDEX x ON y FIELDS z"), None);
+
+        assert_eq!(chunk_table(DOCS_COLLECTION).unwrap().name, "mcp_doc_chunks");
+        assert_eq!(chunk_table(INCIDENTS_COLLECTION).unwrap().name, "mcp_incident_chunks");
+        assert!(chunk_table("unknown").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use similar::{ChangeTag, TextDiff};
+
+/// Lines added and removed between two texts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Unified line diff with three lines of context; empty when the texts are equal
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> (String, DiffStats) {
+    let diff = TextDiff::from_lines(old, new);
+    let mut stats = DiffStats::default();
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => stats.added += 1,
+            ChangeTag::Delete => stats.removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let text = diff.unified_diff().context_radius(3).header(old_label, new_label).to_string();
+    (text, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "# Title\n\nintro\n\n## Scope\nold scope\n";
+        let new = "# Title\n\nintro\n\n## Scope\nnew scope\nmore\n";
+        let (text, stats) = unified_diff(old, new, "v1", "v2");
+        assert_eq!(stats, DiffStats { added: 2, removed: 1 });
+        assert!(text.starts_with("--- v1\n+++ v2\n@@ -3,4 +3,5 @@\n"));
+        assert!(text.contains("\n-old scope\n+new scope\n+more\n"));
+
+        assert_eq!(unified_diff(old, old, "v1", "v2"), (String::new(), DiffStats::default()));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::core::database::Database;
+use crate::core::documents::DocumentKey;
+use crate::core::documents::history::{PRINCIPAL_PARAM, REASON_PARAM};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::fmt;
+
+/// A write made against a version other than the document's current one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionConflict {
+    pub uri: String,
+    /// `None` when the caller sent no expected version
+    pub expected: Option<i64>,
+    pub current: i64,
+    /// Set when `expected` is the published edition's version: the caller edited what a default read returns
+    pub published: Option<i64>,
+}
+
+impl fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expected {
+            Some(expected) if self.published == Some(expected) => write!(
+                f,
+                "Version conflict on {}: version {} is the published edition, but the working copy has newer drafts (version {}). Read {}?drafts=true, reapply your change to that content and retry with its _meta.version.",
+                self.uri, expected, self.current, self.uri
+            ),
+            Some(expected) => write!(
+                f,
+                "Version conflict on {}: expected version {} but the document is at version {}. Re-read it, reapply your change and retry with expected_version {}.",
+                self.uri, expected, self.current, self.current
+            ),
+            None => write!(
+                f,
+                "Missing 'expected_version' for {}: the document is at version {}. Read it first and pass the version you edited.",
+                self.uri, self.current
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionConflict {}
+
+/// A document's content and the version it is at
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurrentDocument {
+    pub content: String,
+    pub version: i64,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    #[serde(default)]
+    pub published_version: Option<i64>,
+}
+
+impl CurrentDocument {
+    /// Edits must be based on the working copy, i.e. `_meta.version` of a `?drafts=true` read.
+    /// A published edition behind newer drafts is rejected, so the drafts are not overwritten.
+    pub fn check_base(&self, uri: String, expected: Option<i64>) -> std::result::Result<(), VersionConflict> {
+        if expected == Some(self.version) {
+            return Ok(());
+        }
+        let published = self.published_version.filter(|p| Some(*p) == expected);
+        Err(VersionConflict { uri, expected, current: self.version, published })
+    }
+}
+
+/// `_meta` of a `resources/read`. Only a drafts read's `version` is an edit base; a published read
+/// just says whether newer drafts exist, and never the working copy's version.
+pub fn read_meta(version: i64, status: Option<&str>, latest_version: Option<i64>, drafts: bool) -> serde_json::Value {
+    let mut meta = serde_json::json!({ "version": version, "status": status });
+    if !drafts {
+        meta["newer_draft"] = serde_json::Value::Bool(latest_version.is_some_and(|latest| latest > version));
+    }
+    meta
+}
+
+pub async fn current(db: &Database, key: &DocumentKey) -> Result<Option<CurrentDocument>> {
+    let mut result = db.query("
+        SELECT content, version, metadata, published_version FROM mcp_documentation
+        WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name
+    ")
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .await?;
+    Ok(result.take(0)?)
+}
+
+/// Replace a document's content if it is still at `expected` (compare-and-set).
+/// Returns the document's version afterwards; it only moves when the content changed.
+/// Fails with [`VersionConflict`] when the document has moved on, or `expected` is missing.
+pub async fn update_content(
+    db: &Database,
+    key: &DocumentKey,
+    content: &str,
+    expected: Option<i64>,
+    principal: &str,
+    reason: Option<&str>,
+) -> Result<i64> {
+    let mut result = db.query("
+        UPDATE mcp_documentation SET content = $content, updated_at = time::now()
+            WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name AND version = $expected
+            RETURN VALUE version;
+        SELECT VALUE version FROM mcp_documentation
+            WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name;
+    ")
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .bind(("content", content.to_string()))
+        .bind(("expected", expected))
+        .bind((PRINCIPAL_PARAM, principal.to_string()))
+        .bind((REASON_PARAM, reason.map(String::from)))
+        .await?
+        .check()
+        .map_err(|e| anyhow!("Failed to update {}: {}", key.uri(), e))?;
+
+    let updated: Option<i64> = result.take(0)?;
+    let current: Option<i64> = result.take(1)?;
+    match (updated, current) {
+        (Some(version), _) => Ok(version),
+        (None, Some(current)) => Err(VersionConflict { uri: key.uri(), expected, current, published: None }.into()),
+        (None, None) => Err(anyhow!("Document not found: {}", key.uri())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflict_message_shows_current_version() {
+        let conflict = VersionConflict { uri: "kyx://kyx-infra/design/architecture".to_string(), expected: Some(3), current: 5, published: None };
+        assert_eq!(
+            conflict.to_string(),
+            "Version conflict on kyx://kyx-infra/design/architecture: expected version 3 but the document is at version 5. Re-read it, reapply your change and retry with expected_version 5."
+        );
+        let missing = VersionConflict { expected: None, ..conflict };
+        assert!(missing.to_string().contains("is at version 5"));
+    }
+
+    #[test]
+    fn test_published_read_is_not_an_edit_base() {
+        // Published v3 is served by default while the working copy has drafts up to v5
+        let meta = read_meta(3, Some("published"), Some(5), false);
+        assert_eq!(meta, serde_json::json!({ "version": 3, "status": "published", "newer_draft": true }));
+        assert!(!meta.to_string().contains('5'));
+
+        let doc = CurrentDocument { content: "v5".to_string(), version: 5, metadata: serde_json::Value::Null, published_version: Some(3) };
+        let uri = "kyx://kyx-infra/design/architecture".to_string();
+        // Editing the published text is rejected and pointed at the drafts read
+        let conflict = doc.check_base(uri.clone(), Some(3)).unwrap_err();
+        assert_eq!(conflict.published, Some(3));
+        assert!(conflict.to_string().contains("is the published edition"));
+        assert!(conflict.to_string().contains("?drafts=true"));
+
+        // A drafts read returns the working copy's version, which is the edit base
+        assert_eq!(read_meta(5, Some("draft"), Some(5), true), serde_json::json!({ "version": 5, "status": "draft" }));
+        assert!(doc.check_base(uri.clone(), Some(5)).is_ok());
+        assert_eq!(doc.check_base(uri, Some(4)).unwrap_err().published, None);
+
+        // Without newer drafts the published version is the working copy's and works as a base
+        assert_eq!(read_meta(5, Some("published"), Some(5), false)["newer_draft"], false);
+    }
+}
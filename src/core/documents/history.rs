@@ -0,0 +1,180 @@
+use crate::core::database::Database;
+use crate::core::documents::DocumentKey;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+/// Query parameters the `mcp_documentation` capture event reads to attribute a change.
+/// Bind them on any query that may write documents.
+pub const PRINCIPAL_PARAM: &str = "kyx_principal";
+pub const REASON_PARAM: &str = "kyx_reason";
+
+/// One entry of a document's history, without its content
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionSummary {
+    pub version: i64,
+    /// `create`, `update` or `delete`
+    pub event: String,
+    pub author: String,
+    pub reason: Option<String>,
+    pub created_at: String,
+    pub title: String,
+    pub name: String,
+    pub lines: i64,
+}
+
+/// The document as it was after a change
+#[derive(Debug, Clone, Deserialize)]
+pub struct DocumentVersion {
+    pub version: i64,
+    pub title: String,
+    pub content: String,
+}
+
+/// Record ID of the document at `key`; falls back to history so deleted documents resolve too
+pub async fn resolve(db: &Database, key: &DocumentKey) -> Result<Option<String>> {
+    let mut result = db.query("
+        LET $live = (SELECT VALUE type::string(id) FROM mcp_documentation
+            WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name)[0];
+        LET $last = (SELECT document, created_at FROM mcp_documentation_history
+            WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name
+            ORDER BY created_at DESC LIMIT 1)[0];
+        RETURN $live OR (IF $last IS NONE THEN NONE ELSE type::string($last.document) END);
+    ")
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .await?;
+    Ok(result.take(2)?)
+}
+
+/// The database clock, to pass to [`changed_since`] after a batch of writes
+pub async fn now(db: &Database) -> Result<String> {
+    let mut result = db.query("RETURN type::string(time::now())").await?;
+    result.take::<Option<String>>(0)?.ok_or_else(|| anyhow!("The database returned no time"))
+}
+
+/// Documents `author` created or changed (not deleted) since `since`
+pub async fn changed_since(db: &Database, since: &str, author: &str) -> Result<Vec<String>> {
+    let mut result = db.query("
+        RETURN array::distinct((SELECT VALUE type::string(document) FROM mcp_documentation_history
+            WHERE created_at >= <datetime> $since AND author = $author AND event != 'delete'))
+    ")
+        .bind(("since", since.to_string()))
+        .bind(("author", author.to_string()))
+        .await?;
+    Ok(result.take::<Option<Vec<String>>>(0)?.unwrap_or_default())
+}
+
+/// A document address that stopped existing: the document was deleted, or moved to `to`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal {
+    pub from: DocumentKey,
+    pub to: Option<DocumentKey>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyRow {
+    project: Option<String>,
+    phase: String,
+    name: String,
+}
+
+impl From<KeyRow> for DocumentKey {
+    fn from(row: KeyRow) -> Self {
+        DocumentKey { project: row.project.unwrap_or_else(|| "unknown".to_string()), phase: row.phase, name: row.name }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RemovalRow {
+    event: String,
+    key: KeyRow,
+    previous: Option<KeyRow>,
+}
+
+/// Documents `author` deleted, renamed or moved to another phase / project since `since`
+pub async fn removed_since(db: &Database, since: &str, author: &str) -> Result<Vec<Removal>> {
+    let mut result = db.query("
+        SELECT created_at, event, { project: project_id.name, phase: sdlc_phase, name: name } AS key,
+            (SELECT project_id.name AS project, sdlc_phase AS phase, name, version FROM mcp_documentation_history
+                WHERE document = $parent.document AND version < $parent.version
+                ORDER BY version DESC LIMIT 1)[0] AS previous
+        FROM mcp_documentation_history
+        WHERE created_at >= <datetime> $since AND author = $author AND event != 'create'
+        ORDER BY created_at
+    ")
+        .bind(("since", since.to_string()))
+        .bind(("author", author.to_string()))
+        .await?;
+    let rows: Vec<RemovalRow> = result.take(0)?;
+
+    Ok(rows.into_iter().filter_map(|row| {
+        let key = DocumentKey::from(row.key);
+        if row.event == "delete" {
+            return Some(Removal { from: key, to: None });
+        }
+        let previous = DocumentKey::from(row.previous?);
+        (previous != key).then_some(Removal { from: previous, to: Some(key) })
+    }).collect())
+}
+
+/// Newest first
+pub async fn list_versions(db: &Database, doc_id: &str, limit: u64) -> Result<Vec<VersionSummary>> {
+    let mut result = db.query("
+        SELECT version, event, author, reason, type::string(created_at) AS created_at, title, name,
+            array::len(string::split(content, '\n')) AS lines
+        FROM mcp_documentation_history
+        WHERE document = <record<mcp_documentation>> $doc
+        ORDER BY version DESC
+        LIMIT $limit
+    ")
+        .bind(("doc", doc_id.to_string()))
+        .bind(("limit", limit))
+        .await?;
+    Ok(result.take(0)?)
+}
+
+/// A version, or the latest one when `version` is `None`
+pub async fn get_version(db: &Database, doc_id: &str, version: Option<i64>) -> Result<Option<DocumentVersion>> {
+    let mut result = db.query("
+        SELECT version, title, content FROM mcp_documentation_history
+        WHERE document = <record<mcp_documentation>> $doc AND ($version IS NONE OR version = $version)
+        ORDER BY version DESC
+        LIMIT 1
+    ")
+        .bind(("doc", doc_id.to_string()))
+        .bind(("version", version))
+        .await?;
+    Ok(result.take(0)?)
+}
+
+/// Put a past version back as the current document (recreating it if it was deleted).
+/// Returns the new version number, or `None` when the document already had that content.
+pub async fn restore(db: &Database, doc_id: &str, version: i64, principal: &str, reason: &str) -> Result<Option<i64>> {
+    let before = get_version(db, doc_id, None).await?.map(|v| v.version);
+    db.query("
+        LET $doc = <record<mcp_documentation>> $doc_id;
+        LET $v = (SELECT * FROM mcp_documentation_history WHERE document = $doc AND version = $version)[0];
+        IF $v IS NONE { THROW 'Version ' + type::string($version) + ' does not exist'; };
+        UPSERT $doc MERGE {
+            project_id: $v.project_id,
+            sdlc_phase: $v.sdlc_phase,
+            name: $v.name,
+            title: $v.title,
+            content: $v.content,
+            mimeType: $v.mimeType,
+            metadata: $v.metadata,
+            updated_at: time::now()
+        };
+    ")
+        .bind(("doc_id", doc_id.to_string()))
+        .bind(("version", version))
+        .bind((PRINCIPAL_PARAM, principal.to_string()))
+        .bind((REASON_PARAM, reason.to_string()))
+        .await?
+        .check()
+        .map_err(|e| anyhow!("Failed to restore version {}: {}", version, e))?;
+
+    let after = get_version(db, doc_id, None).await?.map(|v| v.version);
+    Ok((after != before).then_some(after).flatten())
+}
@@ -0,0 +1,334 @@
+use crate::core::database::Database;
+use crate::core::documents::DocumentKey;
+use crate::core::documents::edit;
+use crate::core::documents::history::Removal;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Values `mcp_documentation.sdlc_phase` accepts
+pub const SDLC_PHASES: [&str; 6] = ["planning", "design", "implementation", "verification", "maintenance", "none"];
+
+lazy_static! {
+    static ref KYX_URI: Regex = Regex::new(r"kyx://[^\s()\[\]<>`'\x22]+").unwrap();
+    /// `[text](target)` and `[text](target "title")`
+    static ref INLINE_LINK: Regex = Regex::new(r#"\]\(\s*<?([^)\s>]+)>?(?:\s+"[^"]*")?\s*\)"#).unwrap();
+    /// `[label]: target`
+    static ref LINK_DEFINITION: Regex = Regex::new(r"^\s{0,3}\[[^\]]+\]:\s*<?([^\s>]+)>?").unwrap();
+    static ref CODE_SPAN: Regex = Regex::new(r"`[^`]*`").unwrap();
+}
+
+/// A reference from one document to a document, a phase (`kyx://project/phase`) or a project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Reference {
+    /// As written in the source document
+    pub target: String,
+    /// Resolved project, phase and name; shorter for phase and project references
+    pub address: Vec<String>,
+    pub anchor: Option<String>,
+    /// 1-based
+    pub line: usize,
+}
+
+pub fn address_uri(address: &[String]) -> String {
+    format!("kyx://{}", address.join("/"))
+}
+
+/// Split off `#anchor` and `?query`; the query (e.g. `?drafts=true`) does not change the target
+fn split_target(target: &str) -> (&str, Option<String>) {
+    let (path, anchor) = match target.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor.to_string()).filter(|a| !a.is_empty())),
+        None => (target, None),
+    };
+    (path.split('?').next().unwrap_or_default(), anchor)
+}
+
+/// `kyx://project/phase/name#anchor`
+fn parse_uri(uri: &str) -> Option<(Vec<String>, Option<String>)> {
+    let (path, anchor) = split_target(uri.strip_prefix("kyx://")?);
+    let address: Vec<String> = path.split('/').filter(|s| !s.is_empty()).map(String::from).collect();
+    (!address.is_empty()).then_some((address, anchor))
+}
+
+/// A markdown link target relative to the source document's phase, e.g. `architecture`,
+/// `../design/architecture.md` or `../../kyx-kernel/planning/prd`. Links with a scheme, bare
+/// anchors, absolute paths and paths deeper than `project/phase/name` (files) are not references.
+fn resolve_relative(source: &DocumentKey, target: &str) -> Option<(Vec<String>, Option<String>)> {
+    if target.starts_with('#') || target.starts_with('/') || target.contains(':') {
+        return None;
+    }
+    let (path, anchor) = split_target(target);
+    let mut address = vec![source.project.clone(), source.phase.clone()];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => { address.pop()?; }
+            segment => address.push(segment.strip_suffix(".md").unwrap_or(segment).to_string()),
+        }
+    }
+    (!address.is_empty() && address.len() <= 3).then_some((address, anchor))
+}
+
+/// Every `kyx://` URI and relative markdown link outside code blocks and code spans
+pub fn extract(content: &str, source: &DocumentKey) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        let text = CODE_SPAN.replace_all(line, "");
+        let mut push = |target: &str, resolved: Option<(Vec<String>, Option<String>)>| {
+            if let Some((address, anchor)) = resolved {
+                references.push(Reference { target: target.to_string(), address, anchor, line: i + 1 });
+            }
+        };
+        for m in KYX_URI.find_iter(&text) {
+            let uri = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_']);
+            push(uri, parse_uri(uri));
+        }
+        let links = INLINE_LINK.captures_iter(&text).chain(LINK_DEFINITION.captures_iter(&text));
+        for target in links.filter_map(|c| c.get(1)).map(|m| m.as_str()) {
+            push(target, resolve_relative(source, target));
+        }
+    }
+    references
+}
+
+/// Why `address` does not resolve, or `None` when it does
+pub fn problem(address: &[String], projects: &HashSet<String>, documents: &HashSet<Vec<String>>) -> Option<String> {
+    if address.len() > 3 {
+        return Some("not a document address (kyx://project/phase/name)".to_string());
+    }
+    if !projects.contains(&address[0]) {
+        return Some(format!("project '{}' does not exist", address[0]));
+    }
+    if let Some(phase) = address.get(1).filter(|p| !SDLC_PHASES.contains(&p.as_str())) {
+        return Some(format!("phase '{}' does not exist (phases: {})", phase, SDLC_PHASES.join(", ")));
+    }
+    (address.len() == 3 && !documents.contains(address)).then(|| "document does not exist".to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceRow {
+    id: String,
+    project: Option<String>,
+    phase: String,
+    name: String,
+    content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SourceLinks {
+    id: String,
+    references: Vec<Reference>,
+}
+
+/// Re-parse documents (record IDs, or all of them) and replace their outgoing `mcp_doc_links` edges.
+/// Edges point at `mcp_doc_addresses:[project, phase, name]`, which exist whether or not a
+/// document lives at the address, so links to missing documents stay in the graph.
+pub async fn refresh(db: &Database, ids: Option<&[String]>) -> Result<usize> {
+    let source = match ids {
+        Some(_) => "array::map($ids, |$id| <record<mcp_documentation>> $id)",
+        None => "mcp_documentation",
+    };
+    let mut result = db.query(format!("
+        SELECT type::string(id) AS id, project_id.name AS project, sdlc_phase AS phase, name, content FROM {}
+    ", source))
+        .bind(("ids", ids.map(|ids| ids.to_vec()).unwrap_or_default()))
+        .await?;
+    let rows: Vec<SourceRow> = result.take(0)?;
+
+    let docs: Vec<SourceLinks> = rows.into_iter().map(|row| {
+        let key = DocumentKey { project: row.project.unwrap_or_else(|| "unknown".to_string()), phase: row.phase, name: row.name };
+        SourceLinks { references: extract(row.content.as_deref().unwrap_or_default(), &key), id: row.id }
+    }).collect();
+    let links = docs.iter().map(|d| d.references.len()).sum();
+
+    db.query(format!("
+        FOR $doc IN $docs {{
+            LET $source = <record<mcp_documentation>> $doc.id;
+            DELETE mcp_doc_links WHERE in = $source;
+            FOR $ref IN $doc.references {{
+                LET $address = type::thing('mcp_doc_addresses', $ref.address);
+                UPSERT $address SET uri = 'kyx://' + array::join($ref.address, '/');
+                RELATE $source->mcp_doc_links->$address SET target = $ref.target, anchor = $ref.anchor OR NONE, line = $ref.line;
+            }};
+        }};
+        {}
+    ", if ids.is_none() { "DELETE mcp_doc_addresses WHERE count(<-mcp_doc_links) = 0;" } else { "" }))
+        .bind(("docs", docs))
+        .await?
+        .check()?;
+    Ok(links)
+}
+
+/// A reference stored in the link graph
+#[derive(Debug, Clone, Deserialize)]
+pub struct Link {
+    project: Option<String>,
+    phase: String,
+    name: String,
+    pub target: String,
+    pub address: Vec<String>,
+    pub line: usize,
+}
+
+impl Link {
+    /// The document the link is written in
+    pub fn source(&self) -> DocumentKey {
+        DocumentKey { project: self.project.clone().unwrap_or_else(|| "unknown".to_string()), phase: self.phase.clone(), name: self.name.clone() }
+    }
+}
+
+const LINK_FIELDS: &str = "in.project_id.name AS project, in.sdlc_phase AS phase, in.name AS name, target, meta::id(out) AS address, line";
+
+/// Links pointing at `key`, whether or not a document lives there now
+pub async fn backlinks(db: &Database, key: &DocumentKey) -> Result<Vec<Link>> {
+    let mut result = db.query(format!("
+        SELECT {} FROM mcp_doc_links
+        WHERE out = type::thing('mcp_doc_addresses', [$project, $phase, $name])
+        ORDER BY project, phase, name, line
+    ", LINK_FIELDS))
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .await?;
+    Ok(result.take(0)?)
+}
+
+#[derive(Debug, Clone)]
+pub struct BrokenLink {
+    pub link: Link,
+    pub problem: String,
+}
+
+/// Rebuild the whole link graph, then list the links (from documents of `project`, or all) that do not resolve
+pub async fn check(db: &Database, project: Option<String>) -> Result<(usize, Vec<BrokenLink>)> {
+    let total = refresh(db, None).await?;
+    let mut result = db.query(format!("
+        SELECT {} FROM mcp_doc_links
+        WHERE $project IS NONE OR in.project_id.name = $project
+        ORDER BY project, phase, name, line;
+        SELECT VALUE name FROM mcp_projects;
+        SELECT VALUE [project_id.name, sdlc_phase, name] FROM mcp_documentation;
+    ", LINK_FIELDS))
+        .bind(("project", project))
+        .await?;
+    let links: Vec<Link> = result.take(0)?;
+    let projects: HashSet<String> = result.take::<Vec<String>>(1)?.into_iter().collect();
+    let documents: HashSet<Vec<String>> = result.take::<Vec<Vec<String>>>(2)?.into_iter().collect();
+
+    let mut broken = Vec::new();
+    for link in links {
+        let Some(mut problem) = problem(&link.address, &projects, &documents) else { continue };
+        if link.address.len() == 3 {
+            let key = DocumentKey { project: link.address[0].clone(), phase: link.address[1].clone(), name: link.address[2].clone() };
+            if let Some(whereabouts) = whereabouts(db, &key).await? {
+                problem = format!("{}; it {}", problem, whereabouts);
+            }
+        }
+        broken.push(BrokenLink { link, problem });
+    }
+    Ok((total, broken))
+}
+
+/// What became of the document last seen at `key`, from its history
+async fn whereabouts(db: &Database, key: &DocumentKey) -> Result<Option<String>> {
+    let mut result = db.query("
+        LET $doc = (SELECT document, created_at FROM mcp_documentation_history
+            WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name
+            ORDER BY created_at DESC LIMIT 1)[0].document;
+        RETURN IF $doc IS NONE THEN NONE
+            ELSE (SELECT VALUE 'kyx://' + project_id.name + '/' + sdlc_phase + '/' + name FROM ONLY $doc) OR 'deleted' END;
+    ")
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .await?;
+    let found: Option<String> = result.take(1)?;
+    Ok(found.map(|f| if f == "deleted" { "was deleted".to_string() } else { format!("was moved to `{}`", f) }))
+}
+
+/// Warnings for removed documents that other documents still link to; empty when there are none
+pub async fn removal_warnings(db: &Database, removals: &[Removal]) -> Result<String> {
+    let mut output = String::new();
+    for removal in removals {
+        // Another document may have taken the address since
+        if edit::current(db, &removal.from).await?.is_some() {
+            continue;
+        }
+        let links = backlinks(db, &removal.from).await?;
+        if links.is_empty() {
+            continue;
+        }
+        let what = match &removal.to {
+            Some(to) => format!("was moved to `{}`", to.uri()),
+            None => "was deleted".to_string(),
+        };
+        log::warn!("⚠️ {} {} but {} links still point to it", removal.from.uri(), what, links.len());
+        output.push_str(&format!("- ⚠️ `{}` {}, but {} link(s) still point to it:\n", removal.from.uri(), what, links.len()));
+        for link in &links {
+            output.push_str(&format!("  - `{}` line {}: `{}`\n", link.source().uri(), link.line, link.target));
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(project: &str, phase: &str, name: &str) -> DocumentKey {
+        DocumentKey { project: project.to_string(), phase: phase.to_string(), name: name.to_string() }
+    }
+
+    #[test]
+    fn test_extract_references() {
+        let content = "\
+See kyx://kyx-kernel/design/architecture#data-flow.
+Schema: [schema](database-schema.md) and [PRD](../planning/prd \"PRD\").
+[other]: ../../kyx-signal/design/architecture
+Ignored: [site](https://example.com), [top](#intro), [file](src/core/mod.rs), `kyx://example/uri`
+```
+kyx://kyx-kernel/design/in-a-code-block
+```
+Phase: kyx://kyx-kernel/planning";
+        let found: Vec<(usize, String, Option<String>)> = extract(content, &key("kyx-kernel", "design", "prd"))
+            .into_iter()
+            .map(|r| (r.line, address_uri(&r.address), r.anchor))
+            .collect();
+        assert_eq!(found, vec![
+            (1, "kyx://kyx-kernel/design/architecture".to_string(), Some("data-flow".to_string())),
+            (2, "kyx://kyx-kernel/design/database-schema".to_string(), None),
+            (2, "kyx://kyx-kernel/planning/prd".to_string(), None),
+            (3, "kyx://kyx-signal/design/architecture".to_string(), None),
+            (8, "kyx://kyx-kernel/planning".to_string(), None),
+        ]);
+    }
+
+    #[test]
+    fn test_problem() {
+        let projects: HashSet<String> = ["kyx-kernel".to_string()].into_iter().collect();
+        let documents: HashSet<Vec<String>> = [vec!["kyx-kernel".to_string(), "design".to_string(), "architecture".to_string()]].into_iter().collect();
+        let check = |uri: &str| problem(&parse_uri(uri).unwrap().0, &projects, &documents);
+
+        assert_eq!(check("kyx://kyx-kernel/design/architecture"), None);
+        assert_eq!(check("kyx://kyx-kernel/design"), None);
+        assert_eq!(check("kyx://kyx-kernel/design/prd").as_deref(), Some("document does not exist"));
+        assert!(check("kyx://kyx-kernel/desing/architecture").unwrap().starts_with("phase 'desing' does not exist"));
+        assert_eq!(check("kyx://kyx-core/design").as_deref(), Some("project 'kyx-core' does not exist"));
+    }
+}
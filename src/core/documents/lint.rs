@@ -0,0 +1,201 @@
+use crate::core::database::Database;
+use crate::core::documents::DocumentKey;
+use crate::core::documents::markdown::{matches_path, outline, parse_heading_path};
+use anyhow::Result;
+use serde::Deserialize;
+
+/// A row of `mcp_document_types`: the structure documents of one kind must follow
+#[derive(Debug, Clone, Deserialize)]
+pub struct DocumentType {
+    pub name: String,
+    /// Applies to documents in this phase; every phase when unset
+    pub sdlc_phase: Option<String>,
+    /// Applies to documents with these names; every document in the phase when empty
+    #[serde(default)]
+    pub doc_names: Vec<String>,
+    /// Heading paths, matched like `patch-document` headings
+    #[serde(default)]
+    pub required_headings: Vec<String>,
+    #[serde(default)]
+    pub required_metadata: Vec<String>,
+    pub max_lines: Option<usize>,
+    pub max_chars: Option<usize>,
+    /// `warn` only reports; `block` also rejects update-document / patch-document writes
+    pub enforcement: String,
+}
+
+impl DocumentType {
+    pub fn applies_to(&self, phase: &str, name: &str) -> bool {
+        self.sdlc_phase.as_deref().is_none_or(|p| p == phase)
+            && (self.doc_names.is_empty() || self.doc_names.iter().any(|n| n == name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// 1-based; `None` for document-wide problems such as a missing heading
+    pub line: Option<usize>,
+    pub message: String,
+    /// The document type that was violated
+    pub rule: String,
+    pub blocking: bool,
+}
+
+/// Check one document against one type
+fn check(doc_type: &DocumentType, content: &str, metadata: &serde_json::Value) -> Vec<Violation> {
+    let mut found: Vec<(Option<usize>, String)> = Vec::new();
+    let headings = outline(content);
+    let lines: Vec<&str> = content.lines().collect();
+
+    for required in &doc_type.required_headings {
+        let path = parse_heading_path(required);
+        match headings.iter().find(|h| matches_path(h, &path)) {
+            None => found.push((None, format!("Missing required heading '{}'", required))),
+            Some(h) if lines[h.line + 1..h.end].iter().all(|l| l.trim().is_empty()) => {
+                found.push((Some(h.line + 1), format!("Required section '{}' is empty", h.path_label())));
+            }
+            Some(_) => {}
+        }
+    }
+    for key in &doc_type.required_metadata {
+        if metadata.get(key).is_none_or(|v| v.is_null() || v.as_str() == Some("")) {
+            found.push((None, format!("Missing required metadata key '{}'", key)));
+        }
+    }
+    if let Some(max) = doc_type.max_lines.filter(|max| lines.len() > *max) {
+        found.push((Some(max + 1), format!("Document has {} lines; the limit is {}", lines.len(), max)));
+    }
+    if let Some(max) = doc_type.max_chars {
+        let total = content.chars().count();
+        if total > max {
+            // The line on which the limit is crossed
+            let mut seen = 0;
+            let line = lines.iter().position(|l| { seen += l.chars().count() + 1; seen > max }).map(|i| i + 1);
+            found.push((line, format!("Document has {} characters; the limit is {}", total, max)));
+        }
+    }
+
+    found.into_iter()
+        .map(|(line, message)| Violation { line, message, rule: doc_type.name.clone(), blocking: doc_type.enforcement == "block" })
+        .collect()
+}
+
+/// Violations of every type that applies to the document, document-wide ones first, then by line
+pub fn validate(types: &[DocumentType], phase: &str, name: &str, content: &str, metadata: &serde_json::Value) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = types.iter()
+        .filter(|t| t.applies_to(phase, name))
+        .flat_map(|t| check(t, content, metadata))
+        .collect();
+    violations.sort_by_key(|v| v.line);
+    violations
+}
+
+pub async fn load_types(db: &Database) -> Result<Vec<DocumentType>> {
+    let mut result = db.query("
+        SELECT name, sdlc_phase, doc_names, required_headings, required_metadata, max_lines, max_chars, enforcement
+        FROM mcp_document_types WHERE active = true ORDER BY name
+    ").await?;
+    Ok(result.take(0)?)
+}
+
+#[derive(Debug, Clone)]
+pub struct DocumentReport {
+    pub key: DocumentKey,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentRow {
+    project: Option<String>,
+    phase: String,
+    name: String,
+    content: String,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+/// Which documents to lint
+#[derive(Debug, Clone, Default)]
+pub struct LintScope {
+    pub project: Option<String>,
+    pub phase: Option<String>,
+    pub name: Option<String>,
+    /// Record IDs, e.g. the documents a snapshot just changed
+    pub ids: Option<Vec<String>>,
+}
+
+/// Lint every document in scope; documents without violations are included with an empty list
+pub async fn lint(db: &Database, scope: LintScope) -> Result<Vec<DocumentReport>> {
+    let types = load_types(db).await?;
+    let mut result = db.query("
+        SELECT project_id.name AS project, sdlc_phase AS phase, name, content, metadata
+        FROM mcp_documentation
+        WHERE ($project IS NONE OR project_id.name = $project)
+            AND ($phase IS NONE OR sdlc_phase = $phase)
+            AND ($name IS NONE OR name = $name)
+            AND ($ids IS NONE OR id IN array::map($ids, |$id| <record<mcp_documentation>> $id))
+        ORDER BY project, phase, name
+    ")
+        .bind(("project", scope.project))
+        .bind(("phase", scope.phase))
+        .bind(("name", scope.name))
+        .bind(("ids", scope.ids))
+        .await?;
+    let rows: Vec<DocumentRow> = result.take(0)?;
+
+    Ok(rows.into_iter().map(|row| DocumentReport {
+        violations: validate(&types, &row.phase, &row.name, &row.content, &row.metadata),
+        key: DocumentKey { project: row.project.unwrap_or_else(|| "unknown".to_string()), phase: row.phase, name: row.name },
+    }).collect())
+}
+
+/// One bullet per violation
+pub fn format_violations(violations: &[Violation]) -> String {
+    violations.iter().map(|v| format!(
+        "- {} {}{} (`{}`)\n",
+        if v.blocking { "❌" } else { "⚠️" },
+        v.line.map(|l| format!("line {}: ", l)).unwrap_or_default(),
+        v.message,
+        v.rule,
+    )).collect()
+}
+
+/// Documents with violations, each under its URI
+pub fn format_reports(reports: &[DocumentReport]) -> String {
+    reports.iter()
+        .filter(|r| !r.violations.is_empty())
+        .map(|r| format!("#### `{}`\n{}\n", r.key.uri(), format_violations(&r.violations)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_reports_lines() {
+        let prd = DocumentType {
+            name: "prd".to_string(),
+            sdlc_phase: Some("planning".to_string()),
+            doc_names: vec!["prd".to_string()],
+            required_headings: vec!["Objective".to_string(), "Core Features".to_string()],
+            required_metadata: vec!["owner".to_string()],
+            max_lines: Some(5),
+            max_chars: None,
+            enforcement: "warn".to_string(),
+        };
+        let content = "# Kyx PRD\n\n## Objective\n\n## Scope\n\nEverything.\n";
+        let violations = validate(std::slice::from_ref(&prd), "planning", "prd", content, &json!({ "owner": "" }));
+        let summary: Vec<(Option<usize>, &str)> = violations.iter().map(|v| (v.line, v.message.as_str())).collect();
+        assert_eq!(summary, vec![
+            (None, "Missing required heading 'Core Features'"),
+            (None, "Missing required metadata key 'owner'"),
+            (Some(3), "Required section 'Kyx PRD > Objective' is empty"),
+            (Some(6), "Document has 7 lines; the limit is 5"),
+        ]);
+
+        assert!(validate(std::slice::from_ref(&prd), "design", "prd", content, &json!({})).is_empty());
+        assert!(!prd.applies_to("planning", "roadmap"));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::core::indexing::chunker::parse_heading;
+
+/// A heading and the extent of its section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+    /// Headings from the document root down to this one
+    pub path: Vec<String>,
+    /// 0-based line of the heading
+    pub line: usize,
+    /// Line after the section, subsections included (the next heading of the same or a higher level)
+    pub end: usize,
+}
+
+impl Heading {
+    pub fn path_label(&self) -> String {
+        self.path.join(" > ")
+    }
+}
+
+/// Headings in document order; `#` lines inside fenced code blocks are not headings
+pub fn outline(markdown: &str) -> Vec<Heading> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some((level, text)) = parse_heading(line) {
+            stack.retain(|(l, _)| *l < level);
+            stack.push((level, text.clone()));
+            headings.push(Heading { level, text, path: stack.iter().map(|(_, t)| t.clone()).collect(), line: i, end: lines.len() });
+        }
+    }
+
+    for i in 0..headings.len() {
+        if let Some(next) = headings[i + 1..].iter().find(|h| h.level <= headings[i].level) {
+            headings[i].end = next.line;
+        }
+    }
+    headings
+}
+
+/// `"Architecture > Data Flow"` → `["Architecture", "Data Flow"]`; leading `#`s are ignored
+pub fn parse_heading_path(path: &str) -> Vec<String> {
+    path.split('>')
+        .map(|segment| segment.trim().trim_start_matches('#').trim().to_string())
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Whether `heading` is addressed by `path`: the path matches the end of the heading's own path,
+/// case-insensitively, so `["Data Flow"]` finds every "Data Flow" section
+pub fn matches_path(heading: &Heading, path: &[String]) -> bool {
+    !path.is_empty()
+        && path.len() <= heading.path.len()
+        && heading.path[heading.path.len() - path.len()..].iter().zip(path)
+            .all(|(a, b)| a.to_lowercase() == b.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_sections() {
+        let doc = "Intro\n# A\ntext\n## B\n```\n# not a heading\n```\n## C\n# D\n";
+        let headings = outline(doc);
+        let summary: Vec<(String, usize, usize)> = headings.iter().map(|h| (h.path_label(), h.line, h.end)).collect();
+        assert_eq!(summary, vec![
+            ("A".to_string(), 1, 8),
+            ("A > B".to_string(), 3, 7),
+            ("A > C".to_string(), 7, 8),
+            ("D".to_string(), 8, 9),
+        ]);
+
+        assert_eq!(parse_heading_path("## A > b "), vec!["A", "b"]);
+        assert!(matches_path(&headings[1], &parse_heading_path("a > B")));
+        assert!(matches_path(&headings[1], &parse_heading_path("B")));
+        assert!(!matches_path(&headings[1], &parse_heading_path("D > B")));
+    }
+}
@@ -0,0 +1,31 @@
+pub mod diff;
+pub mod edit;
+pub mod history;
+pub mod links;
+pub mod lint;
+pub mod markdown;
+pub mod patch;
+pub mod review;
+
+use anyhow::{Result, anyhow};
+
+/// A document addressed the way tools and `kyx://` URIs do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentKey {
+    pub project: String,
+    pub phase: String,
+    pub name: String,
+}
+
+impl DocumentKey {
+    /// From the `project`, `phase` and `name` tool arguments
+    pub fn from_arguments(arguments: &serde_json::Value) -> Result<Self> {
+        let arg = |key: &str| arguments.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(String::from)
+            .ok_or_else(|| anyhow!("Missing '{}' argument", key));
+        Ok(Self { project: arg("project")?, phase: arg("phase")?, name: arg("name")? })
+    }
+
+    pub fn uri(&self) -> String {
+        format!("kyx://{}/{}/{}", self.project, self.phase, self.name)
+    }
+}
@@ -0,0 +1,162 @@
+use crate::core::documents::markdown::{Heading, matches_path, outline};
+use anyhow::{Result, anyhow, bail};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOperation {
+    /// Everything under the heading, subsections included; the heading stays
+    Replace,
+    /// At the end of the section's own text, before its subsections
+    Append,
+    /// After the section and its subsections, e.g. a new sibling section
+    InsertAfter,
+    /// The heading and everything under it
+    Delete,
+}
+
+impl PatchOperation {
+    pub fn parse(operation: &str) -> Result<Self> {
+        match operation {
+            "replace" => Ok(Self::Replace),
+            "append" => Ok(Self::Append),
+            "insert-after" | "insert_after" => Ok(Self::InsertAfter),
+            "delete" => Ok(Self::Delete),
+            other => Err(anyhow!("Unknown operation '{}'; use replace, append, insert-after or delete", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Replace => "replace",
+            Self::Append => "append",
+            Self::InsertAfter => "insert-after",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// The patched document and the section it touched
+#[derive(Debug, Clone)]
+pub struct Patched {
+    pub content: String,
+    /// Full heading path of the section
+    pub section: String,
+    /// 1-based line of its heading in the original document
+    pub line: usize,
+}
+
+/// The one section `path` addresses; not found and ambiguous paths are errors
+fn find_section(headings: &[Heading], path: &[String]) -> Result<Heading> {
+    let label = path.join(" > ");
+    let matches: Vec<&Heading> = headings.iter().filter(|h| matches_path(h, path)).collect();
+    match matches.as_slice() {
+        [heading] => Ok((*heading).clone()),
+        [] => bail!(
+            "Heading '{}' not found. Sections: {}",
+            label,
+            if headings.is_empty() { "none".to_string() } else { headings.iter().map(|h| format!("'{}'", h.path_label())).collect::<Vec<_>>().join(", ") }
+        ),
+        _ => bail!(
+            "Heading '{}' is ambiguous; it matches {}. Give more of the heading path.",
+            label,
+            matches.iter().map(|h| format!("'{}' (line {})", h.path_label(), h.line + 1)).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// `before`, `middle` and `after` joined with exactly one blank line between non-empty blocks
+fn splice(before: &[&str], middle: &[&str], after: &[&str]) -> Vec<String> {
+    let is_blank = |l: &&str| l.trim().is_empty();
+    let before = &before[..before.iter().rposition(|l| !is_blank(l)).map_or(0, |i| i + 1)];
+    let middle = &middle[middle.iter().position(|l| !is_blank(l)).unwrap_or(middle.len())..];
+    let middle = &middle[..middle.iter().rposition(|l| !is_blank(l)).map_or(0, |i| i + 1)];
+    let after = &after[after.iter().position(|l| !is_blank(l)).unwrap_or(after.len())..];
+
+    let mut out: Vec<String> = Vec::new();
+    for block in [before, middle, after] {
+        if block.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        out.extend(block.iter().map(|l| l.to_string()));
+    }
+    out
+}
+
+/// Apply `operation` to the section at `path`; `content` is required except for delete
+pub fn apply(markdown: &str, path: &[String], operation: PatchOperation, content: Option<&str>) -> Result<Patched> {
+    if path.is_empty() {
+        bail!("Missing heading path");
+    }
+    let section = find_section(&outline(markdown), path)?;
+    let content = match (operation, content) {
+        (PatchOperation::Delete, _) => "",
+        (PatchOperation::Replace, Some(content)) => content,
+        (_, Some(content)) if !content.trim().is_empty() => content,
+        (_, _) => bail!("'content' is required for {}", operation.as_str()),
+    };
+
+    let lines: Vec<&str> = markdown.lines().collect();
+    let new_lines: Vec<&str> = content.lines().collect();
+    let patched = match operation {
+        PatchOperation::Replace => splice(&lines[..=section.line], &new_lines, &lines[section.end..]),
+        PatchOperation::Append => {
+            // The section's own text ends at its first subsection
+            let own_end = outline(markdown).iter()
+                .find(|h| h.line > section.line && h.line < section.end)
+                .map_or(section.end, |h| h.line);
+            splice(&lines[..own_end], &new_lines, &lines[own_end..])
+        }
+        PatchOperation::InsertAfter => splice(&lines[..section.end], &new_lines, &lines[section.end..]),
+        PatchOperation::Delete => splice(&lines[..section.line], &[], &lines[section.end..]),
+    };
+
+    let mut content = patched.join("\n");
+    if markdown.ends_with('\n') && !content.is_empty() {
+        content.push('\n');
+    }
+    Ok(Patched { content, section: section.path_label(), line: section.line + 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::documents::markdown::parse_heading_path;
+
+    const DOC: &str = "# Design\n\nIntro.\n\n## API\n\nREST.\n\n### Errors\n\nProblem JSON.\n\n## Storage\n\nSurrealDB.\n\n# Ops\n\n## Errors\n\nPage on-call.\n";
+
+    fn patch(path: &str, operation: &str, content: Option<&str>) -> Result<String> {
+        Ok(apply(DOC, &parse_heading_path(path), PatchOperation::parse(operation)?, content)?.content)
+    }
+
+    #[test]
+    fn test_operations_edit_only_the_section() {
+        assert_eq!(
+            patch("Design > Storage", "replace", Some("Postgres.\n")).unwrap(),
+            DOC.replace("SurrealDB.", "Postgres.")
+        );
+        assert_eq!(
+            patch("API", "append", Some("GraphQL too.")).unwrap(),
+            DOC.replace("REST.\n", "REST.\n\nGraphQL too.\n")
+        );
+        assert_eq!(
+            patch("Storage", "insert-after", Some("## Caching\n\nRedis.")).unwrap(),
+            DOC.replace("SurrealDB.\n", "SurrealDB.\n\n## Caching\n\nRedis.\n")
+        );
+        assert_eq!(
+            patch("Design > API", "delete", None).unwrap(),
+            "# Design\n\nIntro.\n\n## Storage\n\nSurrealDB.\n\n# Ops\n\n## Errors\n\nPage on-call.\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_and_ambiguous_headings() {
+        let err = patch("Errors", "delete", None).unwrap_err().to_string();
+        assert!(err.contains("ambiguous") && err.contains("'Design > API > Errors' (line 9)") && err.contains("'Ops > Errors' (line 19)"), "{}", err);
+        assert!(patch("Ops > Errors", "delete", None).is_ok());
+        assert!(patch("Deployment", "replace", Some("x")).unwrap_err().to_string().contains("not found"));
+        assert!(patch("Storage", "append", Some("  ")).is_err());
+        assert!(patch("Storage", "rename", Some("x")).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::core::config::ANONYMOUS_PRINCIPAL;
+use crate::core::database::Database;
+use crate::core::documents::DocumentKey;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+/// Where a document's working copy stands in the review lifecycle
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReviewState {
+    pub version: i64,
+    /// draft, in_review, published or deprecated
+    pub status: String,
+    /// Assigned at the last submission
+    #[serde(default)]
+    pub reviewers: Vec<String>,
+    pub submitted_by: Option<String>,
+    pub published_version: Option<i64>,
+    /// Status of the published edition, if the document was ever approved
+    pub published_status: Option<String>,
+}
+
+/// Submitting needs a draft and at least one reviewer other than the submitter.
+/// Self-review is only allowed without authentication, where everyone is `anonymous`.
+pub fn check_submit(uri: &str, state: &ReviewState, reviewers: &[String], principal: &str) -> std::result::Result<(), String> {
+    match state.status.as_str() {
+        "draft" => {}
+        "in_review" => return Err(format!(
+            "`{}` version {} is already in review (submitted by {}; reviewers: {}).",
+            uri, state.version, state.submitted_by.as_deref().unwrap_or("unknown"), state.reviewers.join(", ")
+        )),
+        "published" => return Err(format!("`{}` version {} is already published; edit it to start a new draft.", uri, state.version)),
+        status => return Err(format!("`{}` is {}; edit it to start a new draft.", uri, status)),
+    }
+    if reviewers.is_empty() {
+        return Err("Name at least one reviewer in 'reviewers'.".to_string());
+    }
+    if principal != ANONYMOUS_PRINCIPAL && reviewers.iter().any(|r| r == principal) {
+        return Err(format!("You cannot review your own submission: remove '{}' from 'reviewers'.", principal));
+    }
+    Ok(())
+}
+
+/// Approving or rejecting needs a document in review and one of its assigned reviewers.
+/// A rejection has to say what to change.
+pub fn check_review(uri: &str, state: &ReviewState, principal: &str, approve: bool, note: Option<&str>) -> std::result::Result<(), String> {
+    if state.status != "in_review" {
+        return Err(format!("`{}` is not in review (status: {}); submit it with submit-document first.", uri, state.status));
+    }
+    if !state.reviewers.iter().any(|r| r == principal) {
+        return Err(format!(
+            "Only the assigned reviewers ({}) can {} `{}`.",
+            state.reviewers.join(", "), if approve { "approve" } else { "reject" }, uri
+        ));
+    }
+    if !approve && note.is_none_or(|n| n.trim().is_empty()) {
+        return Err("Say what has to change in 'note' when rejecting.".to_string());
+    }
+    Ok(())
+}
+
+pub fn check_deprecate(uri: &str, state: &ReviewState) -> std::result::Result<(), String> {
+    match state.published_status.as_deref() {
+        Some("published") => Ok(()),
+        Some(_) => Err(format!("`{}` is already deprecated.", uri)),
+        None => Err(format!("`{}` has no published edition to deprecate.", uri)),
+    }
+}
+
+const KEY_FILTER: &str = "project_id.name = $project AND sdlc_phase = $phase AND name = $name";
+
+pub async fn state(db: &Database, key: &DocumentKey) -> Result<Option<ReviewState>> {
+    let mut result = db.query(format!("
+        SELECT version, status, reviewers, submitted_by, published_version,
+            (SELECT VALUE status FROM ONLY type::thing('mcp_documentation_published', meta::id($parent.id))) AS published_status
+        FROM mcp_documentation WHERE {}
+    ", KEY_FILTER))
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .await?;
+    Ok(result.take(0)?)
+}
+
+/// Run a review write against the document at `version`. The last statement returns the IDs it
+/// changed; none means the document moved on meanwhile.
+async fn transition(db: &Database, key: &DocumentKey, version: i64, sql: &str, principal: &str, note: Option<&str>, reviewers: Vec<String>) -> Result<()> {
+    let mut result = db.query(sql)
+        .bind(("project", key.project.clone()))
+        .bind(("phase", key.phase.clone()))
+        .bind(("name", key.name.clone()))
+        .bind(("version", version))
+        .bind(("principal", principal.to_string()))
+        .bind(("note", note.map(String::from)))
+        .bind(("reviewers", reviewers))
+        .await?
+        .check()
+        .map_err(|e| anyhow!("Failed to update the review state of {}: {}", key.uri(), e))?;
+    let last = result.num_statements() - 1;
+    let changed: Vec<surrealdb::RecordId> = result.take(last)?;
+    if changed.is_empty() {
+        return Err(anyhow!("{} changed while this request was running; read it again and retry", key.uri()));
+    }
+    Ok(())
+}
+
+pub async fn submit(db: &Database, key: &DocumentKey, version: i64, reviewers: Vec<String>, principal: &str, note: Option<&str>) -> Result<()> {
+    transition(db, key, version, &format!("
+        UPDATE mcp_documentation SET status = 'in_review', reviewers = $reviewers, submitted_by = $principal,
+            submitted_at = time::now(), submission_note = $note
+            WHERE {} AND status = 'draft' AND version = $version
+            RETURN VALUE id;
+    ", KEY_FILTER), principal, note, reviewers).await
+}
+
+/// Copy the reviewed version into `mcp_documentation_published`, replacing the previous edition
+pub async fn approve(db: &Database, key: &DocumentKey, version: i64, principal: &str, note: Option<&str>) -> Result<()> {
+    transition(db, key, version, &format!("
+        BEGIN TRANSACTION;
+        LET $doc = (SELECT * FROM mcp_documentation WHERE {key} AND status = 'in_review' AND version = $version)[0];
+        IF $doc IS NOT NONE {{
+            UPSERT type::thing('mcp_documentation_published', meta::id($doc.id)) CONTENT {{
+                document: $doc.id,
+                project_id: $doc.project_id,
+                sdlc_phase: $doc.sdlc_phase,
+                name: $doc.name,
+                title: $doc.title,
+                content: $doc.content,
+                mimeType: $doc.mimeType,
+                metadata: $doc.metadata,
+                version: $doc.version,
+                status: 'published',
+                approved_by: $principal,
+                approval_note: $note,
+                published_at: time::now()
+            }};
+        }};
+        UPDATE mcp_documentation SET status = 'published', published_version = version, reviewed_by = $principal,
+            reviewed_at = time::now(), review_note = $note
+            WHERE {key} AND status = 'in_review' AND version = $version
+            RETURN VALUE id;
+        COMMIT TRANSACTION;
+    ", key = KEY_FILTER), principal, note, Vec::new()).await
+}
+
+pub async fn reject(db: &Database, key: &DocumentKey, version: i64, principal: &str, note: &str) -> Result<()> {
+    transition(db, key, version, &format!("
+        UPDATE mcp_documentation SET status = 'draft', reviewed_by = $principal, reviewed_at = time::now(),
+            review_note = $note, submitted_by = NONE, submitted_at = NONE, submission_note = NONE
+            WHERE {} AND status = 'in_review' AND version = $version
+            RETURN VALUE id;
+    ", KEY_FILTER), principal, Some(note), Vec::new()).await
+}
+
+/// Deprecate the published edition. The working copy is always written too, even when it is a
+/// newer draft and keeps its status, so the change reaches the search index.
+pub async fn deprecate(db: &Database, key: &DocumentKey, version: i64, principal: &str, note: Option<&str>) -> Result<()> {
+    transition(db, key, version, &format!("
+        BEGIN TRANSACTION;
+        LET $doc = (SELECT id FROM mcp_documentation WHERE {key} AND version = $version)[0];
+        IF $doc IS NOT NONE {{
+            UPDATE type::thing('mcp_documentation_published', meta::id($doc.id)) SET status = 'deprecated',
+                deprecated_by = $principal, deprecated_at = time::now(), deprecation_note = $note
+                WHERE status = 'published';
+        }};
+        UPDATE mcp_documentation SET status = IF status = 'published' THEN 'deprecated' ELSE status END
+            WHERE {key} AND version = $version
+            RETURN VALUE id;
+        COMMIT TRANSACTION;
+    ", key = KEY_FILTER), principal, note, Vec::new()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(status: &str, reviewers: &[&str]) -> ReviewState {
+        ReviewState {
+            version: 3,
+            status: status.to_string(),
+            reviewers: reviewers.iter().map(|r| r.to_string()).collect(),
+            submitted_by: None,
+            published_version: None,
+            published_status: None,
+        }
+    }
+
+    #[test]
+    fn test_review_transitions() {
+        let uri = "kyx://kyx-kernel/planning/prd";
+        let alice = vec!["alice".to_string()];
+        assert!(check_submit(uri, &state("draft", &[]), &alice, "bob").is_ok());
+        assert!(check_submit(uri, &state("draft", &[]), &[], "bob").is_err());
+        assert!(check_submit(uri, &state("draft", &[]), &alice, "alice").is_err());
+        assert!(check_submit(uri, &state("draft", &[]), &[ANONYMOUS_PRINCIPAL.to_string()], ANONYMOUS_PRINCIPAL).is_ok());
+        assert!(check_submit(uri, &state("in_review", &["alice"]), &alice, "bob").is_err());
+
+        let in_review = state("in_review", &["alice"]);
+        assert!(check_review(uri, &in_review, "alice", true, None).is_ok());
+        assert!(check_review(uri, &in_review, "bob", true, None).is_err());
+        assert_eq!(check_review(uri, &in_review, "alice", false, Some(" ")), Err("Say what has to change in 'note' when rejecting.".to_string()));
+        assert!(check_review(uri, &state("draft", &["alice"]), "alice", true, None).is_err());
+
+        assert!(check_deprecate(uri, &state("draft", &[])).is_err());
+        let published = ReviewState { published_status: Some("published".to_string()), ..state("draft", &[]) };
+        assert!(check_deprecate(uri, &published).is_ok());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::core::database::Database;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Cache key: the embedder's fingerprint and the exact text, so a vector is only reused
+/// in the vector space it was computed in
+pub fn cache_key(fingerprint: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(fingerprint.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+#[derive(Debug, Deserialize)]
+struct CachedRow {
+    key: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheEntry {
+    key: String,
+    vector: Vec<f32>,
+}
+
+/// Embeddings persisted in `mcp_embedding_cache`, keyed by `cache_key`
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    db: Database,
+}
+
+impl EmbeddingCache {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_many(&self, keys: &[String]) -> Result<HashMap<String, Vec<f32>>> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut result = self.db
+            .query("SELECT meta::id(id) AS key, vector FROM array::map($keys, |$k| type::thing('mcp_embedding_cache', $k))")
+            .bind(("keys", keys.to_vec()))
+            .await?;
+        let rows: Vec<CachedRow> = result.take(0)?;
+        Ok(rows.into_iter().map(|r| (r.key, r.vector)).collect())
+    }
+
+    /// `entries`: (key, vector)
+    pub async fn put_many(&self, fingerprint: &str, entries: Vec<(String, Vec<f32>)>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let entries: Vec<CacheEntry> = entries.into_iter().map(|(key, vector)| CacheEntry { key, vector }).collect();
+        self.db.query("
+            FOR $e IN $entries {
+                UPSERT type::thing('mcp_embedding_cache', $e.key) SET vector = $e.vector, embedder = $embedder;
+            };
+        ")
+        .bind(("entries", entries))
+        .bind(("embedder", fingerprint.to_string()))
+        .await?
+        .check()?;
+        Ok(())
+    }
+}
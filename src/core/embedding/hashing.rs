@@ -0,0 +1,102 @@
+use crate::core::embedding::{EmbeddingBatch, EmbeddingProvider, estimate_tokens};
+use anyhow::Result;
+use async_trait::async_trait;
+
+pub const DEFAULT_DIMENSIONS: u64 = 384;
+const MODEL: &str = "feature-hashing-v1";
+
+/// Offline embedder: words and character trigrams are hashed into signed buckets and the
+/// vector is L2-normalised. Deterministic across runs and machines, so it suits tests and
+/// air-gapped installs; it captures lexical overlap, not meaning.
+pub struct HashingProvider {
+    dimensions: u64,
+}
+
+impl HashingProvider {
+    pub fn new(dimensions: u64) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    pub fn embed_sync(&self, text: &str) -> Vec<f32> {
+        let dims = self.dimensions as usize;
+        let mut vector = vec![0f32; dims];
+        let mut add = |feature: &str, weight: f32| {
+            let h = fnv1a(feature.as_bytes());
+            let sign = if h >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(h % dims as u64) as usize] += sign * weight;
+        };
+
+        let lower = text.to_lowercase();
+        for word in lower.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            add(word, 1.0);
+            // Trigrams make related word forms (and unsegmented Thai) overlap
+            let chars: Vec<char> = format!("<{}>", word).chars().collect();
+            for gram in chars.windows(3) {
+                add(&gram.iter().collect::<String>(), 0.5);
+            }
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        } else {
+            // Cosine distance is undefined for the zero vector
+            vector[0] = 1.0;
+        }
+        vector
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| (h ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashingProvider {
+    fn name(&self) -> &str {
+        "hashing"
+    }
+
+    fn model(&self) -> &str {
+        MODEL
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.dimensions
+    }
+
+    fn max_batch_size(&self) -> usize {
+        usize::MAX
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<EmbeddingBatch> {
+        Ok(EmbeddingBatch {
+            vectors: texts.iter().map(|t| self.embed_sync(t)).collect(),
+            tokens: texts.iter().map(|t| estimate_tokens(t)).sum(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn test_hashing_is_deterministic_and_normalised() {
+        let provider = HashingProvider::new(256);
+        let a = provider.embed_sync("JWT token rotation policy");
+        assert_eq!(a.len(), 256);
+        assert_eq!(a, provider.embed_sync("JWT token rotation policy"));
+        assert!((cosine(&a, &a) - 1.0).abs() < 1e-5);
+
+        let related = provider.embed_sync("rotation of JWT tokens");
+        let unrelated = provider.embed_sync("Kubernetes deployment runbook");
+        assert!(cosine(&a, &related) > cosine(&a, &unrelated));
+
+        assert_eq!(provider.embed_sync("")[0], 1.0);
+    }
+}
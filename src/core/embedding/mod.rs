@@ -0,0 +1,279 @@
+pub mod cache;
+pub mod hashing;
+pub mod openai;
+
+use crate::core::database::Database;
+use crate::core::metrics::{EMBEDDING_CACHE_HITS_TOTAL, EMBEDDING_CACHE_MISSES_TOTAL};
+use crate::core::rate_limiter::RateLimiter;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cache::{EmbeddingCache, cache_key};
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+pub use hashing::HashingProvider;
+pub use openai::{OpenAiCompatibleProvider, OpenAiProvider};
+
+/// Upper bound on estimated tokens per request (OpenAI allows 300k per call)
+const MAX_BATCH_TOKENS: u32 = 250_000;
+
+/// An embedding vector with the number of provider tokens it cost
+#[derive(Debug, Clone)]
+pub struct Embedding {
+    pub vector: Vec<f32>,
+    pub tokens: u32,
+}
+
+/// Vectors in input order, with the tokens the whole batch cost
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingBatch {
+    pub vectors: Vec<Vec<f32>>,
+    pub tokens: u32,
+}
+
+/// Rough token estimate (~4 chars per token) when the provider does not report usage
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+/// Turns text into vectors of a fixed size
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Provider kind, e.g. `openai` or `hashing`
+    fn name(&self) -> &str;
+    fn model(&self) -> &str;
+    /// Length of every vector this provider returns; vector collections are sized from it
+    fn dimensions(&self) -> u64;
+
+    /// Most inputs accepted in one `embed_batch` call
+    fn max_batch_size(&self) -> usize {
+        64
+    }
+
+    /// One vector per input, in input order
+    async fn embed_batch(&self, texts: &[String]) -> Result<EmbeddingBatch>;
+
+    /// Identifies the vector space; vectors are only comparable when this matches
+    fn fingerprint(&self) -> String {
+        format!("{}:{}:{}", self.name(), self.model(), self.dimensions())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ProviderKind {
+    OpenAi,
+    OpenAiCompatible,
+    Hashing,
+}
+
+impl ProviderKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "openai" => Some(Self::OpenAi),
+            "openai-compatible" | "openai_compatible" | "compatible" => Some(Self::OpenAiCompatible),
+            "hashing" | "local" => Some(Self::Hashing),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingConfig {
+    pub provider: ProviderKind,
+    pub model: String,
+    pub api_key: Option<String>,
+    /// Base URL of an OpenAI-compatible server, e.g. `http://localhost:11434/v1`
+    pub base_url: Option<String>,
+    /// Overrides the model's native size (sent as `dimensions` to OpenAI)
+    pub dimensions: Option<u64>,
+    /// Inputs per embeddings request
+    pub batch_size: usize,
+    /// Embeddings requests in flight at once
+    pub concurrency: usize,
+    /// Reuse vectors of previously embedded text from `mcp_embedding_cache`
+    pub cache: bool,
+}
+
+/// Native vector size of well-known OpenAI models
+pub fn known_dimensions(model: &str) -> Option<u64> {
+    match model {
+        "text-embedding-3-small" | "text-embedding-ada-002" => Some(1536),
+        "text-embedding-3-large" => Some(3072),
+        _ => None,
+    }
+}
+
+pub fn build_provider(config: &EmbeddingConfig, rate_limiter: Arc<RateLimiter>) -> Result<Arc<dyn EmbeddingProvider>> {
+    Ok(match config.provider {
+        ProviderKind::OpenAi => Arc::new(OpenAiProvider::new(config, rate_limiter)?),
+        ProviderKind::OpenAiCompatible => {
+            let base_url = config.base_url.clone()
+                .ok_or_else(|| anyhow!("EMBEDDING_BASE_URL is required for the openai-compatible provider"))?;
+            Arc::new(OpenAiCompatibleProvider::new(config, base_url, rate_limiter)?)
+        }
+        ProviderKind::Hashing => Arc::new(HashingProvider::new(config.dimensions.unwrap_or(hashing::DEFAULT_DIMENSIONS))),
+    })
+}
+
+/// Group input indexes into requests bounded by item count and estimated tokens
+fn plan_batches(texts: &[&str], max_items: usize, max_tokens: u32) -> Vec<Vec<usize>> {
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut tokens = 0;
+    for (i, text) in texts.iter().enumerate() {
+        let t = estimate_tokens(text);
+        match batches.last_mut() {
+            Some(batch) if batch.len() < max_items.max(1) && tokens + t <= max_tokens => {
+                batch.push(i);
+                tokens += t;
+            }
+            _ => {
+                batches.push(vec![i]);
+                tokens = t;
+            }
+        }
+    }
+    batches
+}
+
+/// The configured provider behind a content-hash cache, with batched, bounded-concurrency requests.
+/// Identical texts are embedded at most once.
+pub struct Embedder {
+    provider: Arc<dyn EmbeddingProvider>,
+    cache: Option<EmbeddingCache>,
+    concurrency: usize,
+}
+
+impl Embedder {
+    pub fn new(config: &EmbeddingConfig, db: Database, rate_limiter: Arc<RateLimiter>) -> Result<Self> {
+        Ok(Self {
+            provider: build_provider(config, rate_limiter)?,
+            cache: config.cache.then(|| EmbeddingCache::new(db)),
+            concurrency: config.concurrency.max(1),
+        })
+    }
+
+    pub fn dimensions(&self) -> u64 {
+        self.provider.dimensions()
+    }
+
+    pub fn fingerprint(&self) -> String {
+        self.provider.fingerprint()
+    }
+
+    pub async fn embed(&self, text: &str) -> Result<Embedding> {
+        let batch = self.embed_many(&[text.to_string()]).await?;
+        let vector = batch.vectors.into_iter().next().ok_or_else(|| anyhow!("No embedding returned"))?;
+        Ok(Embedding { vector, tokens: batch.tokens })
+    }
+
+    /// Vectors for `texts` in order; `tokens` counts only what the provider charged (cache hits are free).
+    /// Batches that succeed are cached even if another batch fails, so a retry resumes cheaply.
+    pub async fn embed_many(&self, texts: &[String]) -> Result<EmbeddingBatch> {
+        let fingerprint = self.fingerprint();
+        let keys: Vec<String> = texts.iter().map(|t| cache_key(&fingerprint, t)).collect();
+
+        let mut unique: Vec<(&str, &str)> = Vec::new();
+        let mut seen = HashSet::new();
+        for (key, text) in keys.iter().zip(texts) {
+            if seen.insert(key.as_str()) {
+                unique.push((key.as_str(), text.as_str()));
+            }
+        }
+
+        let mut resolved: HashMap<String, Vec<f32>> = match &self.cache {
+            Some(cache) => {
+                let wanted: Vec<String> = unique.iter().map(|(k, _)| k.to_string()).collect();
+                cache.get_many(&wanted).await.unwrap_or_else(|e| {
+                    log::warn!("⚠️ Embedding cache lookup failed: {}", e);
+                    HashMap::new()
+                })
+            }
+            None => HashMap::new(),
+        };
+        let misses: Vec<(&str, &str)> = unique.into_iter().filter(|(k, _)| !resolved.contains_key(*k)).collect();
+        EMBEDDING_CACHE_HITS_TOTAL.inc_by((seen.len() - misses.len()) as u64);
+        EMBEDDING_CACHE_MISSES_TOTAL.inc_by(misses.len() as u64);
+
+        let miss_texts: Vec<&str> = misses.iter().map(|(_, t)| *t).collect();
+        let batches = plan_batches(&miss_texts, self.provider.max_batch_size(), MAX_BATCH_TOKENS);
+        let results: Vec<(Vec<usize>, Result<EmbeddingBatch>)> = stream::iter(batches)
+            .map(|batch| {
+                let inputs: Vec<String> = batch.iter().map(|i| miss_texts[*i].to_string()).collect();
+                async move { (batch, self.provider.embed_batch(&inputs).await) }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut tokens = 0;
+        let mut fresh = Vec::new();
+        let mut first_error = None;
+        for (batch, result) in results {
+            match result {
+                Ok(embedded) => {
+                    tokens += embedded.tokens;
+                    for (i, vector) in batch.into_iter().zip(embedded.vectors) {
+                        fresh.push((misses[i].0.to_string(), vector));
+                    }
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.put_many(&fingerprint, fresh.clone()).await {
+                log::warn!("⚠️ Failed to store embeddings in cache: {}", e);
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+
+        resolved.extend(fresh);
+        let vectors = keys.iter()
+            .map(|k| resolved.get(k).cloned().ok_or_else(|| anyhow!("Missing embedding for input")))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(EmbeddingBatch { vectors, tokens })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_kind_and_dimensions() {
+        assert_eq!(ProviderKind::parse("OpenAI-Compatible"), Some(ProviderKind::OpenAiCompatible));
+        assert_eq!(ProviderKind::parse("local"), Some(ProviderKind::Hashing));
+        assert_eq!(ProviderKind::parse("cohere"), None);
+
+        let config = EmbeddingConfig {
+            provider: ProviderKind::Hashing,
+            model: "ignored".to_string(),
+            api_key: None,
+            base_url: None,
+            dimensions: Some(64),
+            batch_size: 64,
+            concurrency: 4,
+            cache: false,
+        };
+        let provider = build_provider(&config, Arc::new(RateLimiter::new(&Default::default()))).unwrap();
+        assert_eq!(provider.dimensions(), 64);
+        assert_eq!(provider.fingerprint(), "hashing:feature-hashing-v1:64");
+    }
+
+    #[test]
+    fn test_plan_batches() {
+        let texts = ["a".repeat(40), "b".repeat(40), "c".repeat(40), "d".repeat(400)];
+        let texts: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
+        // By count
+        assert_eq!(plan_batches(&texts, 2, u32::MAX), vec![vec![0, 1], vec![2, 3]]);
+        // By tokens (10 each, then 100)
+        assert_eq!(plan_batches(&texts, 10, 30), vec![vec![0, 1, 2], vec![3]]);
+        assert!(plan_batches(&[], 10, 30).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::core::embedding::{EmbeddingBatch, EmbeddingConfig, EmbeddingProvider, estimate_tokens, known_dimensions};
+use crate::core::metrics;
+use crate::core::rate_limiter::{RateLimiter, POLICY_EMBEDDINGS, GLOBAL_KEY};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+    usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingUsage {
+    total_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    // Configure HTTP client with proper HTTP/2 support
+    Ok(reqwest::Client::builder()
+        .pool_max_idle_per_host(10)
+        .pool_idle_timeout(std::time::Duration::from_secs(90))
+        .timeout(std::time::Duration::from_secs(60))
+        .connect_timeout(std::time::Duration::from_secs(10))
+        .build()?)
+}
+
+/// Any server speaking the OpenAI `/embeddings` API (vLLM, Ollama, LocalAI, TEI, ...)
+pub struct OpenAiCompatibleProvider {
+    http_client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    dimensions: u64,
+    /// Only sent when configured, since most self-hosted servers reject the parameter
+    request_dimensions: Option<u64>,
+    batch_size: usize,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl OpenAiCompatibleProvider {
+    /// One `/embeddings` call for all `texts` (array `input`); returns vectors in input order
+    /// and the token usage the server reported, if any
+    async fn request(&self, texts: &[String]) -> Result<(Vec<Vec<f32>>, Option<u32>)> {
+        // Check rate limit BEFORE calling the provider; a whole batch counts once
+        self.rate_limiter.check(POLICY_EMBEDDINGS, GLOBAL_KEY)?;
+
+        log::info!("🔍 Calling embeddings API at {} for {} inputs...", self.base_url, texts.len());
+
+        let mut request = self.http_client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&EmbeddingRequest { model: &self.model, input: texts, dimensions: self.request_dimensions });
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = match request.send().await {
+            Ok(r) => {
+                log::info!("✅ Embeddings API responded with status: {}", r.status());
+                r
+            },
+            Err(e) => {
+                log::error!("❌ Embeddings API request failed: {:?}", e);
+                return Err(anyhow!("Embeddings API request error: {}", e));
+            }
+        };
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unable to read error".to_string());
+            log::error!("❌ Embeddings API error ({}): {}", status, error_text);
+            return Err(anyhow!("Embeddings API error ({}): {}", status, error_text));
+        }
+
+        let mut result: EmbeddingResponse = response.json().await?;
+        if result.data.len() != texts.len() {
+            return Err(anyhow!("Embeddings API returned {} vectors for {} inputs", result.data.len(), texts.len()));
+        }
+        result.data.sort_by_key(|d| d.index);
+
+        let vectors: Vec<Vec<f32>> = result.data.into_iter().map(|d| d.embedding).collect();
+        if let Some(v) = vectors.iter().find(|v| v.len() as u64 != self.dimensions) {
+            return Err(anyhow!(
+                "Model '{}' returned {} dimensions but {} are configured (EMBEDDING_DIMENSIONS)",
+                self.model, v.len(), self.dimensions
+            ));
+        }
+        Ok((vectors, result.usage.map(|u| u.total_tokens)))
+    }
+
+    pub fn new(config: &EmbeddingConfig, base_url: String, rate_limiter: Arc<RateLimiter>) -> Result<Self> {
+        let dimensions = config.dimensions
+            .or_else(|| known_dimensions(&config.model))
+            .ok_or_else(|| anyhow!("EMBEDDING_DIMENSIONS must be set for embedding model '{}'", config.model))?;
+
+        Ok(Self {
+            http_client: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            model: config.model.clone(),
+            dimensions,
+            request_dimensions: config.dimensions,
+            batch_size: config.batch_size.max(1),
+            rate_limiter,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        "openai-compatible"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.dimensions
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<EmbeddingBatch> {
+        let (vectors, usage) = self.request(texts).await?;
+        Ok(EmbeddingBatch { vectors, tokens: usage.unwrap_or_else(|| texts.iter().map(|t| estimate_tokens(t)).sum()) })
+    }
+}
+
+/// OpenAI's hosted embeddings API; requires `OPENAI_API_KEY`
+pub struct OpenAiProvider {
+    inner: OpenAiCompatibleProvider,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: &EmbeddingConfig, rate_limiter: Arc<RateLimiter>) -> Result<Self> {
+        let base_url = config.base_url.clone().unwrap_or_else(|| OPENAI_BASE_URL.to_string());
+        Ok(Self { inner: OpenAiCompatibleProvider::new(config, base_url, rate_limiter)? })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.inner.dimensions()
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<EmbeddingBatch> {
+        if self.inner.api_key.as_deref().is_none_or(|k| k == "sk-placeholder") {
+            return Err(anyhow!("OPENAI_API_KEY is not set. Please provide a valid key in .env file."));
+        }
+        let (vectors, usage) = self.inner.request(texts).await?;
+        let tokens = match usage {
+            Some(tokens) => {
+                metrics::track_openai_usage(tokens);
+                tokens
+            }
+            None => texts.iter().map(|t| estimate_tokens(t)).sum(),
+        };
+        Ok(EmbeddingBatch { vectors, tokens })
+    }
+}
@@ -0,0 +1,252 @@
+use crate::core::embedding::estimate_tokens;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Token budget per chunk, with overlap carried into the next chunk of the same section
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChunkingConfig {
+    pub max_tokens: usize,
+    pub overlap_tokens: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self { max_tokens: 800, overlap_tokens: 100 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub index: usize,
+    /// Headings from the document root down to this chunk's section
+    pub heading_path: Vec<String>,
+    /// GitHub-style anchor of the innermost heading (empty before the first heading)
+    pub anchor: String,
+    pub text: String,
+}
+
+struct Section {
+    heading_path: Vec<String>,
+    anchor: String,
+    lines: Vec<String>,
+}
+
+/// `## Title` → (2, "Title"); closing hashes are dropped
+pub(crate) fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim().to_string();
+    Some((level, text))
+}
+
+/// GitHub-style heading anchor; non-ASCII letters (e.g. Thai) are kept
+pub fn slugify(heading: &str) -> String {
+    heading.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c)
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn split_sections(markdown: &str) -> Vec<Section> {
+    let mut sections = vec![Section { heading_path: Vec::new(), anchor: String::new(), lines: Vec::new() }];
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    // Repeated headings get `-1`, `-2`, ... like GitHub
+    let mut slugs: HashMap<String, usize> = HashMap::new();
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some((level, text)) = parse_heading(line) {
+            let slug = slugify(&text);
+            let seen = slugs.entry(slug.clone()).or_insert(0);
+            let anchor = if *seen == 0 { slug } else { format!("{}-{}", slug, seen) };
+            *seen += 1;
+
+            stack.retain(|(l, _)| *l < level);
+            stack.push((level, text));
+            sections.push(Section {
+                heading_path: stack.iter().map(|(_, t)| t.clone()).collect(),
+                anchor,
+                lines: Vec::new(),
+            });
+        }
+        if let Some(section) = sections.last_mut() {
+            section.lines.push(line.to_string());
+        }
+    }
+
+    sections
+}
+
+/// Paragraphs (blank-line separated); fenced code blocks stay whole
+fn paragraphs(lines: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if line.trim().is_empty() && !in_fence {
+            if !current.is_empty() {
+                out.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        out.push(current.join("\n"));
+    }
+    out
+}
+
+/// Hard-split text that alone exceeds the budget, at character boundaries
+fn split_oversized(text: &str, max_tokens: usize) -> Vec<String> {
+    let max_chars = (max_tokens * 4).max(1);
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(max_chars).map(|c| c.iter().collect()).collect()
+}
+
+fn pack(paragraphs: Vec<String>, config: &ChunkingConfig) -> Vec<String> {
+    let max_tokens = config.max_tokens.max(1);
+    let pieces: Vec<String> = paragraphs.into_iter()
+        .flat_map(|p| if estimate_tokens(&p) as usize > max_tokens { split_oversized(&p, max_tokens) } else { vec![p] })
+        .collect();
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_tokens = 0;
+    // Whether `current` holds anything beyond the overlap copied from the previous chunk
+    let mut has_new = false;
+
+    for piece in pieces {
+        let tokens = estimate_tokens(&piece) as usize;
+        if has_new && current_tokens + tokens > max_tokens {
+            chunks.push(current.join("\n\n"));
+
+            // Carry trailing paragraphs into the next chunk, up to the overlap budget
+            let mut overlap = Vec::new();
+            let mut overlap_tokens = 0;
+            for p in current.iter().rev() {
+                let t = estimate_tokens(p) as usize;
+                if overlap_tokens + t > config.overlap_tokens || overlap_tokens + t + tokens > max_tokens {
+                    break;
+                }
+                overlap_tokens += t;
+                overlap.insert(0, p.clone());
+            }
+            current = overlap;
+            current_tokens = overlap_tokens;
+        }
+        current_tokens += tokens;
+        current.push(piece);
+        has_new = true;
+    }
+    if has_new {
+        chunks.push(current.join("\n\n"));
+    }
+    chunks
+}
+
+/// Split markdown by heading hierarchy, then pack each section's paragraphs into chunks within the token budget
+pub fn chunk_markdown(markdown: &str, config: &ChunkingConfig) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    for section in split_sections(markdown) {
+        // Skip sections that are only a heading (their title still appears in child heading paths)
+        let body = if section.heading_path.is_empty() { &section.lines[..] } else { &section.lines[1..] };
+        if body.iter().all(|l| l.trim().is_empty()) {
+            continue;
+        }
+
+        for text in pack(paragraphs(&section.lines), config) {
+            chunks.push(Chunk {
+                index: chunks.len(),
+                heading_path: section.heading_path.clone(),
+                anchor: section.anchor.clone(),
+                text,
+            });
+        }
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "Intro line.\n\n# Architecture\n\nOverview.\n\n## Data Flow\n\nEvents go to the bus.\n\n```\n# not a heading\n```\n\n## ความปลอดภัย (Security)\n\nUse mTLS.\n\n# Empty Parent\n## Child\n\nChild body.\n\n## Data Flow\n\nAgain.\n";
+
+    #[test]
+    fn test_heading_paths_and_anchors() {
+        let chunks = chunk_markdown(DOC, &ChunkingConfig::default());
+        let paths: Vec<String> = chunks.iter().map(|c| c.heading_path.join(" > ")).collect();
+        assert_eq!(paths, vec![
+            "",
+            "Architecture",
+            "Architecture > Data Flow",
+            "Architecture > ความปลอดภัย (Security)",
+            "Empty Parent > Child",
+            "Empty Parent > Data Flow",
+        ]);
+        assert_eq!(chunks[2].anchor, "data-flow");
+        assert!(chunks[2].text.contains("# not a heading"));
+        assert_eq!(chunks[3].anchor, "ความปลอดภัย-security");
+        assert_eq!(chunks[5].anchor, "data-flow-1");
+        assert_eq!(chunks.iter().map(|c| c.index).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_token_budget_and_overlap() {
+        // Each paragraph is ~10 tokens
+        let body: Vec<String> = (0..10).map(|i| format!("p{} {}", i, "x".repeat(36))).collect();
+        let markdown = format!("# Big\n\n{}", body.join("\n\n"));
+        let config = ChunkingConfig { max_tokens: 35, overlap_tokens: 10 };
+
+        let chunks = chunk_markdown(&markdown, &config);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(estimate_tokens(&chunk.text) as usize <= 40, "chunk too large: {}", chunk.text);
+            assert_eq!(chunk.heading_path, vec!["Big".to_string()]);
+        }
+        // The last paragraph of one chunk opens the next
+        let last_of_first = chunks[0].text.rsplit("\n\n").next().unwrap();
+        assert!(chunks[1].text.starts_with(last_of_first));
+    }
+
+    #[test]
+    fn test_oversized_paragraph_is_split() {
+        let markdown = format!("# Wall\n\n{}", "y".repeat(1000));
+        let chunks = chunk_markdown(&markdown, &ChunkingConfig { max_tokens: 50, overlap_tokens: 0 });
+        assert!(chunks.len() >= 5);
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 200 + "# Wall".len() + 2));
+    }
+}
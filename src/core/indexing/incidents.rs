@@ -0,0 +1,165 @@
+use crate::core::database::{Database, vector::{ScoredPoint, SearchFilter, VectorStore}};
+use crate::core::indexing::{DesiredPoint, IndexReport, apply_plan, content_hash, existing_points, plan_sync, point_id};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Vector collection holding `mcp_incident` embeddings, one point per incident
+pub const INCIDENTS_COLLECTION: &str = "incidents";
+
+/// Similarity at which `report-incident` flags an existing incident as a likely duplicate
+pub const DUPLICATE_MIN_SCORE: f64 = 0.85;
+
+/// The text embedded for an incident; a new report is embedded the same way so the two compare
+pub fn incident_text(title: &str, symptom: &str, root_cause: Option<&str>, solution: Option<&str>, language: &str) -> String {
+    let mut text = format!("Title: {}\n", title);
+    if !language.is_empty() {
+        text.push_str(&format!("Language: {}\n", language));
+    }
+    text.push_str(&format!("Symptom: {}\n", symptom));
+    for (label, value) in [("Root cause", root_cause), ("Solution", solution)] {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            text.push_str(&format!("{}: {}\n", label, value));
+        }
+    }
+    text
+}
+
+/// One ranked incident as markdown, with its root cause and solution
+pub fn format_hit(rank: usize, hit: &ScoredPoint) -> String {
+    let field = |key: &str| hit.payload[key].as_str().filter(|s| !s.is_empty());
+    let mut out = format!(
+        "{}. **{}** (Score: {:.4}) · {} · {}",
+        rank, field("title").unwrap_or("Untitled"), hit.score,
+        field("status").unwrap_or("unknown"), field("project_name").unwrap_or("unknown"),
+    );
+    if let Some(language) = field("language") {
+        out.push_str(&format!(" · {}", language));
+    }
+    out.push_str(&format!("\n   `{}`\n", field("incident_id").unwrap_or_default()));
+    let excerpt = |text: &str| text.chars().take(300).collect::<String>();
+    if let Some(symptom) = field("symptom") {
+        out.push_str(&format!("   - **Symptom**: {}\n", excerpt(symptom)));
+    }
+    if let Some(root_cause) = field("root_cause") {
+        out.push_str(&format!("   - **Root cause**: {}\n", excerpt(root_cause)));
+    }
+    match field("solution") {
+        Some(solution) => out.push_str(&format!("   - **Solution**: {}\n\n", excerpt(solution))),
+        None => out.push_str("   - _No solution recorded yet_\n\n"),
+    }
+    out
+}
+
+#[derive(Debug, Deserialize)]
+struct IncidentRow {
+    incident_id: String,
+    project_name: Option<String>,
+    title: String,
+    symptom: String,
+    root_cause: Option<String>,
+    solution: Option<String>,
+    status: String,
+    programming_language: String,
+    updated_at: Option<String>,
+}
+
+/// Keeps the incidents collection in step with `mcp_incident`
+pub struct IncidentIndexer {
+    db: Database,
+    vector: Arc<VectorStore>,
+}
+
+impl IncidentIndexer {
+    pub fn new(db: Database, vector: Arc<VectorStore>) -> Self {
+        Self { db, vector }
+    }
+
+    /// Points for every incident, or only for `scope` (record IDs; deleted ones yield nothing)
+    async fn desired_points(&self, scope: Option<&[String]>) -> Result<Vec<DesiredPoint>> {
+        let source = match scope {
+            Some(_) => "array::map($ids, |$id| <record<mcp_incident>> $id)",
+            None => "mcp_incident",
+        };
+        let mut result = self.db.query(format!("
+            SELECT title, symptom, root_cause, solution, status, programming_language,
+                project_id.name AS project_name, type::string(updated_at) AS updated_at,
+                type::string(id) AS incident_id
+            FROM {}
+        ", source))
+            .bind(("ids", scope.map(|ids| ids.to_vec()).unwrap_or_default()))
+            .await?;
+        let incidents: Vec<IncidentRow> = result.take(0)?;
+        let embedder = self.vector.embedder().fingerprint();
+
+        Ok(incidents.into_iter().map(|incident| {
+            let text = incident_text(
+                &incident.title, &incident.symptom, incident.root_cause.as_deref(),
+                incident.solution.as_deref(), &incident.programming_language,
+            );
+            let hash = content_hash(&embedder, &text);
+            let payload = json!({
+                "incident_id": incident.incident_id,
+                "title": incident.title,
+                "project_name": incident.project_name.unwrap_or_else(|| "unknown".to_string()),
+                "status": incident.status,
+                // Lowercased so the language filter is an exact match in every backend
+                "language": incident.programming_language.to_lowercase(),
+                "symptom": incident.symptom,
+                "root_cause": incident.root_cause,
+                "solution": incident.solution,
+                "updated_at": incident.updated_at,
+                "content": text,
+            });
+            DesiredPoint::new(point_id(&incident.incident_id, 0), hash, text, payload)
+        }).collect())
+    }
+
+    /// Embed new and changed incidents; run by the incident watcher as they are written
+    pub async fn sync(&self) -> Result<IndexReport> {
+        self.sync_scope(None).await
+    }
+
+    /// Re-index only these incidents (record IDs), dropping the points of any that were deleted
+    pub async fn sync_incidents(&self, incident_ids: &[String]) -> Result<IndexReport> {
+        self.sync_scope(Some(incident_ids)).await
+    }
+
+    async fn sync_scope(&self, scope: Option<&[String]>) -> Result<IndexReport> {
+        self.vector.ensure_collection(INCIDENTS_COLLECTION).await?;
+        for field in ["project_name", "language", "incident_id"] {
+            self.vector.ensure_payload_index(INCIDENTS_COLLECTION, field).await?;
+        }
+
+        let desired = self.desired_points(scope).await?;
+        let existing = existing_points(&self.vector, INCIDENTS_COLLECTION, "incident_id", scope).await?;
+        let plan = plan_sync(&existing, desired, false);
+        if !plan.upserts.is_empty() || !plan.deletes.is_empty() {
+            log::info!("🚨 Incident indexing plan: {} to embed, {} to delete", plan.upserts.len(), plan.deletes.len());
+        }
+        Ok(apply_plan(&self.vector, INCIDENTS_COLLECTION, plan).await)
+    }
+
+    /// Past incidents most similar to `text`, best first
+    pub async fn find_similar(&self, text: &str, filter: &SearchFilter, limit: u64) -> Result<(Vec<ScoredPoint>, u32)> {
+        self.vector.search(INCIDENTS_COLLECTION, text, limit, filter, false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incident_text() {
+        assert_eq!(
+            incident_text("Pool exhausted", "timeouts under load", None, Some(""), ""),
+            "Title: Pool exhausted\nSymptom: timeouts under load\n"
+        );
+        assert_eq!(
+            incident_text("Pool exhausted", "timeouts", Some("leaked connections"), Some("close in finally"), "rust"),
+            "Title: Pool exhausted\nLanguage: rust\nSymptom: timeouts\nRoot cause: leaked connections\nSolution: close in finally\n"
+        );
+    }
+}
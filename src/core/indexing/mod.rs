@@ -0,0 +1,400 @@
+pub mod chunker;
+pub mod incidents;
+pub mod watcher;
+
+use crate::core::audit::chain::canonical_json;
+use crate::core::database::{Database, vector::{SourceFilter, VectorStore}};
+use chunker::{ChunkingConfig, chunk_markdown};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Vector collection holding `mcp_documentation` embeddings
+pub const DOCS_COLLECTION: &str = "documentation";
+
+/// Stable point ID: UUIDv5 of the SurrealDB record ID and chunk index
+pub fn point_id(record_id: &str, chunk_index: usize) -> String {
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, format!("surrealdb://{}#{}", record_id, chunk_index).as_bytes()).to_string()
+}
+
+/// Hash of exactly what gets embedded (and by which embedder), so any change forces a re-embed
+pub fn content_hash(embedder: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(embedder.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Payload fields the vector backend keeps a keyword index on, for filtered search
+pub const FILTER_FIELDS: [&str; 2] = ["project_name", "sdlc_phase"];
+
+/// A point that should exist after indexing
+#[derive(Debug, Clone)]
+pub struct DesiredPoint {
+    pub id: String,
+    pub hash: String,
+    /// Hash of the payload, so metadata edits (e.g. `updated_at`) refresh it without re-embedding
+    pub payload_hash: String,
+    pub text: String,
+    pub payload: serde_json::Value,
+}
+
+impl DesiredPoint {
+    pub fn new(id: String, hash: String, text: String, mut payload: serde_json::Value) -> Self {
+        let payload_hash = hex::encode(Sha256::digest(canonical_json(&payload).as_bytes()));
+        if let Some(map) = payload.as_object_mut() {
+            map.insert("content_hash".to_string(), json!(hash));
+            map.insert("payload_hash".to_string(), json!(payload_hash));
+        }
+        Self { id, hash, payload_hash, text, payload }
+    }
+}
+
+/// Hashes stored in an existing point's payload
+#[derive(Debug, Clone, Default)]
+pub struct StoredPoint {
+    pub content_hash: Option<String>,
+    pub payload_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Updated,
+    /// Same embedded text, only the payload is rewritten
+    Metadata,
+}
+
+/// What a sync has to do, computed before any embedding is requested
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    pub upserts: Vec<(DesiredPoint, Change)>,
+    pub unchanged: usize,
+    /// Points of removed documents, plus any point not derived from a current record (e.g. random legacy IDs)
+    pub deletes: Vec<String>,
+}
+
+/// Diff the desired points against the collection
+pub fn plan_sync(existing: &HashMap<String, StoredPoint>, desired: Vec<DesiredPoint>, force: bool) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+    let wanted: HashSet<String> = desired.iter().map(|p| p.id.clone()).collect();
+
+    for point in desired {
+        let Some(stored) = existing.get(&point.id) else {
+            plan.upserts.push((point, Change::Added));
+            continue;
+        };
+        if force || stored.content_hash.as_deref() != Some(point.hash.as_str()) {
+            plan.upserts.push((point, Change::Updated));
+        } else if stored.payload_hash.as_deref() != Some(point.payload_hash.as_str()) {
+            plan.upserts.push((point, Change::Metadata));
+        } else {
+            plan.unchanged += 1;
+        }
+    }
+
+    plan.deletes = existing.keys().filter(|id| !wanted.contains(*id)).cloned().collect();
+    plan.deletes.sort();
+    plan
+}
+
+#[derive(Debug, Default)]
+pub struct IndexReport {
+    pub added: usize,
+    pub updated: usize,
+    /// Payload refreshed without re-embedding
+    pub refreshed: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+    pub errors: Vec<String>,
+    /// Embedding tokens spent
+    pub tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocRow {
+    doc_id: String,
+    project_name: Option<String>,
+    name: String,
+    title: Option<String>,
+    content: Option<String>,
+    sdlc_phase: Option<String>,
+    updated_at: Option<String>,
+    #[serde(rename = "mimeType")]
+    mime_type: Option<String>,
+    version: Option<i64>,
+    status: Option<String>,
+    /// The row in `mcp_documentation_published`, if the document was ever approved
+    published: Option<PublishedRow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PublishedRow {
+    title: Option<String>,
+    content: Option<String>,
+    version: i64,
+    status: String,
+    published_at: Option<String>,
+}
+
+/// One edition of a document to chunk: its published edition or the working copy
+struct Edition<'a> {
+    /// Point IDs derive from this, so the two editions of a document never collide
+    source_id: String,
+    title: &'a str,
+    content: &'a str,
+    version: Option<i64>,
+    status: &'a str,
+    updated_at: Option<&'a str>,
+    published: bool,
+    /// Whether this is also the document's newest content
+    latest: bool,
+}
+
+/// Keeps the documentation collection in step with `mcp_documentation`, embedding only what changed
+pub struct DocumentIndexer {
+    db: Database,
+    vector: Arc<VectorStore>,
+    chunking: ChunkingConfig,
+}
+
+/// `kyx://project/phase/name`, with `#anchor` when the chunk sits under a heading
+pub fn section_uri(project: &str, phase: &str, name: &str, anchor: &str) -> String {
+    let uri = format!("kyx://{}/{}/{}", project, phase, name);
+    if anchor.is_empty() { uri } else { format!("{}#{}", uri, anchor) }
+}
+
+/// The same URI, but reading the latest draft instead of the published edition
+pub fn drafts_uri(uri: &str) -> String {
+    match uri.split_once('#') {
+        Some((base, anchor)) => format!("{}?drafts=true#{}", base, anchor),
+        None => format!("{}?drafts=true", uri),
+    }
+}
+
+impl DocumentIndexer {
+    pub fn new(db: Database, vector: Arc<VectorStore>, chunking: ChunkingConfig) -> Self {
+        Self { db, vector, chunking }
+    }
+
+    /// Points for every document, or only for `scope` (record IDs; deleted ones yield nothing).
+    /// A document's published edition and, when it differs, its working copy are indexed side by side.
+    async fn desired_points(&self, scope: Option<&[String]>) -> Result<Vec<DesiredPoint>> {
+        let source = match scope {
+            Some(_) => "array::map($ids, |$id| <record<mcp_documentation>> $id)",
+            None => "mcp_documentation",
+        };
+        let mut result = self.db.query(format!("
+            SELECT name, title, content, sdlc_phase, mimeType, project_id.name AS project_name,
+                type::string(updated_at) AS updated_at, type::string(id) AS doc_id, version, status,
+                (SELECT title, content, version, status, type::string(published_at) AS published_at
+                    FROM ONLY type::thing('mcp_documentation_published', meta::id($parent.id))) AS published
+            FROM {}
+        ", source))
+            .bind(("ids", scope.map(|ids| ids.to_vec()).unwrap_or_default()))
+            .await?;
+        let docs: Vec<DocRow> = result.take(0)?;
+
+        let mut points = Vec::new();
+        for doc in &docs {
+            let live = doc.published.as_ref().filter(|p| p.status == "published");
+            let current = live.is_some_and(|p| Some(p.version) == doc.version);
+            if let Some(published) = live {
+                points.extend(self.edition_points(doc, Edition {
+                    source_id: doc.doc_id.clone(),
+                    title: published.title.as_deref().unwrap_or(&doc.name),
+                    content: published.content.as_deref().unwrap_or_default(),
+                    version: Some(published.version),
+                    status: &published.status,
+                    updated_at: published.published_at.as_deref(),
+                    published: true,
+                    latest: current,
+                }));
+            }
+            let status = doc.status.as_deref().unwrap_or("draft");
+            if !current && status != "deprecated" {
+                points.extend(self.edition_points(doc, Edition {
+                    source_id: format!("{}?draft", doc.doc_id),
+                    title: doc.title.as_deref().unwrap_or(&doc.name),
+                    content: doc.content.as_deref().unwrap_or_default(),
+                    version: doc.version,
+                    status,
+                    updated_at: doc.updated_at.as_deref(),
+                    published: false,
+                    latest: true,
+                }));
+            }
+        }
+        Ok(points)
+    }
+
+    fn edition_points(&self, doc: &DocRow, edition: Edition) -> Vec<DesiredPoint> {
+        if edition.content.is_empty() {
+            return Vec::new();
+        }
+        let embedder = self.vector.embedder().fingerprint();
+        let phase = doc.sdlc_phase.as_deref().unwrap_or("unknown");
+        let project = doc.project_name.as_deref().unwrap_or("unknown");
+
+        chunk_markdown(edition.content, &self.chunking).into_iter().map(|chunk| {
+            let section = chunk.heading_path.join(" > ");
+            // Title and section path give each chunk the context it lost by being split out
+            let text = format!("Title: {}\nPhase: {}\nSection: {}\n\n{}", edition.title, phase, section, chunk.text);
+            let hash = content_hash(&embedder, &text);
+            let uri = section_uri(project, phase, &doc.name, &chunk.anchor);
+            let payload = json!({
+                "title": edition.title,
+                "project_name": project,
+                "sdlc_phase": phase,
+                "doc_name": doc.name,
+                "doc_id": doc.doc_id,
+                "updated_at": edition.updated_at,
+                "version": edition.version,
+                "status": edition.status,
+                "published": edition.published,
+                "latest": edition.latest,
+                "mimeType": doc.mime_type.as_deref().unwrap_or("text/markdown"),
+                "chunk_index": chunk.index,
+                "heading_path": chunk.heading_path,
+                "section": section,
+                "anchor": chunk.anchor,
+                "uri": if edition.published { uri } else { drafts_uri(&uri) },
+                "content": chunk.text,
+            });
+            DesiredPoint::new(point_id(&edition.source_id, chunk.index), hash, text, payload)
+        }).collect()
+    }
+
+    /// Bring the collection up to date; `force` re-embeds unchanged documents too
+    pub async fn sync(&self, force: bool) -> Result<IndexReport> {
+        self.sync_scope(None, force).await
+    }
+
+    /// Re-index only these documents (record IDs), dropping the points of any that were deleted
+    pub async fn sync_documents(&self, doc_ids: &[String]) -> Result<IndexReport> {
+        self.sync_scope(Some(doc_ids), false).await
+    }
+
+    async fn sync_scope(&self, scope: Option<&[String]>, force: bool) -> Result<IndexReport> {
+        self.vector.ensure_collection(DOCS_COLLECTION).await?;
+        for field in FILTER_FIELDS.into_iter().chain(["doc_id"]) {
+            self.vector.ensure_payload_index(DOCS_COLLECTION, field).await?;
+        }
+
+        let desired = self.desired_points(scope).await?;
+        let existing = existing_points(&self.vector, DOCS_COLLECTION, "doc_id", scope).await?;
+        let plan = plan_sync(&existing, desired, force);
+        log::info!("📚 Indexing plan: {} to embed, {} unchanged, {} to delete", plan.upserts.len(), plan.unchanged, plan.deletes.len());
+        Ok(apply_plan(&self.vector, DOCS_COLLECTION, plan).await)
+    }
+}
+
+/// Hashes of the points in `collection`; with a scope, only points whose `source_field` is one of
+/// its record IDs, filtered by the backend
+async fn existing_points(vector: &VectorStore, collection: &str, source_field: &str, scope: Option<&[String]>) -> Result<HashMap<String, StoredPoint>> {
+    let sources = scope.map(|ids| SourceFilter { field: source_field, ids });
+    Ok(vector.list_points(collection, &["content_hash", "payload_hash"], sources).await?
+        .into_iter()
+        .map(|(id, payload)| {
+            let field = |name: &str| payload.get(name).and_then(|v| v.as_str()).map(String::from);
+            (id, StoredPoint { content_hash: field("content_hash"), payload_hash: field("payload_hash") })
+        })
+        .collect())
+}
+
+/// Refresh payloads, embed what changed in one batched pass and drop stale points
+async fn apply_plan(vector: &VectorStore, collection: &str, plan: SyncPlan) -> IndexReport {
+    let mut report = IndexReport { unchanged: plan.unchanged, ..Default::default() };
+    let (refresh, embed): (Vec<_>, Vec<_>) = plan.upserts.into_iter().partition(|(_, c)| *c == Change::Metadata);
+
+    let refreshed = refresh.len();
+    let payloads = refresh.into_iter().map(|(p, _)| (p.id, p.payload)).collect();
+    match vector.set_payloads(collection, payloads).await {
+        Ok(()) => report.refreshed = refreshed,
+        Err(e) => report.errors.push(format!("refresh {} payloads: {}", refreshed, e)),
+    }
+
+    // One batched, cached embedding pass for everything that changed
+    let (added, updated) = (
+        embed.iter().filter(|(_, c)| *c == Change::Added).count(),
+        embed.iter().filter(|(_, c)| *c == Change::Updated).count(),
+    );
+    let documents = embed.into_iter().map(|(p, _)| (p.id, p.text, p.payload)).collect();
+    match vector.upsert_documents(collection, documents).await {
+        Ok(tokens) => {
+            report.tokens = tokens;
+            report.added = added;
+            report.updated = updated;
+        }
+        Err(e) => {
+            log::error!("🔥 Failed to index {}: {}", collection, e);
+            report.errors.push(format!("embed {} chunks: {}", added + updated, e));
+        }
+    }
+
+    let deleted = plan.deletes.len();
+    match vector.delete_points(collection, plan.deletes).await {
+        Ok(()) => report.deleted = deleted,
+        Err(e) => report.errors.push(format!("delete stale points: {}", e)),
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desired(record_id: &str, text: &str) -> DesiredPoint {
+        DesiredPoint::new(point_id(record_id, 0), content_hash("m", text), text.to_string(), json!({ "updated_at": "t1" }))
+    }
+
+    fn stored(point: &DesiredPoint) -> StoredPoint {
+        StoredPoint { content_hash: Some(point.hash.clone()), payload_hash: Some(point.payload_hash.clone()) }
+    }
+
+    #[test]
+    fn test_point_id_is_stable() {
+        assert_eq!(point_id("mcp_documentation:a", 0), point_id("mcp_documentation:a", 0));
+        assert_ne!(point_id("mcp_documentation:a", 0), point_id("mcp_documentation:a", 1));
+        assert_ne!(point_id("mcp_documentation:a", 0), point_id("mcp_documentation:b", 0));
+        assert!(Uuid::parse_str(&point_id("mcp_documentation:a", 0)).is_ok());
+        assert_ne!(content_hash("m1", "x"), content_hash("m2", "x"));
+    }
+
+    #[test]
+    fn test_plan_sync() {
+        // Same text, but e.g. `updated_at` moved since it was indexed
+        let retouched = desired("doc:retouched", "same");
+        let retouched_stored = StoredPoint { payload_hash: Some("old".to_string()), ..stored(&retouched) };
+        let existing: HashMap<String, StoredPoint> = [
+            (point_id("doc:same", 0), stored(&desired("doc:same", "same"))),
+            (point_id("doc:edited", 0), stored(&desired("doc:edited", "old"))),
+            (point_id("doc:retouched", 0), retouched_stored),
+            (point_id("doc:removed", 0), stored(&desired("doc:removed", "gone"))),
+            ("legacy-random-id".to_string(), StoredPoint::default()),
+        ].into_iter().collect();
+        let wanted = vec![desired("doc:same", "same"), desired("doc:edited", "new"), retouched, desired("doc:new", "new")];
+
+        let plan = plan_sync(&existing, wanted.clone(), false);
+        assert_eq!(plan.unchanged, 1);
+        let changes: Vec<(String, Change)> = plan.upserts.iter().map(|(p, c)| (p.id.clone(), *c)).collect();
+        assert_eq!(changes, vec![
+            (point_id("doc:edited", 0), Change::Updated),
+            (point_id("doc:retouched", 0), Change::Metadata),
+            (point_id("doc:new", 0), Change::Added),
+        ]);
+        let mut expected_deletes = vec![point_id("doc:removed", 0), "legacy-random-id".to_string()];
+        expected_deletes.sort();
+        assert_eq!(plan.deletes, expected_deletes);
+
+        let forced = plan_sync(&existing, wanted, true);
+        assert_eq!(forced.unchanged, 0);
+        assert_eq!(forced.upserts.len(), 4);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::core::database::{Database, vector::VectorStore};
+use crate::core::indexing::{DocumentIndexer, IndexReport};
+use crate::core::indexing::chunker::ChunkingConfig;
+use crate::core::indexing::incidents::IncidentIndexer;
+use crate::core::metrics::{INDEX_ERRORS_TOTAL, INDEX_LAG_SECONDS, INDEX_PENDING_DOCUMENTS, INDEX_REINDEXED_TOTAL};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use surrealdb::{Notification, RecordId};
+use tokio::time::Instant;
+
+/// Wait before resubscribing after the LIVE query ends or fails
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+/// Wait before retrying documents whose re-index failed
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoIndexConfig {
+    /// Re-index documents in the background as they change
+    pub enabled: bool,
+    /// Quiet period after the last edit before re-indexing
+    pub debounce: Duration,
+}
+
+impl AutoIndexConfig {
+    /// Continuous edits still get indexed after this long
+    fn max_delay(&self) -> Duration {
+        self.debounce * 10
+    }
+}
+
+impl Default for AutoIndexConfig {
+    fn default() -> Self {
+        Self { enabled: true, debounce: Duration::from_secs(2) }
+    }
+}
+
+/// Records changed since the last re-index
+#[derive(Debug, Default)]
+struct PendingChanges {
+    docs: HashSet<String>,
+    first_seen: Option<Instant>,
+    last_seen: Option<Instant>,
+    not_before: Option<Instant>,
+}
+
+impl PendingChanges {
+    fn record(&mut self, doc_id: String, now: Instant) {
+        self.docs.insert(doc_id);
+        self.first_seen.get_or_insert(now);
+        self.last_seen = Some(now);
+    }
+
+    /// `debounce` after the last edit, but no later than `max_delay` after the first
+    fn due_at(&self, debounce: Duration, max_delay: Duration) -> Option<Instant> {
+        let due = (self.last_seen? + debounce).min(self.first_seen? + max_delay);
+        Some(self.not_before.map_or(due, |not_before| due.max(not_before)))
+    }
+
+    fn take(&mut self) -> (Vec<String>, Option<Instant>) {
+        let first_seen = self.first_seen;
+        let docs = std::mem::take(&mut self.docs).into_iter().collect();
+        *self = Self::default();
+        (docs, first_seen)
+    }
+
+    /// Put back documents that failed, keeping their age so lag keeps growing
+    fn retry(&mut self, docs: Vec<String>, first_seen: Option<Instant>, now: Instant) {
+        for doc in docs {
+            self.record(doc, now);
+        }
+        self.first_seen = first_seen.into_iter().chain(self.first_seen).min();
+        self.not_before = Some(now + RETRY_DELAY);
+    }
+
+    fn lag(&self, now: Instant) -> Duration {
+        self.first_seen.map_or(Duration::ZERO, |first| now.saturating_duration_since(first))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangedRecord {
+    id: RecordId,
+}
+
+/// A table followed by a [`Watcher`], whose changed records get re-indexed in batches
+#[async_trait]
+pub trait WatchedTable: Send + Sync + 'static {
+    /// Table followed through the LIVE query
+    const TABLE: &'static str;
+
+    /// Index whatever changed while nobody was listening
+    async fn catch_up(&self) -> Result<IndexReport>;
+
+    /// Whether every record was indexed
+    async fn reindex(&self, ids: &[String]) -> bool;
+
+    /// Records still waiting, and how long the oldest has waited
+    fn observe(&self, _lag: Duration, _pending: usize) {}
+}
+
+/// Keeps an index fresh by following a table through a LIVE query
+pub struct Watcher<T> {
+    db: Database,
+    target: T,
+    config: AutoIndexConfig,
+}
+
+/// Follows `mcp_documentation` into the documentation collection
+pub type DocumentWatcher = Watcher<Documents>;
+
+/// Follows `mcp_incident` into the incidents collection
+pub type IncidentWatcher = Watcher<IncidentIndexer>;
+
+impl DocumentWatcher {
+    pub fn new(db: Database, vector: Arc<VectorStore>, chunking: ChunkingConfig, config: AutoIndexConfig) -> Self {
+        let target = Documents { indexer: DocumentIndexer::new(db.clone(), vector, chunking), db: db.clone() };
+        Self { db, target, config }
+    }
+}
+
+impl IncidentWatcher {
+    pub fn new(db: Database, vector: Arc<VectorStore>, config: AutoIndexConfig) -> Self {
+        Self { target: IncidentIndexer::new(db.clone(), vector), db, config }
+    }
+}
+
+impl<T: WatchedTable> Watcher<T> {
+    pub fn spawn(self) {
+        if !self.config.enabled {
+            return;
+        }
+        tokio::spawn(async move {
+            loop {
+                match self.watch().await {
+                    Ok(()) => log::warn!("⚠️ {} change stream ended; resubscribing", T::TABLE),
+                    Err(e) => log::error!("🔥 {} change stream failed: {}", T::TABLE, e),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+    }
+
+    async fn watch(&self) -> Result<()> {
+        let mut changes = self.db.select(T::TABLE).live().await?;
+        log::info!("👀 Watching {} for changes (debounce {:?})", T::TABLE, self.config.debounce);
+
+        // Catch up on edits made while nobody was listening; unchanged points cost nothing
+        match self.target.catch_up().await {
+            Ok(report) if report.errors.is_empty() => {}
+            Ok(report) => log::warn!("⚠️ Catch-up indexing of {} finished with {} errors", T::TABLE, report.errors.len()),
+            Err(e) => log::warn!("⚠️ Catch-up indexing of {} failed: {}", T::TABLE, e),
+        }
+
+        let mut pending = PendingChanges::default();
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            let due = pending.due_at(self.config.debounce, self.config.max_delay());
+            tokio::select! {
+                change = changes.next() => match change {
+                    Some(Ok(notification)) => {
+                        let notification: Notification<ChangedRecord> = notification;
+                        pending.record(notification.data.id.to_string(), Instant::now());
+                    }
+                    Some(Err(e)) => log::warn!("⚠️ Bad {} change notification: {}", T::TABLE, e),
+                    None => return Ok(()),
+                },
+                _ = tokio::time::sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {
+                    let (ids, first_seen) = pending.take();
+                    if !self.target.reindex(&ids).await {
+                        pending.retry(ids, first_seen, Instant::now());
+                    }
+                }
+                _ = tick.tick() => {}
+            }
+            self.target.observe(pending.lag(Instant::now()), pending.docs.len());
+        }
+    }
+}
+
+/// Documents and their link graph
+pub struct Documents {
+    db: Database,
+    indexer: DocumentIndexer,
+}
+
+#[async_trait]
+impl WatchedTable for Documents {
+    const TABLE: &'static str = "mcp_documentation";
+
+    async fn catch_up(&self) -> Result<IndexReport> {
+        self.indexer.sync(false).await
+    }
+
+    async fn reindex(&self, docs: &[String]) -> bool {
+        // Also catches edits made outside the tools; links do not hold up the index
+        if let Err(e) = crate::core::documents::links::refresh(&self.db, Some(docs)).await {
+            log::warn!("⚠️ Failed to refresh links of {} changed documents: {}", docs.len(), e);
+        }
+        match self.indexer.sync_documents(docs).await {
+            Ok(report) if report.errors.is_empty() => {
+                INDEX_REINDEXED_TOTAL.inc_by(docs.len() as u64);
+                log::info!(
+                    "📚 Re-indexed {} changed documents: {} chunks embedded, {} refreshed, {} deleted",
+                    docs.len(), report.added + report.updated, report.refreshed, report.deleted
+                );
+                true
+            }
+            Ok(report) => {
+                INDEX_ERRORS_TOTAL.inc();
+                log::error!("🔥 Re-indexing {} documents failed: {}", docs.len(), report.errors.join("; "));
+                false
+            }
+            Err(e) => {
+                INDEX_ERRORS_TOTAL.inc();
+                log::error!("🔥 Re-indexing {} documents failed: {}", docs.len(), e);
+                false
+            }
+        }
+    }
+
+    fn observe(&self, lag: Duration, pending: usize) {
+        INDEX_LAG_SECONDS.set(lag.as_secs_f64());
+        INDEX_PENDING_DOCUMENTS.set(pending as i64);
+    }
+}
+
+#[async_trait]
+impl WatchedTable for IncidentIndexer {
+    const TABLE: &'static str = "mcp_incident";
+
+    async fn catch_up(&self) -> Result<IndexReport> {
+        self.sync().await
+    }
+
+    async fn reindex(&self, incidents: &[String]) -> bool {
+        match self.sync_incidents(incidents).await {
+            Ok(report) if report.errors.is_empty() => {
+                log::info!("🚨 Re-indexed {} changed incidents: {} embedded, {} deleted", incidents.len(), report.added + report.updated, report.deleted);
+                true
+            }
+            Ok(report) => {
+                log::error!("🔥 Re-indexing {} incidents failed: {}", incidents.len(), report.errors.join("; "));
+                false
+            }
+            Err(e) => {
+                log::error!("🔥 Re-indexing {} incidents failed: {}", incidents.len(), e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_changes_debounce() {
+        let start = Instant::now();
+        let debounce = Duration::from_secs(2);
+        let max_delay = Duration::from_secs(20);
+        let mut pending = PendingChanges::default();
+        assert_eq!(pending.due_at(debounce, max_delay), None);
+
+        // Each edit pushes the deadline back...
+        pending.record("mcp_documentation:a".to_string(), start);
+        pending.record("mcp_documentation:a".to_string(), start + Duration::from_secs(1));
+        assert_eq!(pending.due_at(debounce, max_delay), Some(start + Duration::from_secs(3)));
+        // ...but never past max_delay from the first one
+        pending.record("mcp_documentation:b".to_string(), start + Duration::from_secs(19));
+        assert_eq!(pending.due_at(debounce, max_delay), Some(start + max_delay));
+        assert_eq!(pending.lag(start + Duration::from_secs(5)), Duration::from_secs(5));
+
+        let (mut docs, first_seen) = pending.take();
+        docs.sort();
+        assert_eq!(docs, ["mcp_documentation:a", "mcp_documentation:b"]);
+        assert_eq!(pending.lag(start), Duration::ZERO);
+
+        // A failed batch keeps its age and waits out the retry delay
+        let now = start + Duration::from_secs(21);
+        pending.retry(docs, first_seen, now);
+        assert_eq!(pending.lag(now), Duration::from_secs(21));
+        assert_eq!(pending.due_at(debounce, max_delay), Some(now + RETRY_DELAY));
+    }
+}
@@ -1,28 +1,72 @@
-use crate::core::database::{Database, vector::VectorStore};
+use crate::core::database::{Database, vector::{SearchFilter, VectorStore}};
 use crate::core::mcp::{JsonRpcRequest, JsonRpcResponse, McpInitializeResult, ServerInfo, JsonRpcError};
 use crate::core::mcp::types::{Resource, ResourceContent, Tool};
 use crate::core::mcp::rules::RuleManager;
+use crate::core::mcp::quota::{QuotaConfig, QuotaManager};
+use crate::core::config::ANONYMOUS_PRINCIPAL;
+use crate::core::audit::{AuditLog, AuditEntry, AuditContext, chain, export, stats};
+use crate::core::indexing::{DocumentIndexer, DOCS_COLLECTION, drafts_uri, section_uri};
+use crate::core::indexing::incidents::{self, IncidentIndexer, DUPLICATE_MIN_SCORE};
+use crate::core::search::{self, fulltext};
+use crate::core::documents::{DocumentKey, diff, edit::{self, VersionConflict}, history, links, lint, markdown, patch::{self, PatchOperation}, review};
+use crate::core::indexing::chunker::ChunkingConfig;
+use crate::core::rate_limiter::{RateLimiter, POLICY_DATABASE, GLOBAL_KEY};
 use serde_json::json;
 use anyhow::Result;
 use url::Url;
 use std::time::Instant;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 pub struct McpHandler {
     db: Database,
     rules: RuleManager,
+    quotas: QuotaManager,
     vector: Arc<VectorStore>,
+    rate_limiter: Arc<RateLimiter>,
+    audit: Arc<AuditLog>,
+    principal: String,
+    context: AuditContext,
+    chunking: ChunkingConfig,
+    // Embedding tokens spent while serving this request (charged against token quotas)
+    embedding_tokens: AtomicU32,
 }
 
 impl McpHandler {
-    pub fn new(db: Database, vector: Arc<VectorStore>) -> Self {
+    pub fn new(db: Database, vector: Arc<VectorStore>, rate_limiter: Arc<RateLimiter>, audit: Arc<AuditLog>) -> Self {
         Self { 
             db: db.clone(), 
-            rules: RuleManager::new(db),
+            rules: RuleManager::new(db.clone()),
+            quotas: QuotaManager::new(db, QuotaConfig::default()),
             vector,
+            rate_limiter,
+            audit,
+            principal: ANONYMOUS_PRINCIPAL.to_string(),
+            context: AuditContext::default(),
+            chunking: ChunkingConfig::default(),
+            embedding_tokens: AtomicU32::new(0),
         }
     }
 
+    /// Attribute this request to an authenticated principal, session and client
+    pub fn with_context(mut self, context: AuditContext) -> Self {
+        if let Some(principal) = &context.principal {
+            self.principal = principal.clone();
+        }
+        self.context = context;
+        self
+    }
+
+    pub fn with_chunking(mut self, chunking: ChunkingConfig) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    pub fn with_quotas(mut self, config: QuotaConfig) -> Self {
+        self.quotas = QuotaManager::new(self.db.clone(), config);
+        self
+    }
+
     pub async fn handle_request(&self, req: JsonRpcRequest) -> Result<Option<JsonRpcResponse>> {
         let is_notification = req.id.is_none();
         let id = req.id.clone().unwrap_or(json!(null));
@@ -103,6 +147,7 @@ impl McpHandler {
     }
 
     async fn handle_tools_list(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let start = Instant::now();
         let mut result = self.db.query("SELECT name, title, description, input_schema, execution_type, sql_template, parameter_map, type::string(project_id) as project_id FROM mcp_tools WHERE active = true").await?;
         
         let mut tools: Vec<Tool> = match result.take::<Vec<Tool>>(0) {
@@ -139,6 +184,9 @@ impl McpHandler {
             });
         }
 
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_request_audit("tools/list", &json!({}), "success", &format!("Listed {} tools", tools.len()), duration_ms).await;
+
         Ok(JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
             id: req.id.unwrap_or(json!(null)),
@@ -152,13 +200,15 @@ impl McpHandler {
         use crate::modules::governance::infrastructure::SurrealGovernanceRepository;
         
         let repo = SurrealGovernanceRepository::new(self.db.clone());
-        let docs = repo.list_documents(None).await?;
+        // Published editions only; drafts are read with `?drafts=true`
+        let docs = repo.list_documents(None, false).await?;
         
         let resources: Vec<Resource> = docs.iter().map(|doc| {
+            let deprecated = if doc.status.as_deref() == Some("deprecated") { " (deprecated)" } else { "" };
             Resource {
                 uri: format!("kyx://{}/{}/{}", doc.project_name, doc.sdlc_phase, doc.name),
                 name: doc.title.clone(),
-                description: Some(format!("SDLC Document for {} in {} phase", doc.project_name, doc.sdlc_phase)),
+                description: Some(format!("SDLC Document for {} in {} phase{}", doc.project_name, doc.sdlc_phase, deprecated)),
                 mime_type: Some(doc.mime_type.clone()),
             }
         }).collect();
@@ -175,6 +225,7 @@ impl McpHandler {
         use crate::modules::governance::repository::GovernanceRepository;
         use crate::modules::governance::infrastructure::SurrealGovernanceRepository;
 
+        let start = Instant::now();
         let params = req.params.as_ref().ok_or_else(|| anyhow::anyhow!("Missing params"))?;
         let uri = params.get("uri").and_then(|v| v.as_str()).ok_or_else(|| anyhow::anyhow!("Missing uri"))?;
         
@@ -183,9 +234,25 @@ impl McpHandler {
         let mut path_segments = parsed_url.path_segments().ok_or_else(|| anyhow::anyhow!("Invalid path in URI"))?;
         let sdlc_phase = path_segments.next().ok_or_else(|| anyhow::anyhow!("Missing phase in URI"))?;
         let doc_name = path_segments.next().ok_or_else(|| anyhow::anyhow!("Missing doc_name in URI"))?;
+        // The published edition unless the reader opts in to the latest draft
+        let drafts = parsed_url.query_pairs().any(|(key, value)| key == "drafts" && value == "true");
 
         let repo = SurrealGovernanceRepository::new(self.db.clone());
-        let doc_opt = repo.find_document(project_name.to_string(), sdlc_phase.to_string(), doc_name.to_string()).await?;
+        let doc_opt = repo.find_document(project_name.to_string(), sdlc_phase.to_string(), doc_name.to_string(), drafts).await?;
+        // Say so when the document exists but has never been approved
+        let not_found = match &doc_opt {
+            None if !drafts && repo.find_document(project_name.to_string(), sdlc_phase.to_string(), doc_name.to_string(), true).await?.is_some() => {
+                format!("Resource not found: the document has no published edition yet. Read {}?drafts=true for the latest draft.", uri.split('#').next().unwrap_or(uri))
+            }
+            _ => "Resource not found".to_string(),
+        };
+
+        let audit_args = json!({ "uri": uri, "project": project_name });
+        let duration_ms = start.elapsed().as_millis() as i64;
+        match &doc_opt {
+            Some(_) => self.record_request_audit("resources/read", &audit_args, "success", "Resource read", duration_ms).await,
+            None => self.record_request_audit("resources/read", &audit_args, "error", &not_found, duration_ms).await,
+        }
 
         match doc_opt {
             Some(doc) => {
@@ -207,6 +274,7 @@ impl McpHandler {
                                 mime_type: Some(doc.mime_type),
                                 text: Some(final_content),
                                 blob: None,
+                                meta: doc.version.map(|version| edit::read_meta(version, doc.status.as_deref(), doc.latest_version, drafts)),
                             }
                         ]
                     })),
@@ -220,7 +288,7 @@ impl McpHandler {
                     result: None,
                     error: Some(JsonRpcError {
                         code: -32001,
-                        message: "Resource not found".to_string(),
+                        message: not_found,
                         data: None,
                     }),
                 })
@@ -237,68 +305,30 @@ impl McpHandler {
         message: &str,
         duration_ms: i64,
     ) {
-        // Try to get project_id from tool definition first, then fallback to arguments
-        let mut bind_project: Option<surrealdb::sql::Thing> = None;
-
-        if let Some(pid_val) = tool_project_id {
-            if let Some(s) = pid_val.as_str() {
-                if let Ok(thing) = surrealdb::sql::thing(s) {
-                    bind_project = Some(thing);
-                }
-            } else if let Ok(thing) = serde_json::from_value::<surrealdb::sql::Thing>(pid_val.clone()) {
-                bind_project = Some(thing);
-            }
-        }
-
-        if bind_project.is_none() {
-            if let Some(p_arg) = arguments.get("project").or_else(|| arguments.get("project_id")) {
-                if let Some(s) = p_arg.as_str() {
-                    if let Ok(thing) = surrealdb::sql::thing(s) {
-                        bind_project = Some(thing);
-                    } else {
-                        // Lookup by name
-                         let mut p_query = self.db.query("SELECT id FROM mcp_projects WHERE name = $name LIMIT 1")
-                            .bind(("name", s.to_string()))
-                            .await.ok();
-                        if let Some(mut pq) = p_query {
-                            let ids: Vec<serde_json::Value> = pq.take(0).unwrap_or_default();
-                            if let Some(first) = ids.first() {
-                                if let Some(id_val) = first.get("id") {
-                                    if let Ok(thing) = serde_json::from_value::<surrealdb::sql::Thing>(id_val.clone()) {
-                                        bind_project = Some(thing);
-                                    } else if let Some(id_str) = id_val.as_str() {
-                                        if let Ok(thing) = surrealdb::sql::thing(id_str) {
-                                            bind_project = Some(thing);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let query = if bind_project.is_some() {
-            "CREATE mcp_audit_log SET tool_name = $tool, project_id = $project, arguments = $args, status = $status, message = $msg, duration_ms = $duration_ms"
-        } else {
-            "CREATE mcp_audit_log SET tool_name = $tool, arguments = $args, status = $status, message = $msg, duration_ms = $duration_ms"
-        };
-
-        let mut insert = self.db.query(query)
-            .bind(("tool", tool_name.to_string()))
-            .bind(("args", arguments.clone()))
-            .bind(("status", status.to_string()))
-            .bind(("msg", message.to_string()))
-            .bind(("duration_ms", duration_ms));
-            
-        if let Some(p) = bind_project {
-            insert = insert.bind(("project", p));
-        }
+        self.audit.record(AuditEntry {
+            method: "tools/call".to_string(),
+            tool_name: tool_name.to_string(),
+            tool_project_id,
+            arguments: arguments.clone(),
+            status: status.to_string(),
+            message: message.to_string(),
+            duration_ms,
+            context: self.context.clone(),
+        }).await;
+    }
 
-        if let Err(e) = insert.await {
-            log::error!("🔥 Failed to record audit log for {}: {}", tool_name, e);
-        }
+    /// Audit a non-tool request; the method doubles as the record's `tool_name`
+    async fn record_request_audit(&self, method: &str, arguments: &serde_json::Value, status: &str, message: &str, duration_ms: i64) {
+        self.audit.record(AuditEntry {
+            method: method.to_string(),
+            tool_name: method.to_string(),
+            tool_project_id: None,
+            arguments: arguments.clone(),
+            status: status.to_string(),
+            message: message.to_string(),
+            duration_ms,
+            context: self.context.clone(),
+        }).await;
     }
 
     async fn handle_tools_call(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
@@ -307,12 +337,57 @@ impl McpHandler {
         let arguments = params.get("arguments").ok_or_else(|| anyhow::anyhow!("Missing arguments"))?;
 
         let start = Instant::now();
+
+        // Quota Check (per principal / per project); charges the call up front
+        let project = arguments.get("project").and_then(|v| v.as_str());
+        let standings = match self.quotas.reserve(name, &self.principal, project).await {
+            Ok(s) => s,
+            Err(e) => {
+                let duration_ms = start.elapsed().as_millis() as i64;
+                self.record_audit_log(name, None, arguments, "error", &e.to_string(), duration_ms).await;
+                return Err(e);
+            }
+        };
+
+        let result = self.dispatch_tool_call(&req, name, arguments, start).await;
+
+        if !standings.is_empty() {
+            self.quotas.record(&standings, self.embedding_tokens.load(Ordering::Relaxed)).await;
+        }
+
+        result
+    }
+
+    async fn dispatch_tool_call(&self, req: &JsonRpcRequest, name: &str, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
         log::info!("🛠️ Dispatching tool call: {}", name);
 
         // Static Tool Dispatch (Phase 3)
         match name {
-            "search-semantic" => return self.handle_search_semantic(&req, arguments, start).await,
-            "index-documents" => return self.handle_index_documents(&req, arguments, start).await,
+            "search-semantic" => return self.handle_search_semantic(req, arguments, start).await,
+            "search-hybrid" => return self.handle_search_hybrid(req, arguments, start).await,
+            "index-documents" => return self.handle_index_documents(req, arguments, start).await,
+            "get-usage" => return self.handle_get_usage(req, arguments, start).await,
+            "verify-audit-chain" => return self.handle_verify_audit_chain(req, arguments, start).await,
+            "export-audit" => return self.handle_export_audit(req, arguments, start).await,
+            "audit-stats" => return self.handle_audit_stats(req, arguments, start).await,
+            "find-similar-incidents" => return self.handle_find_similar_incidents(req, arguments, start).await,
+            "list-document-versions" => return self.handle_list_document_versions(req, arguments, start).await,
+            "diff-document" => return self.handle_diff_document(req, arguments, start).await,
+            "restore-document-version" => return self.handle_restore_document_version(req, arguments, start).await,
+            "update-document" => return self.handle_update_document(req, arguments, start).await,
+            "patch-document" => return self.handle_patch_document(req, arguments, start).await,
+            "lint-documents" => return self.handle_lint_documents(req, arguments, start).await,
+            "list-backlinks" => return self.handle_list_backlinks(req, arguments, start).await,
+            "check-links" => return self.handle_check_links(req, arguments, start).await,
+            "submit-document" | "approve-document" | "reject-document" | "deprecate-document" => {
+                return self.handle_review_document(req, name, arguments, start).await
+            }
+            // Runs the dynamic tool below unless the report looks like a duplicate
+            "report-incident" if arguments.get("check_duplicates").and_then(|v| v.as_bool()).unwrap_or(false) => {
+                if let Some(response) = self.check_incident_duplicates(req, arguments, start).await? {
+                    return Ok(response);
+                }
+            }
             _ => {}
         }
 
@@ -325,6 +400,14 @@ impl McpHandler {
         
         if let Some(tool) = tools.pop() {
             if let Some(exec_type) = &tool.execution_type {
+            if exec_type == "raw_sql" || exec_type == "dynamic_sql" {
+                if let Err(e) = self.rate_limiter.check(POLICY_DATABASE, GLOBAL_KEY) {
+                    let duration_ms = start.elapsed().as_millis() as i64;
+                    self.record_audit_log(name, tool.project_id.clone(), arguments, "error", &e.to_string(), duration_ms).await;
+                    return Err(e.into());
+                }
+            }
+
             if exec_type == "raw_sql" {
                 // Execute arbitrary SQL from arguments
                 let sql = arguments.get("sql_commands")
@@ -333,7 +416,13 @@ impl McpHandler {
                     .ok_or_else(|| anyhow::anyhow!("Missing 'sql_commands' or 'sql' argument for raw_sql execution"))?;
 
                 log::info!("🚀 Executing Raw SQL Tool: {}", name);
-                let mut result = match self.db.query(sql).await {
+                // Documents the batch changes are linted afterwards
+                let since = history::now(&self.db).await?;
+                let mut result = match self.db.query(sql)
+                    .bind((history::PRINCIPAL_PARAM, self.principal.clone()))
+                    .bind((history::REASON_PARAM, change_reason(arguments)))
+                    .await
+                {
                     Ok(r) => r,
                     Err(e) => {
                         let duration_ms = start.elapsed().as_millis() as i64;
@@ -398,13 +487,30 @@ impl McpHandler {
 
                 let final_results: Vec<serde_json::Value> = all_results.into_iter().map(flatten_v).collect();
                 
-                let text_output = if final_results.len() == 1 && final_results[0].is_string() {
+                let mut text_output = if final_results.len() == 1 && final_results[0].is_string() {
                     final_results[0].as_str().unwrap_or("").to_string()
                 } else {
                     let json_output = serde_json::to_string_pretty(&final_results)?;
                     format!("Raw SQL Result:\n```json\n{}\n```", json_output)
                 };
 
+                let changed = history::changed_since(&self.db, &since, &self.principal).await?;
+                if !changed.is_empty() {
+                    if let Err(e) = links::refresh(&self.db, Some(&changed)).await {
+                        log::warn!("⚠️ Failed to refresh document links: {}", e);
+                    }
+                    let reports = lint::lint(&self.db, lint::LintScope { ids: Some(changed), ..Default::default() }).await?;
+                    let violations = lint::format_reports(&reports);
+                    if !violations.is_empty() {
+                        text_output.push_str(&format!("\n\n### Document standard violations\n\n{}", violations));
+                    }
+                }
+                let removed = history::removed_since(&self.db, &since, &self.principal).await?;
+                let warnings = links::removal_warnings(&self.db, &removed).await?;
+                if !warnings.is_empty() {
+                    text_output.push_str(&format!("\n\n### Broken inbound links\n\n{}", warnings));
+                }
+
                 let duration_ms = start.elapsed().as_millis() as i64;
                 self.record_audit_log(name, tool.project_id.clone(), arguments, "success", "Raw SQL executed", duration_ms).await;
 
@@ -426,7 +532,10 @@ impl McpHandler {
                         // Dynamic execution
                         log::info!("🚀 Executing Dynamic Tool: {}", name);
                         
-                        let mut query = self.db.query(sql);
+                        // Attribution for mcp_documentation_history
+                        let mut query = self.db.query(sql)
+                            .bind((history::PRINCIPAL_PARAM, self.principal.clone()))
+                            .bind((history::REASON_PARAM, change_reason(arguments)));
 
                         // Map parameters
                         if let Some(param_map_val) = &tool.parameter_map {
@@ -436,7 +545,7 @@ impl McpHandler {
                             if let Some(param_map) = param_map_json.as_object() {
                                 for (arg_key, sql_var) in param_map {
                                     if let Some(sql_var_str) = sql_var.as_str() {
-                                        if let Some(val) = arguments.get(&arg_key) {
+                                        if let Some(val) = arguments.get(arg_key) {
                                             // Bind matching argument to SQL variable
                                             match val {
                                                 serde_json::Value::String(s) => {
@@ -572,44 +681,345 @@ impl McpHandler {
         let duration_ms = start.elapsed().as_millis() as i64;
         self.record_audit_log(name, None, arguments, "error", &format!("Tool not found: {}", name), duration_ms).await;
 
-        Ok(JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: req.id.unwrap_or(json!(null)),
-            result: None,
-            error: Some(JsonRpcError {
-                code: -32601,
-                message: format!("Tool not found: {}", name),
-                data: None,
+        Ok(JsonRpcResponse::error(-32601, &format!("Tool not found: {}", name), req.id.clone()))
+    }
+
+    async fn handle_verify_audit_chain(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let after_seq = arguments.get("from_seq").and_then(|v| v.as_i64()).map(|s| s - 1).unwrap_or(0);
+
+        // Include records still queued in the background writer
+        self.audit.flush().await;
+
+        let report = chain::verify_chain(self.audit.db(), after_seq).await?;
+        let checkpoints = chain::verify_checkpoints(self.audit.db(), self.audit.config()).await?;
+        let intact = report.broken.is_none() && checkpoints.failures.is_empty();
+
+        let mut output = String::from("### Audit Chain Verification\n\n");
+        match (report.first_seq, report.last_seq) {
+            (Some(first), Some(last)) => output.push_str(&format!("- **Records checked**: {} (seq {} → {})\n", report.verified, first, last)),
+            _ => output.push_str("- **Records checked**: 0 (no chained records)\n"),
+        }
+        if report.archived > 0 {
+            output.push_str(&format!("- **Archived links checked**: {} (via tombstones)\n", report.archived));
+        }
+        match &report.broken {
+            None => output.push_str("- **Chain**: ✅ intact\n"),
+            Some(b) => output.push_str(&format!("- **Chain**: 🚨 first broken link at seq {} (`{}`): {}\n", b.seq, b.id, b.reason)),
+        }
+        output.push_str(&format!("- **Checkpoints checked**: {}\n", checkpoints.checked));
+        for failure in &checkpoints.failures {
+            output.push_str(&format!("  - 🚨 {}\n", failure));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        let message = if intact { "Audit chain intact".to_string() } else { "Audit chain broken".to_string() };
+        self.record_audit_log("verify-audit-chain", None, arguments, "success", &message, duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(!intact),
             }),
-        })
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_export_audit(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let text = |key: &str| arguments.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(String::from);
+        let time = |key: &str| -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+            match text(key) {
+                Some(s) => chrono::DateTime::parse_from_rfc3339(&s)
+                    .map(|dt| Some(dt.with_timezone(&chrono::Utc)))
+                    .map_err(|e| anyhow::anyhow!("Invalid '{}' (expected RFC 3339): {}", key, e)),
+                None => Ok(None),
+            }
+        };
+
+        // Accept a project name or record ID
+        let project_id = match text("project") {
+            Some(project) => {
+                let mut result = self.db.query("SELECT VALUE type::string(id) FROM mcp_projects WHERE name = $project OR type::string(id) = $project LIMIT 1")
+                    .bind(("project", project.clone()))
+                    .await?;
+                let ids: Vec<String> = result.take(0)?;
+                Some(ids.into_iter().next().ok_or_else(|| anyhow::anyhow!("Project not found: {}", project))?)
+            }
+            None => None,
+        };
+
+        let filter = export::ExportFilter {
+            from: time("from")?,
+            to: time("to")?,
+            tool_name: text("tool_name"),
+            status: text("status"),
+            principal: text("principal"),
+            project_id,
+            include_archived: arguments.get("include_archived").and_then(|v| v.as_bool()).unwrap_or(false),
+            limit: arguments.get("limit").and_then(|v| v.as_u64()).map(|n| n as usize).unwrap_or(export::DEFAULT_EXPORT_LIMIT),
+        };
+
+        // Include records still queued in the background writer
+        self.audit.flush().await;
+        let result = export::export_jsonl(self.audit.db(), self.audit.config(), &filter).await?;
+
+        let mut summary = match &result.next_from {
+            Some(next) => format!("Exported {} audit records (limit reached). Continue with `from` = `{}`; records sharing that timestamp appear in both pages.", result.count, next),
+            None => format!("Exported {} audit records.", result.count),
+        };
+        let body = match (result.jsonl, &result.file) {
+            (Some(jsonl), _) => jsonl,
+            (None, Some(path)) => {
+                summary.push_str(&format!(" The {} bytes are too large to return inline and were written to `{}` on the server.", result.bytes, path.display()));
+                String::new()
+            }
+            (None, None) => String::new(),
+        };
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("export-audit", None, arguments, "success", &summary, duration_ms).await;
+
+        let mut content = Vec::new();
+        if !body.is_empty() {
+            content.push(crate::core::mcp::types::ToolContent {
+                content_type: "text".to_string(),
+                text: Some(body),
+                image: None,
+            });
+        }
+        content.push(crate::core::mcp::types::ToolContent {
+            content_type: "text".to_string(),
+            text: Some(summary),
+            image: None,
+        });
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content,
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_audit_stats(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let text = |key: &str| arguments.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(String::from);
+        let time = |key: &str| -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+            match text(key) {
+                Some(s) => chrono::DateTime::parse_from_rfc3339(&s)
+                    .map(|dt| Some(dt.with_timezone(&chrono::Utc)))
+                    .map_err(|e| anyhow::anyhow!("Invalid '{}' (expected RFC 3339): {}", key, e)),
+                None => Ok(None),
+            }
+        };
+
+        // group_by accepts an array or a comma-separated string
+        let dimensions: Vec<String> = match arguments.get("group_by") {
+            Some(serde_json::Value::Array(items)) => items.iter().filter_map(|v| v.as_str().map(String::from)).collect(),
+            Some(serde_json::Value::String(s)) => s.split(',').map(|d| d.trim().to_string()).filter(|d| !d.is_empty()).collect(),
+            _ => vec!["tool".to_string()],
+        };
+        let group_by = dimensions.iter()
+            .map(|d| stats::Dimension::parse(d).ok_or_else(|| anyhow::anyhow!("Invalid group_by '{}' (expected tool, project, principal or status)", d)))
+            .collect::<Result<Vec<_>>>()?;
+        let bucket = text("bucket")
+            .map(|b| stats::Bucket::parse(&b).ok_or_else(|| anyhow::anyhow!("Invalid bucket '{}' (expected hour, day or week)", b)))
+            .transpose()?;
+
+        let to = time("to")?.unwrap_or_else(chrono::Utc::now);
+        let query = stats::StatsQuery {
+            from: time("from")?.unwrap_or(to - chrono::Duration::days(7)),
+            to,
+            group_by,
+            bucket,
+            tool_name: text("tool_name"),
+            principal: text("principal"),
+            project: text("project"),
+        };
+
+        let groups = stats::audit_stats(self.audit.db(), &query, self.audit.config().stats_max_days).await?;
+        let output = match text("format").as_deref() {
+            Some("json") => serde_json::to_string_pretty(&stats::to_json(&query, &groups))?,
+            _ => stats::to_markdown(&query, &groups),
+        };
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("audit-stats", None, arguments, "success", &format!("Aggregated {} groups", groups.len()), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_get_usage(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let project = arguments.get("project").and_then(|v| v.as_str());
+        let tool = arguments.get("tool_name").and_then(|v| v.as_str());
+
+        let standings = self.quotas.standings(tool, &self.principal, project).await?;
+
+        let mut output = format!("### Usage for `{}`\n\n", self.principal);
+        if standings.is_empty() {
+            output.push_str("No quotas apply to you");
+            if let Some(p) = project {
+                output.push_str(&format!(" or project `{}`", p));
+            }
+            output.push('.');
+        } else {
+            output.push_str("| Tool | Scope | Subject | Period | Calls | Tokens | Resets At | Status |\n");
+            output.push_str("|------|-------|---------|--------|-------|--------|-----------|--------|\n");
+            for s in &standings {
+                output.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} | {} | {} |\n",
+                    s.quota.tool_name,
+                    s.quota.scope.as_str(),
+                    s.subject,
+                    s.quota.period.as_str(),
+                    s.calls_display(),
+                    s.tokens_display(),
+                    s.resets_at.format("%Y-%m-%d %H:%M UTC"),
+                    if s.exceeded() { "⛔ exhausted" } else { "✅ ok" }
+                ));
+            }
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("get-usage", None, arguments, "success", "Usage reported", duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
     }
 
     async fn handle_search_semantic(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
         let query = arguments.get("query").and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing 'query' argument"))?;
         let limit = arguments.get("limit").and_then(|v| v.as_u64()).unwrap_or(5);
+        let filter = SearchFilter {
+            project: arguments.get("project").and_then(|v| v.as_str()).map(String::from),
+            phase: arguments.get("phase").and_then(|v| v.as_str()).map(String::from),
+            language: None,
+            min_score: arguments.get("min_score").and_then(|v| v.as_f64()).map(|s| s as f32),
+            drafts: arguments.get("include_drafts").and_then(|v| v.as_bool()).unwrap_or(false),
+        };
+
+        let rerank = arguments.get("rerank").and_then(|v| v.as_bool()).unwrap_or(false);
+        let diversify = arguments.get("diversify").and_then(|v| v.as_bool()).unwrap_or(false);
+        let group = arguments.get("group_by_document").and_then(|v| v.as_bool()).unwrap_or(false);
+        // A second pass reorders a deeper candidate list than what is returned
+        let depth = if rerank || diversify || group { (limit * 4).max(20) } else { limit };
 
         log::info!("🔍 Performing semantic search for: '{}'", query);
         
         // Ensure collection exists
-        self.vector.ensure_collection("documentation", 1536).await?;
+        self.vector.ensure_collection(DOCS_COLLECTION).await?;
 
-        let results = self.vector.search("documentation", query, limit).await?;
-        
-        let mut output = String::from("### Semantic Search Results\n\n");
-        if results.is_empty() {
-            output.push_str("No relevant documents found. Try running `index-documents` first.");
+        let (mut hits, tokens) = self.vector.search(DOCS_COLLECTION, query, depth, &filter, diversify).await?;
+        self.embedding_tokens.fetch_add(tokens, Ordering::Relaxed);
+
+        let mut notes = Vec::new();
+        let mut reranked_by = None;
+        if rerank {
+            match self.vector.reranker() {
+                Some(reranker) => {
+                    let texts: Vec<String> = hits.iter().map(|h| h.payload["content"].as_str().unwrap_or_default().to_string()).collect();
+                    match reranker.rerank(query, &texts).await {
+                        Ok(scores) => {
+                            for (hit, score) in hits.iter_mut().zip(scores) {
+                                hit.score = score;
+                            }
+                            hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+                            reranked_by = Some(reranker.name().to_string());
+                        }
+                        Err(e) => {
+                            log::warn!("⚠️ Re-ranking failed, keeping vector order: {}", e);
+                            notes.push(format!("Re-ranking failed, showing vector order ({})", e));
+                        }
+                    }
+                }
+                None => notes.push("Re-ranking is not configured (RERANK_URL), showing vector order".to_string()),
+            }
+        }
+        if diversify {
+            let vectors: Vec<&[f32]> = hits.iter().map(|h| h.vector.as_deref().unwrap_or_default()).collect();
+            let relevance: Vec<f64> = hits.iter().map(|h| h.score).collect();
+            let order = search::maximal_marginal_relevance(&vectors, &relevance, search::MMR_LAMBDA, hits.len());
+            hits = order.into_iter().map(|i| hits[i].clone()).collect();
+        }
+
+        // Each entry: the document's hits, best first
+        let groups: Vec<Vec<usize>> = if group {
+            let mut groups = search::group_by_key(hits.iter().map(|h| h.payload["doc_id"].as_str().unwrap_or_default()));
+            groups.truncate(limit as usize);
+            groups
         } else {
-            for (i, res) in results.iter().enumerate() {
-                let score = res.get("score").and_then(|v| v.as_f64()).unwrap_or(0.0);
-                let payload = res.get("payload").and_then(|v| v.as_object());
-                let content = payload.and_then(|p| p.get("content")).and_then(|v| v.as_str()).unwrap_or("No content");
-                let title = payload.and_then(|p| p.get("title")).and_then(|v| v.as_str()).unwrap_or("Untitled");
-                
-                output.push_str(&format!("{}. **{}** (Score: {:.4})\n", i + 1, title, score));
-                output.push_str(&format!("   {}\n\n", content.chars().take(200).collect::<String>()));
+            (0..hits.len().min(limit as usize)).map(|i| vec![i]).collect()
+        };
+
+        let mut output = String::from("### Semantic Search Results\n\n");
+        for note in &notes {
+            output.push_str(&format!("⚠️ {}\n\n", note));
+        }
+        if let Some(name) = reranked_by {
+            output.push_str(&format!("_Re-ranked by {}; scores are re-ranker relevance._\n\n", name));
+        }
+        if groups.is_empty() {
+            if filter.project.is_some() || filter.phase.is_some() || filter.min_score.is_some() {
+                output.push_str("No relevant documents matched the project, phase or min_score filters.");
+            } else {
+                output.push_str("No relevant documents found. Try running `index-documents` first.");
             }
         }
+        for (i, members) in groups.iter().enumerate() {
+            let best = &hits[members[0]];
+            let payload = &best.payload;
+            let title = payload["title"].as_str().unwrap_or("Untitled");
+            let section = |p: &serde_json::Value| p["section"].as_str().filter(|s| !s.is_empty()).map(String::from);
+
+            if group {
+                output.push_str(&format!("{}. **{}** (Score: {:.4}, {} matching sections)\n", i + 1, title, best.score, members.len()));
+                // Up to three sections per document keep the list scannable
+                for member in members.iter().take(3) {
+                    let hit = &hits[*member];
+                    let content = hit.payload["content"].as_str().unwrap_or("No content");
+                    output.push_str(&format!("   - › {} (Score: {:.4})", section(&hit.payload).unwrap_or_else(|| "Introduction".to_string()), hit.score));
+                    if let Some(uri) = hit.payload["uri"].as_str() {
+                        output.push_str(&format!(" `{}`", uri));
+                    }
+                    output.push_str(&format!("\n     {}\n", content.chars().take(200).collect::<String>()));
+                }
+                output.push('\n');
+                continue;
+            }
+
+            let content = payload["content"].as_str().unwrap_or("No content");
+            match section(payload) {
+                Some(section) => output.push_str(&format!("{}. **{}** › {} (Score: {:.4})\n", i + 1, title, section, best.score)),
+                None => output.push_str(&format!("{}. **{}** (Score: {:.4})\n", i + 1, title, best.score)),
+            }
+            if let Some(uri) = payload["uri"].as_str() {
+                output.push_str(&format!("   `{}`\n", uri));
+            }
+            output.push_str(&format!("   {}\n\n", content.chars().take(500).collect::<String>()));
+        }
 
         let duration_ms = start.elapsed().as_millis() as i64;
         self.record_audit_log("search-semantic", None, arguments, "success", "Semantic search completed", duration_ms).await;
@@ -627,20 +1037,628 @@ impl McpHandler {
         ))
     }
 
+    async fn handle_search_hybrid(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let query = arguments.get("query").and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing 'query' argument"))?;
+        let limit = arguments.get("limit").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+        let filter = SearchFilter {
+            project: arguments.get("project").and_then(|v| v.as_str()).map(String::from),
+            phase: arguments.get("phase").and_then(|v| v.as_str()).map(String::from),
+            language: None,
+            min_score: None,
+            drafts: arguments.get("include_drafts").and_then(|v| v.as_bool()).unwrap_or(false),
+        };
+        // Each ranking contributes a deeper candidate list than what is returned
+        let depth = (limit * 4).max(20);
+
+        log::info!("🔍 Performing hybrid search for: '{}'", query);
+
+        let bm25 = fulltext::search_documents(&self.db, query, &filter, depth).await?;
+
+        // Vector results are per chunk: keep each document's best chunk. Without a vector backend or
+        // embeddings the search degrades to BM25 only.
+        let mut notes = Vec::new();
+        let mut chunks: Vec<serde_json::Value> = Vec::new();
+        match self.vector.search(DOCS_COLLECTION, query, depth as u64, &filter, false).await {
+            Ok((results, tokens)) => {
+                self.embedding_tokens.fetch_add(tokens, Ordering::Relaxed);
+                for res in results {
+                    let doc_id = res.payload["doc_id"].as_str().unwrap_or_default();
+                    if !doc_id.is_empty() && !chunks.iter().any(|c| c["doc_id"] == doc_id) {
+                        chunks.push(res.payload);
+                    }
+                }
+            }
+            Err(e) => {
+                log::warn!("⚠️ Vector search unavailable, using full-text only: {}", e);
+                notes.push(format!("Vector search unavailable, showing full-text results only ({})", e));
+            }
+        }
+
+        let bm25_ids: Vec<String> = bm25.iter().map(|h| h.doc_id.clone()).collect();
+        let vector_ids: Vec<String> = chunks.iter().map(|c| c["doc_id"].as_str().unwrap_or_default().to_string()).collect();
+        let fused = search::reciprocal_rank_fusion(&[bm25_ids.clone(), vector_ids.clone()], search::RRF_K);
+
+        let mut output = String::from("### Hybrid Search Results\n\n");
+        for note in &notes {
+            output.push_str(&format!("⚠️ {}\n\n", note));
+        }
+        if fused.is_empty() {
+            output.push_str("No matching documents found.");
+        }
+        for (i, (doc_id, score)) in fused.iter().take(limit).enumerate() {
+            let hit = bm25.iter().find(|h| &h.doc_id == doc_id);
+            let chunk = chunks.iter().find(|c| c["doc_id"] == doc_id.as_str());
+            let rank = |ids: &[String]| ids.iter().position(|id| id == doc_id).map(|r| format!("#{}", r + 1)).unwrap_or_else(|| "–".to_string());
+
+            let title = hit.map(|h| h.title.as_str())
+                .or_else(|| chunk.and_then(|c| c["title"].as_str()))
+                .unwrap_or("Untitled");
+            let section = chunk.and_then(|c| c["section"].as_str()).filter(|s| !s.is_empty());
+            // Link to the best-matching section when the vector side found one
+            let uri = chunk.and_then(|c| c["uri"].as_str()).map(String::from).or_else(|| hit.map(|h| {
+                let uri = section_uri(h.project_name.as_deref().unwrap_or("unknown"), &h.sdlc_phase, &h.name, "");
+                if filter.drafts { drafts_uri(&uri) } else { uri }
+            }));
+            let snippet = match hit {
+                Some(h) => h.snippet(),
+                None => fulltext::snippet(chunk.and_then(|c| c["content"].as_str()).unwrap_or_default()),
+            };
+
+            match section {
+                Some(section) => output.push_str(&format!("{}. **{}** › {}", i + 1, title, section)),
+                None => output.push_str(&format!("{}. **{}**", i + 1, title)),
+            }
+            output.push_str(&format!(" (RRF: {:.4} · BM25 {} · Vector {})\n", score, rank(&bm25_ids), rank(&vector_ids)));
+            if let Some(uri) = uri {
+                output.push_str(&format!("   `{}`\n", uri));
+            }
+            output.push_str(&format!("   {}\n\n", snippet));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("search-hybrid", None, arguments, "success", "Hybrid search completed", duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_find_similar_incidents(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let arg = |key: &str| arguments.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+        let text = match (arg("query"), arg("title"), arg("symptom")) {
+            (Some(query), _, _) => query.to_string(),
+            (None, None, None) => return Err(anyhow::anyhow!("Provide 'query', or the 'title' / 'symptom' of the incident")),
+            (None, title, symptom) => incidents::incident_text(title.unwrap_or_default(), symptom.unwrap_or_default(), None, None, arg("language").unwrap_or_default()),
+        };
+        let limit = arguments.get("limit").and_then(|v| v.as_u64()).unwrap_or(5);
+        let filter = SearchFilter {
+            project: arg("project").map(String::from),
+            phase: None,
+            language: arg("language").map(str::to_lowercase),
+            min_score: arguments.get("min_score").and_then(|v| v.as_f64()).map(|s| s as f32),
+            ..Default::default()
+        };
+
+        log::info!("🚨 Finding incidents similar to: '{}'", text.lines().next().unwrap_or_default());
+        let indexer = IncidentIndexer::new(self.db.clone(), self.vector.clone());
+        let (hits, tokens) = indexer.find_similar(&text, &filter, limit).await?;
+        self.embedding_tokens.fetch_add(tokens, Ordering::Relaxed);
+
+        let mut output = String::from("### Similar Incidents\n\n");
+        if hits.is_empty() {
+            output.push_str("No similar incidents found.");
+        }
+        for (i, hit) in hits.iter().enumerate() {
+            output.push_str(&incidents::format_hit(i + 1, hit));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("find-similar-incidents", None, arguments, "success", &format!("{} similar incidents", hits.len()), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    /// A response listing likely duplicates of the incident being reported, or `None` to go ahead and create it
+    async fn check_incident_duplicates(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<Option<JsonRpcResponse>> {
+        let arg = |key: &str| arguments.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+        let text = incidents::incident_text(arg("title"), arg("symptom"), None, None, arg("language"));
+        let filter = SearchFilter {
+            project: arguments.get("project").and_then(|v| v.as_str()).map(String::from),
+            phase: None,
+            min_score: Some(arguments.get("duplicate_threshold").and_then(|v| v.as_f64()).unwrap_or(DUPLICATE_MIN_SCORE) as f32),
+            ..Default::default()
+        };
+
+        let indexer = IncidentIndexer::new(self.db.clone(), self.vector.clone());
+        let (output, is_error) = match indexer.find_similar(&text, &filter, 3).await {
+            Ok((hits, tokens)) => {
+                self.embedding_tokens.fetch_add(tokens, Ordering::Relaxed);
+                if hits.is_empty() {
+                    return Ok(None);
+                }
+                let mut output = String::from("### Possible Duplicate Incidents\n\n⚠️ No incident was created: these existing incidents look like the same problem.\n\n");
+                for (i, hit) in hits.iter().enumerate() {
+                    output.push_str(&incidents::format_hit(i + 1, hit));
+                }
+                output.push_str("If one of them matches, use `update-incident` on it. If this is a new problem, call `report-incident` again with `check_duplicates: false`.");
+                (output, false)
+            }
+            Err(e) => {
+                log::warn!("⚠️ Incident duplicate check failed: {}", e);
+                (format!("Duplicate check failed, no incident was created: {}\n\nCall `report-incident` with `check_duplicates: false` to create it without the check.", e), true)
+            }
+        };
+
+        let message = if is_error { "Duplicate check failed" } else { "Likely duplicates found, incident not created" };
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("report-incident", None, arguments, if is_error { "error" } else { "success" }, message, duration_ms).await;
+
+        Ok(Some(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(is_error),
+            }),
+            req.id.clone()
+        )))
+    }
+
+    async fn handle_list_document_versions(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let limit = arguments.get("limit").and_then(|v| v.as_u64()).unwrap_or(20);
+        let doc_id = history::resolve(&self.db, &key).await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", key.uri()))?;
+        let versions = history::list_versions(&self.db, &doc_id, limit).await?;
+
+        let mut output = format!("### Versions of `{}`\n\n", key.uri());
+        output.push_str("| Version | Change | Title | Author | At | Lines | Reason |\n|---|---|---|---|---|---|---|\n");
+        for v in &versions {
+            let renamed = if v.name != key.name { format!(" (as `{}`)", v.name) } else { String::new() };
+            output.push_str(&format!(
+                "| {} | {}{} | {} | {} | {} | {} | {} |\n",
+                v.version, v.event, renamed, v.title, v.author, v.created_at, v.lines,
+                v.reason.as_deref().unwrap_or("").replace('|', "\\|").replace('\n', " "),
+            ));
+        }
+        if versions.is_empty() {
+            output.push_str("\nNo recorded versions.");
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("list-document-versions", None, arguments, "success", &format!("{} versions", versions.len()), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_diff_document(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let doc_id = history::resolve(&self.db, &key).await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", key.uri()))?;
+
+        // Defaults: the latest version against the one before it
+        let to = history::get_version(&self.db, &doc_id, arguments.get("to_version").and_then(|v| v.as_i64())).await?
+            .ok_or_else(|| anyhow::anyhow!("Version {} of {} not found", arguments["to_version"], key.uri()))?;
+        let from_version = arguments.get("from_version").and_then(|v| v.as_i64()).unwrap_or(to.version - 1);
+        let from = history::get_version(&self.db, &doc_id, Some(from_version)).await?
+            .ok_or_else(|| anyhow::anyhow!("Version {} of {} not found", from_version, key.uri()))?;
+
+        let (text, stats) = diff::unified_diff(
+            &from.content, &to.content,
+            &format!("{} (version {})", key.uri(), from.version),
+            &format!("{} (version {})", key.uri(), to.version),
+        );
+        let mut output = format!("### Diff of `{}`: version {} → {}\n\n", key.uri(), from.version, to.version);
+        if from.title != to.title {
+            output.push_str(&format!("Title: \"{}\" → \"{}\"\n\n", from.title, to.title));
+        }
+        if text.is_empty() {
+            output.push_str("The content is identical.");
+        } else {
+            output.push_str(&format!("+{} / -{} lines\n\n```diff\n{}```", stats.added, stats.removed, text));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("diff-document", None, arguments, "success", &format!("Diff {} -> {}", from.version, to.version), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_restore_document_version(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let version = arguments.get("version").and_then(|v| v.as_i64())
+            .ok_or_else(|| anyhow::anyhow!("Missing 'version' argument"))?;
+        let doc_id = history::resolve(&self.db, &key).await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", key.uri()))?;
+        let reason = change_reason(arguments).unwrap_or_else(|| format!("Restored version {}", version));
+        let since = history::now(&self.db).await?;
+
+        let mut output = match history::restore(&self.db, &doc_id, version, &self.principal, &reason).await {
+            Ok(Some(new_version)) => format!("Restored `{}` to version {}; the current document is now version {}.", key.uri(), version, new_version),
+            Ok(None) => format!("`{}` already matches version {}; nothing changed.", key.uri(), version),
+            Err(e) => {
+                let duration_ms = start.elapsed().as_millis() as i64;
+                self.record_audit_log("restore-document-version", None, arguments, "error", &e.to_string(), duration_ms).await;
+                return Err(e);
+            }
+        };
+        // A restore can take the document back to an older name
+        if let Err(e) = links::refresh(&self.db, Some(std::slice::from_ref(&doc_id))).await {
+            log::warn!("⚠️ Failed to refresh links of {}: {}", key.uri(), e);
+        }
+        let warnings = links::removal_warnings(&self.db, &history::removed_since(&self.db, &since, &self.principal).await?).await?;
+        if !warnings.is_empty() {
+            output.push_str(&format!("\n\n### Broken inbound links\n\n{}", warnings));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("restore-document-version", None, arguments, "success", &format!("Restored version {}", version), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    /// Validate `content` against the document's types and write it if it is still at `expected`.
+    /// `Ok(Err(..))` carries problems the caller can fix: a stale version or a blocking violation.
+    async fn write_document(
+        &self,
+        key: &DocumentKey,
+        doc: &edit::CurrentDocument,
+        content: &str,
+        expected: Option<i64>,
+        reason: Option<&str>,
+    ) -> Result<std::result::Result<(i64, Vec<lint::Violation>), String>> {
+        if let Err(conflict) = doc.check_base(key.uri(), expected) {
+            return Ok(Err(conflict.to_string()));
+        }
+        let violations = lint::validate(&lint::load_types(&self.db).await?, &key.phase, &key.name, content, &doc.metadata);
+        if violations.iter().any(|v| v.blocking) {
+            return Ok(Err(format!(
+                "Not saved: `{}` would violate its document type (❌ blocks the write).\n\n{}",
+                key.uri(), lint::format_violations(&violations)
+            )));
+        }
+        match edit::update_content(&self.db, key, content, expected, &self.principal, reason).await {
+            Ok(version) => {
+                if version != doc.version {
+                    self.refresh_links(key).await;
+                }
+                Ok(Ok((version, violations)))
+            }
+            Err(e) => match e.downcast_ref::<VersionConflict>() {
+                Some(conflict) => Ok(Err(conflict.to_string())),
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Re-parse the links of the document at `key`; a failure only costs link freshness
+    async fn refresh_links(&self, key: &DocumentKey) {
+        let refreshed = match history::resolve(&self.db, key).await {
+            Ok(Some(id)) => links::refresh(&self.db, Some(&[id])).await.map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = refreshed {
+            log::warn!("⚠️ Failed to refresh links of {}: {}", key.uri(), e);
+        }
+    }
+
+    /// Tool result for update-document / patch-document, with any remaining violations listed after `message`
+    async fn document_write_response(
+        &self,
+        req: &JsonRpcRequest,
+        name: &str,
+        arguments: &serde_json::Value,
+        start: Instant,
+        outcome: Result<std::result::Result<(String, Vec<lint::Violation>), String>>,
+    ) -> Result<JsonRpcResponse> {
+        let duration_ms = start.elapsed().as_millis() as i64;
+        let (output, is_error) = match outcome {
+            Ok(Ok((message, violations))) if violations.is_empty() => (message, false),
+            Ok(Ok((message, violations))) => (format!("{}\n\nDocument standard violations:\n{}", message, lint::format_violations(&violations)), false),
+            Ok(Err(message)) => (message, true),
+            Err(e) => {
+                self.record_audit_log(name, None, arguments, "error", &e.to_string(), duration_ms).await;
+                return Err(e);
+            }
+        };
+        let status = if is_error { "error" } else { "success" };
+        self.record_audit_log(name, None, arguments, status, &output, duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(is_error),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_update_document(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let content = arguments.get("content").and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing 'content' argument"))?;
+        let expected = arguments.get("expected_version").and_then(|v| v.as_i64());
+        let reason = change_reason(arguments);
+
+        let doc = edit::current(&self.db, &key).await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", key.uri()))?;
+        let outcome = self.write_document(&key, &doc, content, expected, reason.as_deref()).await
+            .map(|written| written.map(|(version, violations)| {
+                let message = if version == doc.version {
+                    format!("`{}` already had this content; it stays at version {}.", key.uri(), version)
+                } else {
+                    format!("Updated `{}`; it is now version {}.", key.uri(), version)
+                };
+                (message, violations)
+            }));
+        self.document_write_response(req, "update-document", arguments, start, outcome).await
+    }
+
+    async fn handle_patch_document(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let path = match arguments.get("heading_path") {
+            Some(serde_json::Value::Array(items)) => items.iter().filter_map(|v| v.as_str()).flat_map(markdown::parse_heading_path).collect(),
+            Some(serde_json::Value::String(s)) => markdown::parse_heading_path(s),
+            _ => Vec::new(),
+        };
+        let operation = PatchOperation::parse(arguments.get("operation").and_then(|v| v.as_str()).unwrap_or_default())?;
+        let expected = arguments.get("expected_version").and_then(|v| v.as_i64());
+        let reason = change_reason(arguments);
+
+        let doc = edit::current(&self.db, &key).await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", key.uri()))?;
+        // Stale writes, missing sections and ambiguous headings go back as tool errors the caller can fix
+        let outcome = if let Err(conflict) = doc.check_base(key.uri(), expected) {
+            Ok(Err(conflict.to_string()))
+        } else {
+            match patch::apply(&doc.content, &path, operation, arguments.get("content").and_then(|v| v.as_str())) {
+                Err(e) => Ok(Err(e.to_string())),
+                Ok(patched) => self.write_document(&key, &doc, &patched.content, expected, reason.as_deref()).await
+                    .map(|written| written.map(|(version, violations)| {
+                        let (_, stats) = diff::unified_diff(&doc.content, &patched.content, "", "");
+                        let message = format!(
+                            "Patched `{}`: {} of '{}' (line {}), +{} / -{} lines; it is now version {}.",
+                            key.uri(), operation.as_str(), patched.section, patched.line, stats.added, stats.removed, version
+                        );
+                        (message, violations)
+                    })),
+            }
+        };
+        self.document_write_response(req, "patch-document", arguments, start, outcome).await
+    }
+
+    async fn handle_lint_documents(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let arg = |key: &str| arguments.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(String::from);
+        let scope = lint::LintScope { project: arg("project"), phase: arg("phase"), name: arg("name"), ids: None };
+        let reports = lint::lint(&self.db, scope).await?;
+
+        let failing = reports.iter().filter(|r| !r.violations.is_empty()).count();
+        let violations: usize = reports.iter().map(|r| r.violations.len()).sum();
+        let mut output = format!(
+            "### Document Lint\n\nChecked {} documents: {} with violations ({} in total).\n\n",
+            reports.len(), failing, violations
+        );
+        output.push_str(&lint::format_reports(&reports));
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("lint-documents", None, arguments, "success", &format!("{} of {} documents with violations", failing, reports.len()), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_list_backlinks(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let backlinks = links::backlinks(&self.db, &key).await?;
+
+        let mut output = format!("### Backlinks to `{}`\n\n", key.uri());
+        if edit::current(&self.db, &key).await?.is_none() {
+            output.push_str("⚠️ No document exists at this address, so these links are broken.\n\n");
+        }
+        for link in &backlinks {
+            output.push_str(&format!("- `{}` line {}: `{}`\n", link.source().uri(), link.line, link.target));
+        }
+        if backlinks.is_empty() {
+            output.push_str("No documents link here.");
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("list-backlinks", None, arguments, "success", &format!("{} backlinks", backlinks.len()), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    async fn handle_check_links(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let project = arguments.get("project").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(String::from);
+        let (total, broken) = links::check(&self.db, project).await?;
+
+        let mut output = format!("### Link Check\n\nFound {} links in all documents: {} broken.\n", total, broken.len());
+        let mut source = String::new();
+        for b in &broken {
+            let uri = b.link.source().uri();
+            if uri != source {
+                output.push_str(&format!("\n#### `{}`\n\n", uri));
+                source = uri;
+            }
+            // Relative links show where they resolve to
+            let resolved = links::address_uri(&b.link.address);
+            let target = if b.link.target.starts_with(&resolved) { format!("`{}`", b.link.target) } else { format!("`{}` (`{}`)", b.link.target, resolved) };
+            output.push_str(&format!("- line {}: {} → {}\n", b.link.line, target, b.problem));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        self.record_audit_log("check-links", None, arguments, "success", &format!("{} broken of {} links", broken.len(), total), duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(false),
+            }),
+            req.id.clone()
+        ))
+    }
+
+    /// submit-document, approve-document, reject-document and deprecate-document. Transitions the
+    /// document's state does not allow go back as tool errors explaining why.
+    async fn handle_review_document(&self, req: &JsonRpcRequest, name: &str, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
+        let key = DocumentKey::from_arguments(arguments)?;
+        let uri = key.uri();
+        let note = arguments.get("note").and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty());
+        let state = review::state(&self.db, &key).await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", uri))?;
+
+        let outcome: Result<std::result::Result<String, String>> = match name {
+            "submit-document" => {
+                // Reviewers of the last submission unless new ones are named
+                let reviewers: Vec<String> = match arguments.get("reviewers").and_then(|v| v.as_array()) {
+                    Some(items) => items.iter().filter_map(|v| v.as_str()).map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect(),
+                    None => state.reviewers.clone(),
+                };
+                match review::check_submit(&uri, &state, &reviewers, &self.principal) {
+                    Err(message) => Ok(Err(message)),
+                    Ok(()) => review::submit(&self.db, &key, state.version, reviewers.clone(), &self.principal, note).await
+                        .map(|()| Ok(format!("Submitted `{}` version {} for review by {}.", uri, state.version, reviewers.join(", ")))),
+                }
+            }
+            "approve-document" | "reject-document" => {
+                let approve = name == "approve-document";
+                match review::check_review(&uri, &state, &self.principal, approve, note) {
+                    Err(message) => Ok(Err(message)),
+                    Ok(()) if approve => review::approve(&self.db, &key, state.version, &self.principal, note).await
+                        .map(|()| Ok(format!("Approved `{}`: version {} is now the published edition.", uri, state.version))),
+                    Ok(()) => review::reject(&self.db, &key, state.version, &self.principal, note.unwrap_or_default()).await
+                        .map(|()| Ok(format!("Rejected `{}` version {}; it is back to draft.", uri, state.version))),
+                }
+            }
+            _ => match review::check_deprecate(&uri, &state) {
+                Err(message) => Ok(Err(message)),
+                Ok(()) => review::deprecate(&self.db, &key, state.version, &self.principal, note).await
+                    .map(|()| Ok(format!(
+                        "Deprecated the published edition of `{}` (version {}); it no longer shows up in searches.",
+                        uri, state.published_version.unwrap_or(state.version)
+                    ))),
+            },
+        };
+
+        let duration_ms = start.elapsed().as_millis() as i64;
+        let (output, is_error) = match outcome {
+            Ok(Ok(message)) => (message, false),
+            Ok(Err(message)) => (message, true),
+            Err(e) => {
+                self.record_audit_log(name, None, arguments, "error", &e.to_string(), duration_ms).await;
+                return Err(e);
+            }
+        };
+        self.record_audit_log(name, None, arguments, if is_error { "error" } else { "success" }, &output, duration_ms).await;
+
+        Ok(JsonRpcResponse::success(
+            json!(crate::core::mcp::types::CallToolResult {
+                content: vec![crate::core::mcp::types::ToolContent {
+                    content_type: "text".to_string(),
+                    text: Some(output),
+                    image: None,
+                }],
+                is_error: Some(is_error),
+            }),
+            req.id.clone()
+        ))
+    }
+
     async fn handle_index_documents(&self, req: &JsonRpcRequest, arguments: &serde_json::Value, start: Instant) -> Result<JsonRpcResponse> {
-        log::info!("⚙️ Starting document re-indexing into Qdrant...");
-        
-        // 1. Fetch all documentation from SurrealDB - select specific fields to avoid enum issues
-        let mut result = self.db.query("SELECT name, title, content, sdlc_phase, string::concat(type::string(id)) AS doc_id FROM mcp_documentation").await?;
-        let docs: Vec<serde_json::Value> = match result.take(0) {
-            Ok(d) => d,
+        log::info!("⚙️ Starting incremental document indexing into the {} vector backend...", self.vector.backend().name());
+        let force = arguments.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let indexer = DocumentIndexer::new(self.db.clone(), self.vector.clone(), self.chunking.clone());
+        let report = match indexer.sync(force).await {
+            Ok(r) => r,
             Err(e) => {
-                log::error!("Failed to fetch documents: {}", e);
+                log::error!("Failed to index documents: {}", e);
+                let duration_ms = start.elapsed().as_millis() as i64;
+                self.record_audit_log("index-documents", None, arguments, "error", &e.to_string(), duration_ms).await;
                 return Ok(JsonRpcResponse::success(
                     json!(crate::core::mcp::types::CallToolResult {
                         content: vec![crate::core::mcp::types::ToolContent {
                             content_type: "text".to_string(),
-                            text: Some(format!("Error fetching documents: {}", e)),
+                            text: Some(format!("Error indexing documents: {}", e)),
                             image: None,
                         }],
                         is_error: Some(true),
@@ -649,55 +1667,22 @@ impl McpHandler {
                 ));
             }
         };
+        self.embedding_tokens.fetch_add(report.tokens, Ordering::Relaxed);
 
-        // 2. Ensure collection exists (OpenAI text-embedding-3-small is 1536 dims)
-        log::info!("📦 Ensuring Qdrant collection exists...");
-        self.vector.ensure_collection("documentation", 1536).await?;
-        log::info!("✅ Qdrant collection ready");
-
-        let mut indexed_count = 0;
-        let mut errors = Vec::new();
-
-        log::info!("📚 Processing {} documents for indexing...", docs.len());
-
-        for doc in docs {
-            let id = doc.get("doc_id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
-            let name = doc.get("name").and_then(|v| v.as_str()).unwrap_or("Unnamed Document");
-            let title = doc.get("title").and_then(|v| v.as_str()).unwrap_or(name);
-            let content = doc.get("content").and_then(|v| v.as_str()).unwrap_or_default();
-            let phase = doc.get("sdlc_phase").and_then(|v| v.as_str()).unwrap_or("unknown");
-            
-            if content.is_empty() { continue; }
-
-            // Combine title and content for better embedding
-            let text_to_embed = format!("Title: {}\nPhase: {}\n\n{}", title, phase, content);
-            
-            let metadata = json!({
-                "title": title,
-                "phase": phase,
-                "doc_id": id
-            });
-
-            // Use doc name or part of it as id if string id is not a valid UUID
-            let qdrant_id = uuid::Uuid::new_v4().to_string(); 
-
-            log::info!("🔄 Indexing document: {}", title);
-            match self.vector.upsert_document("documentation", qdrant_id, &text_to_embed, metadata).await {
-                Ok(_) => indexed_count += 1,
-                Err(e) => {
-                    log::error!("🔥 Failed to index document {}: {}", name, e);
-                    errors.push(format!("{}: {}", name, e));
-                }
-            }
-        }
-
-        let mut output = format!("✅ Successfully indexed {} documents into Qdrant.", indexed_count);
-        if !errors.is_empty() {
-            output.push_str(&format!("\n\n⚠️ Encountered {} errors:\n- {}", errors.len(), errors.join("\n- ")));
+        let mut output = String::from("### Document Indexing\n\nCounts are per section chunk.\n\n");
+        output.push_str(&format!("- **Added**: {}\n", report.added));
+        output.push_str(&format!("- **Updated**: {}\n", report.updated));
+        output.push_str(&format!("- **Metadata refreshed**: {} (not re-embedded)\n", report.refreshed));
+        output.push_str(&format!("- **Unchanged**: {} (not re-embedded)\n", report.unchanged));
+        output.push_str(&format!("- **Deleted**: {}\n", report.deleted));
+        output.push_str(&format!("- **Embedding tokens**: {}\n", report.tokens));
+        if !report.errors.is_empty() {
+            output.push_str(&format!("\n⚠️ Encountered {} errors:\n- {}", report.errors.len(), report.errors.join("\n- ")));
         }
 
+        let message = format!("Indexed: {} added, {} updated, {} refreshed, {} unchanged, {} deleted", report.added, report.updated, report.refreshed, report.unchanged, report.deleted);
         let duration_ms = start.elapsed().as_millis() as i64;
-        self.record_audit_log("index-documents", None, arguments, "success", &format!("Indexed {} docs", indexed_count), duration_ms).await;
+        self.record_audit_log("index-documents", None, arguments, "success", &message, duration_ms).await;
 
         Ok(JsonRpcResponse::success(
             json!(crate::core::mcp::types::CallToolResult {
@@ -712,3 +1697,8 @@ impl McpHandler {
         ))
     }
 }
+
+/// The optional `reason` argument recorded with document changes
+fn change_reason(arguments: &serde_json::Value) -> Option<String> {
+    arguments.get("reason").and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty()).map(String::from)
+}
@@ -64,7 +64,16 @@ pub struct ServerInfo {
     pub version: String,
 }
 
+/// `clientInfo` sent by the client in `initialize`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
 pub mod handler;
 pub mod types;
 pub mod rules;
+pub mod quota;
 pub mod tools;
@@ -0,0 +1,445 @@
+use crate::core::database::Database;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Quota rows with this tool name apply to every tool (usage is pooled)
+pub const ALL_TOOLS: &str = "*";
+/// Marks the error a reservation throws when a quota is exhausted
+const EXHAUSTED: &str = "quota exhausted:";
+/// Reservations that fail for other reasons (e.g. a write conflict with a parallel call) are retried
+const RESERVE_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QuotaConfig {
+    /// Let calls through when quotas cannot be checked, instead of rejecting them
+    pub fail_open: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaScope {
+    Principal,
+    Project,
+}
+
+impl QuotaScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaScope::Principal => "principal",
+            QuotaScope::Project => "project",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaPeriod {
+    Hour,
+    Day,
+    Month,
+}
+
+impl QuotaPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaPeriod::Hour => "hour",
+            QuotaPeriod::Day => "day",
+            QuotaPeriod::Month => "month",
+        }
+    }
+
+    /// Start of the (UTC) window containing `now`
+    pub fn window_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let day = Utc.with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0).unwrap();
+        match self {
+            QuotaPeriod::Hour => day + Duration::hours(now.hour() as i64),
+            QuotaPeriod::Day => day,
+            QuotaPeriod::Month => Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0).unwrap(),
+        }
+    }
+
+    /// Start of the window following the one that starts at `start`
+    pub fn window_end(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            QuotaPeriod::Hour => start + Duration::hours(1),
+            QuotaPeriod::Day => start + Duration::days(1),
+            QuotaPeriod::Month => {
+                let (year, month) = if start.month() == 12 { (start.year() + 1, 1) } else { (start.year(), start.month() + 1) };
+                Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap()
+            }
+        }
+    }
+}
+
+/// A quota definition from `mcp_quotas`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Quota {
+    pub tool_name: String,
+    pub scope: QuotaScope,
+    /// Specific principal/project this quota is for; `None` applies to each one individually
+    pub subject: Option<String>,
+    pub max_calls: Option<i64>,
+    pub max_tokens: Option<i64>,
+    pub period: QuotaPeriod,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct UsageCounters {
+    calls: i64,
+    tokens: i64,
+}
+
+/// Where a principal or project stands against one quota in the current window
+#[derive(Debug, Clone)]
+pub struct QuotaStanding {
+    pub quota: Quota,
+    pub subject: String,
+    pub window_start: DateTime<Utc>,
+    pub resets_at: DateTime<Utc>,
+    pub calls: i64,
+    pub tokens: i64,
+}
+
+impl QuotaStanding {
+    pub fn exceeded(&self) -> bool {
+        self.quota.max_calls.is_some_and(|max| self.calls >= max)
+            || self.quota.max_tokens.is_some_and(|max| self.tokens >= max)
+    }
+
+    fn usage_key(&self) -> Vec<String> {
+        vec![
+            self.quota.scope.as_str().to_string(),
+            self.subject.clone(),
+            self.quota.tool_name.clone(),
+            self.quota.period.as_str().to_string(),
+            self.window_start.to_rfc3339(),
+        ]
+    }
+
+    fn describe_limit(used: i64, max: Option<i64>) -> String {
+        match max {
+            Some(max) => format!("{} / {}", used, max),
+            None => format!("{} / ∞", used),
+        }
+    }
+
+    pub fn calls_display(&self) -> String {
+        Self::describe_limit(self.calls, self.quota.max_calls)
+    }
+
+    pub fn tokens_display(&self) -> String {
+        Self::describe_limit(self.tokens, self.quota.max_tokens)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Charge {
+    index: usize,
+    key: Vec<String>,
+    scope: &'static str,
+    subject: String,
+    tool: String,
+    period: &'static str,
+    window_start: surrealdb::sql::Datetime,
+    max_calls: i64,
+    max_tokens: i64,
+}
+
+/// What to do when quotas could not be checked: reject the call unless configured to fail open
+fn lookup_failed(config: &QuotaConfig, tool_name: &str, e: anyhow::Error) -> Result<Vec<QuotaStanding>> {
+    if config.fail_open {
+        log::warn!("⚠️ Quota check failed for {}, letting the call through (QUOTA_FAIL_OPEN): {}", tool_name, e);
+        return Ok(Vec::new());
+    }
+    log::error!("🔥 Quota check failed for {}: {}", tool_name, e);
+    Err(anyhow!("Quotas for tool '{}' could not be checked; try again later", tool_name))
+}
+
+/// `quota`'s standing in the current window, if it applies to the principal or the project charged
+fn standing_for(quota: Quota, principal: &str, project: Option<&str>, now: DateTime<Utc>) -> Option<QuotaStanding> {
+    let subject = match quota.scope {
+        QuotaScope::Principal => principal,
+        QuotaScope::Project => project?,
+    };
+    if quota.subject.as_deref().is_some_and(|s| s != subject) {
+        return None;
+    }
+    let window_start = quota.period.window_start(now);
+    Some(QuotaStanding {
+        resets_at: quota.period.window_end(window_start),
+        subject: subject.to_string(),
+        quota,
+        window_start,
+        calls: 0,
+        tokens: 0,
+    })
+}
+
+/// Enforces per-tool call/token quotas per principal and per project, persisted in `mcp_usage`
+pub struct QuotaManager {
+    db: Database,
+    config: QuotaConfig,
+}
+
+impl QuotaManager {
+    pub fn new(db: Database, config: QuotaConfig) -> Self {
+        Self { db, config }
+    }
+
+    async fn load_quotas(&self, tool_name: Option<&str>) -> Result<Vec<Quota>> {
+        let mut result = match tool_name {
+            Some(tool) => self.db.query("
+                SELECT tool_name, scope, subject, max_calls, max_tokens, period FROM mcp_quotas
+                WHERE active = true AND tool_name IN [$tool, $all]
+            ")
+            .bind(("tool", tool.to_string()))
+            .bind(("all", ALL_TOOLS))
+            .await?,
+            None => self.db.query("
+                SELECT tool_name, scope, subject, max_calls, max_tokens, period FROM mcp_quotas
+                WHERE active = true ORDER BY tool_name
+            ")
+            .await?,
+        };
+        let quotas: Vec<Quota> = result.take(0)?;
+        Ok(quotas)
+    }
+
+    /// Fill in the usage recorded for `standing`'s window
+    async fn load_usage(&self, mut standing: QuotaStanding) -> Result<QuotaStanding> {
+        let mut result = self.db.query("SELECT calls, tokens FROM type::thing('mcp_usage', $key)")
+            .bind(("key", standing.usage_key()))
+            .await?;
+        let usage: Vec<UsageCounters> = result.take(0)?;
+        if let Some(usage) = usage.into_iter().next() {
+            standing.calls = usage.calls;
+            standing.tokens = usage.tokens;
+        }
+        Ok(standing)
+    }
+
+    /// Quotas that apply to the caller in the current window, without their usage
+    async fn applicable(&self, tool_name: Option<&str>, principal: &str, project: Option<&str>) -> Result<Vec<QuotaStanding>> {
+        let now = Utc::now();
+        Ok(self.load_quotas(tool_name).await?
+            .into_iter()
+            .filter_map(|quota| standing_for(quota, principal, project, now))
+            .collect())
+    }
+
+    /// Current standing for every quota that applies to the caller (optionally limited to one tool).
+    /// Project quotas are matched against the project each tool's calls are charged to, resolved as
+    /// in [`QuotaManager::reserve`]; pooled quotas without a tool use the `project` argument alone.
+    pub async fn standings(&self, tool_name: Option<&str>, principal: &str, project: Option<&str>) -> Result<Vec<QuotaStanding>> {
+        let now = Utc::now();
+        let mut resolved: HashMap<String, Option<String>> = HashMap::new();
+        let mut standings = Vec::new();
+        for quota in self.load_quotas(tool_name).await? {
+            let tool = tool_name.unwrap_or(&quota.tool_name).to_string();
+            let charged = match resolved.get(&tool) {
+                Some(charged) => charged.clone(),
+                None => {
+                    let charged = self.resolve_project(&tool, project).await?;
+                    resolved.insert(tool, charged.clone());
+                    charged
+                }
+            };
+            if let Some(standing) = standing_for(quota, principal, charged.as_deref(), now) {
+                standings.push(self.load_usage(standing).await?);
+            }
+        }
+        Ok(standings)
+    }
+
+    /// The project a call is charged to: the `project` argument when it names a known project,
+    /// else the project the tool belongs to. Callers cannot dodge project quotas by leaving it out
+    /// or making one up.
+    async fn resolve_project(&self, tool_name: &str, project: Option<&str>) -> Result<Option<String>> {
+        let mut result = self.db.query("
+            LET $named = (SELECT VALUE name FROM mcp_projects WHERE name = $project)[0];
+            RETURN $named OR (SELECT VALUE project_id.name FROM mcp_tools WHERE name = $tool)[0];
+        ")
+            .bind(("project", project.map(String::from)))
+            .bind(("tool", tool_name.to_string()))
+            .await?;
+        Ok(result.take(1)?)
+    }
+
+    /// Charge one call against every quota that applies, unless one of them is exhausted. Check and
+    /// charge happen in a single transaction, so parallel calls cannot overshoot a budget. Returns
+    /// the standings to charge tokens to afterwards with [`QuotaManager::record`].
+    pub async fn reserve(&self, tool_name: &str, principal: &str, project: Option<&str>) -> Result<Vec<QuotaStanding>> {
+        let standings = match self.resolve_project(tool_name, project).await {
+            Ok(project) => self.applicable(Some(tool_name), principal, project.as_deref()).await,
+            Err(e) => Err(e),
+        };
+        let standings = match standings {
+            Ok(s) if s.is_empty() => return Ok(s),
+            Ok(s) => s,
+            Err(e) => return lookup_failed(&self.config, tool_name, e),
+        };
+
+        let mut attempt = 1;
+        loop {
+            let e = match self.charge_calls(&standings).await {
+                Ok(()) => return Ok(standings),
+                Err(e) => e,
+            };
+            let exhausted = e.to_string().split(EXHAUSTED).nth(1)
+                .and_then(|rest| rest.trim_start().split(|c: char| !c.is_ascii_digit()).next()?.parse::<usize>().ok())
+                .and_then(|i| standings.get(i));
+            if let Some(standing) = exhausted {
+                return Err(self.exceeded_error(tool_name, standing.clone()).await);
+            }
+            if attempt >= RESERVE_ATTEMPTS {
+                return lookup_failed(&self.config, tool_name, e);
+            }
+            log::debug!("Retrying quota reservation for {}: {}", tool_name, e);
+            attempt += 1;
+        }
+    }
+
+    async fn charge_calls(&self, standings: &[QuotaStanding]) -> Result<()> {
+        let charges: Vec<Charge> = standings.iter().enumerate().map(|(index, s)| Charge {
+            index,
+            key: s.usage_key(),
+            scope: s.quota.scope.as_str(),
+            subject: s.subject.clone(),
+            tool: s.quota.tool_name.clone(),
+            period: s.quota.period.as_str(),
+            window_start: surrealdb::sql::Datetime::from(s.window_start),
+            max_calls: s.quota.max_calls.unwrap_or(i64::MAX),
+            max_tokens: s.quota.max_tokens.unwrap_or(i64::MAX),
+        }).collect();
+
+        self.db.query(format!("
+            BEGIN TRANSACTION;
+            FOR $c IN $charges {{
+                LET $after = UPSERT type::thing('mcp_usage', $c.key) SET
+                    scope = $c.scope, subject = $c.subject, tool_name = $c.tool, period = $c.period,
+                    window_start = $c.window_start, calls += 1, updated_at = time::now()
+                    WHERE calls < $c.max_calls AND tokens < $c.max_tokens
+                    RETURN AFTER;
+                IF array::len($after) = 0 {{ THROW '{} ' + <string> $c.index; }};
+            }};
+            COMMIT TRANSACTION;
+        ", EXHAUSTED))
+            .bind(("charges", charges))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn exceeded_error(&self, tool_name: &str, standing: QuotaStanding) -> anyhow::Error {
+        let s = match self.load_usage(standing.clone()).await {
+            Ok(s) => s,
+            Err(_) => standing,
+        };
+        anyhow!(
+            "Quota exceeded for tool '{}' ({} '{}'): calls {}, tokens {} per {}. Resets at {}.",
+            tool_name,
+            s.quota.scope.as_str(),
+            s.subject,
+            s.calls_display(),
+            s.tokens_display(),
+            s.quota.period.as_str(),
+            s.resets_at.to_rfc3339()
+        )
+    }
+
+    /// Charge the embedding tokens a call used against the windows it reserved
+    pub async fn record(&self, standings: &[QuotaStanding], tokens: u32) {
+        if tokens == 0 {
+            return;
+        }
+        for s in standings {
+            let update = self.db.query("
+                UPDATE type::thing('mcp_usage', $key) SET tokens += $tokens, updated_at = time::now()
+            ")
+            .bind(("key", s.usage_key()))
+            .bind(("tokens", tokens as i64));
+
+            if let Err(e) = update.await {
+                log::error!("🔥 Failed to record usage for {} ({}): {}", s.quota.tool_name, s.subject, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(max_calls: Option<i64>, max_tokens: Option<i64>) -> Quota {
+        Quota {
+            tool_name: "index-documents".to_string(),
+            scope: QuotaScope::Principal,
+            subject: None,
+            max_calls,
+            max_tokens,
+            period: QuotaPeriod::Day,
+        }
+    }
+
+    #[test]
+    fn test_period_windows() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 17, 42, 5).unwrap();
+
+        let hour = QuotaPeriod::Hour.window_start(now);
+        assert_eq!(hour, Utc.with_ymd_and_hms(2026, 12, 31, 17, 0, 0).unwrap());
+        assert_eq!(QuotaPeriod::Hour.window_end(hour), Utc.with_ymd_and_hms(2026, 12, 31, 18, 0, 0).unwrap());
+
+        let day = QuotaPeriod::Day.window_start(now);
+        assert_eq!(QuotaPeriod::Day.window_end(day), Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+
+        let month = QuotaPeriod::Month.window_start(now);
+        assert_eq!(month, Utc.with_ymd_and_hms(2026, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(QuotaPeriod::Month.window_end(month), Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_standing_exceeded() {
+        let now = Utc::now();
+        let mut standing = QuotaStanding {
+            quota: quota(Some(2), Some(1000)),
+            subject: "alice".to_string(),
+            window_start: now,
+            resets_at: now,
+            calls: 1,
+            tokens: 999,
+        };
+        assert!(!standing.exceeded());
+
+        standing.calls = 2;
+        assert!(standing.exceeded());
+
+        standing.calls = 0;
+        standing.tokens = 1000;
+        assert!(standing.exceeded());
+
+        standing.quota = quota(None, None);
+        assert!(!standing.exceeded());
+        assert_eq!(standing.calls_display(), "0 / ∞");
+    }
+
+    #[test]
+    fn test_project_quota_needs_charged_project() {
+        let now = Utc::now();
+        let project_quota = || Quota { scope: QuotaScope::Project, subject: Some("kyx-infra".to_string()), ..quota(Some(10), None) };
+
+        assert!(standing_for(project_quota(), "alice", None, now).is_none());
+        assert!(standing_for(project_quota(), "alice", Some("kyx-web"), now).is_none());
+        let standing = standing_for(project_quota(), "alice", Some("kyx-infra"), now).unwrap();
+        assert_eq!(standing.subject, "kyx-infra");
+        assert_eq!(standing_for(quota(Some(10), None), "alice", None, now).unwrap().subject, "alice");
+    }
+
+    #[test]
+    fn test_lookup_failure_fails_closed() {
+        let failure = || anyhow!("connection reset");
+        assert!(lookup_failed(&QuotaConfig::default(), "index-documents", failure()).is_err());
+        assert!(lookup_failed(&QuotaConfig { fail_open: true }, "index-documents", failure()).unwrap().is_empty());
+    }
+}
@@ -17,6 +17,8 @@ pub struct ResourceContent {
     pub mime_type: Option<String>,
     pub text: Option<String>,
     pub blob: Option<String>,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +27,7 @@ pub struct Tool {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(skip_serializing)]
+    #[allow(dead_code)]
     pub title: Option<String>,
     pub description: String,
     #[serde(alias = "input_schema")]
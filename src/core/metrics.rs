@@ -1,5 +1,5 @@
 use prometheus::{
-    Counter, Histogram, IntCounter, IntGauge, Registry,
+    Counter, Gauge, Histogram, IntCounter, IntGauge, Registry,
     HistogramOpts, Encoder, TextEncoder,
 };
 use lazy_static::lazy_static;
@@ -53,6 +53,18 @@ lazy_static! {
         "mcp_openai_cost_usd",
         "Estimated OpenAI cost in USD"
     ).unwrap();
+
+    /// Texts whose embedding was served from the cache
+    pub static ref EMBEDDING_CACHE_HITS_TOTAL: IntCounter = IntCounter::new(
+        "mcp_embedding_cache_hits_total",
+        "Embeddings served from the content-hash cache"
+    ).unwrap();
+
+    /// Texts that had to be sent to the embedding provider
+    pub static ref EMBEDDING_CACHE_MISSES_TOTAL: IntCounter = IntCounter::new(
+        "mcp_embedding_cache_misses_total",
+        "Embeddings requested from the provider"
+    ).unwrap();
     
     // ========================================================================
     // Session Metrics
@@ -87,6 +99,62 @@ lazy_static! {
             "Database query duration in seconds"
         )
     ).unwrap();
+    
+    // ========================================================================
+    // Audit Writer Metrics
+    // ========================================================================
+    
+    /// Audit records waiting for the background writer
+    pub static ref AUDIT_QUEUE_DEPTH: IntGauge = IntGauge::new(
+        "mcp_audit_queue_depth",
+        "Audit records waiting for the background writer"
+    ).unwrap();
+    
+    /// Audit records written to SurrealDB
+    pub static ref AUDIT_WRITTEN_TOTAL: IntCounter = IntCounter::new(
+        "mcp_audit_written_total",
+        "Audit records written to SurrealDB"
+    ).unwrap();
+    
+    /// Audit records dropped because the queue was full
+    pub static ref AUDIT_DROPPED_TOTAL: IntCounter = IntCounter::new(
+        "mcp_audit_dropped_total",
+        "Audit records dropped because the queue was full"
+    ).unwrap();
+    
+    /// Audit records spooled to disk while SurrealDB was unavailable
+    pub static ref AUDIT_SPOOLED_TOTAL: IntCounter = IntCounter::new(
+        "mcp_audit_spooled_total",
+        "Audit records spooled to disk while SurrealDB was unavailable"
+    ).unwrap();
+    
+    // ========================================================================
+    // Document Index Metrics
+    // ========================================================================
+    
+    /// Age of the oldest document change not yet reflected in the vector index
+    pub static ref INDEX_LAG_SECONDS: Gauge = Gauge::new(
+        "mcp_index_lag_seconds",
+        "Age of the oldest document change not yet re-indexed"
+    ).unwrap();
+    
+    /// Changed documents waiting for the debounce window
+    pub static ref INDEX_PENDING_DOCUMENTS: IntGauge = IntGauge::new(
+        "mcp_index_pending_documents",
+        "Changed documents waiting to be re-indexed"
+    ).unwrap();
+    
+    /// Documents re-indexed by the background indexer
+    pub static ref INDEX_REINDEXED_TOTAL: IntCounter = IntCounter::new(
+        "mcp_index_reindexed_documents_total",
+        "Documents re-indexed by the background indexer"
+    ).unwrap();
+    
+    /// Background re-index runs that failed or reported errors
+    pub static ref INDEX_ERRORS_TOTAL: IntCounter = IntCounter::new(
+        "mcp_index_errors_total",
+        "Background re-index runs that failed"
+    ).unwrap();
 }
 
 /// Initialize all metrics and register with Prometheus
@@ -100,6 +168,8 @@ pub fn init_metrics() {
     REGISTRY.register(Box::new(OPENAI_REQUESTS_TOTAL.clone())).unwrap();
     REGISTRY.register(Box::new(OPENAI_TOKENS_TOTAL.clone())).unwrap();
     REGISTRY.register(Box::new(OPENAI_COST_USD.clone())).unwrap();
+    REGISTRY.register(Box::new(EMBEDDING_CACHE_HITS_TOTAL.clone())).unwrap();
+    REGISTRY.register(Box::new(EMBEDDING_CACHE_MISSES_TOTAL.clone())).unwrap();
     
     // Session metrics
     REGISTRY.register(Box::new(ACTIVE_SESSIONS.clone())).unwrap();
@@ -109,6 +179,18 @@ pub fn init_metrics() {
     REGISTRY.register(Box::new(DB_QUERIES_TOTAL.clone())).unwrap();
     REGISTRY.register(Box::new(DB_QUERY_DURATION.clone())).unwrap();
     
+    // Audit writer metrics
+    REGISTRY.register(Box::new(AUDIT_QUEUE_DEPTH.clone())).unwrap();
+    REGISTRY.register(Box::new(AUDIT_WRITTEN_TOTAL.clone())).unwrap();
+    REGISTRY.register(Box::new(AUDIT_DROPPED_TOTAL.clone())).unwrap();
+    REGISTRY.register(Box::new(AUDIT_SPOOLED_TOTAL.clone())).unwrap();
+    
+    // Document index metrics
+    REGISTRY.register(Box::new(INDEX_LAG_SECONDS.clone())).unwrap();
+    REGISTRY.register(Box::new(INDEX_PENDING_DOCUMENTS.clone())).unwrap();
+    REGISTRY.register(Box::new(INDEX_REINDEXED_TOTAL.clone())).unwrap();
+    REGISTRY.register(Box::new(INDEX_ERRORS_TOTAL.clone())).unwrap();
+    
     log::info!("📊 Prometheus metrics initialized");
 }
 
@@ -126,7 +208,6 @@ pub fn track_tool_call(tool_name: &str, duration_ms: u64, success: bool) {
 }
 
 /// Track OpenAI API usage and calculate cost
-#[allow(dead_code)]
 pub fn track_openai_usage(tokens: u32) {
     OPENAI_REQUESTS_TOTAL.inc();
     OPENAI_TOKENS_TOTAL.inc_by(tokens as u64);
@@ -1,8 +1,13 @@
+pub mod audit;
 pub mod config;
 pub mod database;
+pub mod documents;
+pub mod embedding;
+pub mod indexing;
 
 pub mod mcp;
 pub mod metrics;
 pub mod rate_limiter;
-pub mod security;
+pub mod redaction;
+pub mod search;
 pub mod transport;
@@ -1,127 +1,355 @@
-use governor::{Quota, RateLimiter as GovernorRateLimiter, state::{InMemoryState, NotKeyed}, clock::DefaultClock};
-use nonzero_ext::nonzero;
+use governor::{
+    Quota, RateLimiter as GovernorRateLimiter,
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::num::NonZeroU32;
-use anyhow::{Result, anyhow};
 use std::sync::Arc;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::time::Duration;
 
-/// Rate limiter for controlling API usage and preventing abuse
-pub struct RateLimiter {
-    // OpenAI API: 60 requests/min (conservative limit)
-    openai_limiter: Arc<GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>,
-    
-    // Database: 1000 requests/min (high limit for internal use)
-    db_limiter: Arc<GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>,
-    
-    // Per-user limits: 100 requests/hour
-    user_limiters: Arc<Mutex<HashMap<String, GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>>>,
+/// Policy applied to every HTTP request, keyed by client IP
+pub const POLICY_HTTP: &str = "http";
+/// Policy applied to every `tools/call`, keyed by client and tool name
+pub const POLICY_TOOL: &str = "tool";
+/// Policy applied to embedding provider calls (OpenAI)
+pub const POLICY_EMBEDDINGS: &str = "embeddings";
+/// Policy applied to dynamic/raw SQL tool executions
+pub const POLICY_DATABASE: &str = "database";
+
+/// Key used by policies that are not partitioned per caller
+pub const GLOBAL_KEY: &str = "global";
+
+type KeyedLimiter = GovernorRateLimiter<
+    String,
+    DefaultKeyedStateStore<String>,
+    DefaultClock,
+    StateInformationMiddleware,
+>;
+
+/// A quota of `limit` requests per `period_secs`, e.g. `300/60s` or `100/1h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct PolicySpec {
+    pub limit: u32,
+    pub period_secs: u64,
 }
 
-impl RateLimiter {
-    pub fn new() -> Self {
+impl PolicySpec {
+    pub const fn new(limit: u32, period_secs: u64) -> Self {
+        Self { limit, period_secs }
+    }
+
+    /// Parse `<limit>/<period>` where period is seconds with an optional `s`, `m`, `h` or `d` suffix
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (limit, period) = spec.trim().split_once('/')?;
+        let limit: u32 = limit.trim().parse().ok().filter(|l| *l > 0)?;
+
+        let period = period.trim();
+        let (digits, multiplier) = match period.chars().last()? {
+            's' => (&period[..period.len() - 1], 1),
+            'm' => (&period[..period.len() - 1], 60),
+            'h' => (&period[..period.len() - 1], 3600),
+            'd' => (&period[..period.len() - 1], 86400),
+            _ => (period, 1),
+        };
+        let period_secs = digits.trim().parse::<u64>().ok().filter(|p| *p > 0)? * multiplier;
+
+        Some(Self { limit, period_secs })
+    }
+
+    fn quota(&self) -> Quota {
+        let burst = NonZeroU32::new(self.limit).unwrap_or(NonZeroU32::MIN);
+        let period = Duration::from_secs(self.period_secs.max(1));
+        let replenish = period / burst.get();
+        Quota::with_period(replenish.max(Duration::from_nanos(1)))
+            .expect("replenish interval is non-zero")
+            .allow_burst(burst)
+    }
+}
+
+/// Rate limiting policies loaded from configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    pub http: PolicySpec,
+    pub tool: PolicySpec,
+    pub embeddings: PolicySpec,
+    pub database: PolicySpec,
+    /// Per-tool overrides of the `tool` policy
+    pub tool_overrides: HashMap<String, PolicySpec>,
+    /// How often idle keys are evicted from every policy
+    pub housekeeping_secs: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
         Self {
-            openai_limiter: Arc::new(GovernorRateLimiter::direct(
-                Quota::per_minute(nonzero!(60u32))
-            )),
-            db_limiter: Arc::new(GovernorRateLimiter::direct(
-                Quota::per_minute(nonzero!(1000u32))
-            )),
-            user_limiters: Arc::new(Mutex::new(HashMap::new())),
+            http: PolicySpec::new(300, 60),
+            tool: PolicySpec::new(100, 3600),
+            embeddings: PolicySpec::new(60, 60),
+            database: PolicySpec::new(1000, 60),
+            tool_overrides: HashMap::new(),
+            housekeeping_secs: 60,
         }
     }
-    
-    /// Check if OpenAI API call is allowed
-    pub fn check_openai(&self) -> Result<()> {
-        self.openai_limiter.check()
-            .map_err(|_| anyhow!("OpenAI rate limit exceeded (60 requests/min). Please try again later."))?;
-        Ok(())
-    }
-    
-    /// Check if database query is allowed
-    pub fn check_database(&self) -> Result<()> {
-        self.db_limiter.check()
-            .map_err(|_| anyhow!("Database rate limit exceeded (1000 requests/min)."))?;
-        Ok(())
-    }
-    
-    /// Check if user has exceeded their rate limit
-    pub fn check_user(&self, user_id: &str) -> Result<()> {
-        let mut limiters = self.user_limiters.lock().unwrap();
-        
-        let limiter = limiters.entry(user_id.to_string())
-            .or_insert_with(|| {
-                GovernorRateLimiter::direct(Quota::per_hour(nonzero!(100u32)))
-            });
-        
-        limiter.check()
-            .map_err(|_| anyhow!("User rate limit exceeded (100 requests/hour). Please try again later."))?;
-        
-        Ok(())
-    }
-    
-    /// Get current OpenAI request count (for monitoring)
-    pub fn openai_remaining(&self) -> u32 {
-        // Note: governor doesn't expose remaining count directly
-        // This is a placeholder for future metrics
-        60
-    }
-    
-    /// Get current user request count
-    pub fn user_remaining(&self, user_id: &str) -> u32 {
-        // Placeholder for future metrics
-        100
+}
+
+/// Outcome of an allowed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the quota is fully replenished
+    pub reset: Duration,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Rate limit exceeded for '{policy}' ({limit} requests per {period_secs}s). Retry after {}s.", retry_after.as_secs().max(1))]
+pub struct RateLimitExceeded {
+    pub policy: String,
+    pub limit: u32,
+    pub period_secs: u64,
+    pub retry_after: Duration,
+}
+
+impl RateLimitExceeded {
+    /// `Retry-After` and `RateLimit-*` headers for a 429 response
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let retry_after = self.retry_after.as_secs().max(1).to_string();
+        vec![
+            ("Retry-After", retry_after.clone()),
+            ("RateLimit-Limit", self.limit.to_string()),
+            ("RateLimit-Remaining", "0".to_string()),
+            ("RateLimit-Reset", retry_after),
+            ("RateLimit-Policy", format!("{};w={}", self.limit, self.period_secs)),
+        ]
     }
 }
 
-impl Clone for RateLimiter {
-    fn clone(&self) -> Self {
+struct Policy {
+    name: String,
+    spec: PolicySpec,
+    limiter: KeyedLimiter,
+}
+
+impl Policy {
+    fn new(name: &str, spec: PolicySpec) -> Self {
         Self {
-            openai_limiter: Arc::clone(&self.openai_limiter),
-            db_limiter: Arc::clone(&self.db_limiter),
-            user_limiters: Arc::clone(&self.user_limiters),
+            name: name.to_string(),
+            spec,
+            limiter: GovernorRateLimiter::keyed(spec.quota())
+                .with_middleware::<StateInformationMiddleware>(),
         }
     }
+
+    fn check(&self, key: &str) -> Result<RateLimitStatus, RateLimitExceeded> {
+        match self.limiter.check_key(&key.to_string()) {
+            Ok(snapshot) => {
+                let remaining = snapshot.remaining_burst_capacity();
+                let used = self.spec.limit.saturating_sub(remaining);
+                Ok(RateLimitStatus {
+                    limit: self.spec.limit,
+                    remaining,
+                    reset: snapshot.quota().replenish_interval() * used,
+                })
+            }
+            Err(not_until) => Err(RateLimitExceeded {
+                policy: self.name.clone(),
+                limit: self.spec.limit,
+                period_secs: self.spec.period_secs,
+                retry_after: not_until.wait_time_from(DefaultClock::default().now()),
+            }),
+        }
+    }
+
+    fn evict_idle(&self) {
+        self.limiter.retain_recent();
+        self.limiter.shrink_to_fit();
+    }
+}
+
+/// Named, keyed rate limiting policies shared by the HTTP transport, tool dispatch and vector store
+pub struct RateLimiter {
+    policies: HashMap<String, Policy>,
+    housekeeping: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let mut policies = HashMap::new();
+        for (name, spec) in [
+            (POLICY_HTTP, config.http),
+            (POLICY_TOOL, config.tool),
+            (POLICY_EMBEDDINGS, config.embeddings),
+            (POLICY_DATABASE, config.database),
+        ] {
+            policies.insert(name.to_string(), Policy::new(name, spec));
+        }
+        for (tool, spec) in &config.tool_overrides {
+            let name = Self::tool_policy_name(tool);
+            policies.insert(name.clone(), Policy::new(&name, *spec));
+        }
+
+        Self {
+            policies,
+            housekeeping: Duration::from_secs(config.housekeeping_secs.max(1)),
+        }
+    }
+
+    fn tool_policy_name(tool: &str) -> String {
+        format!("{}:{}", POLICY_TOOL, tool)
+    }
+
+    /// Consume one request from `policy` for `key`
+    pub fn check(&self, policy: &str, key: &str) -> Result<RateLimitStatus, RateLimitExceeded> {
+        match self.policies.get(policy) {
+            Some(p) => p.check(key),
+            None => {
+                log::warn!("⚠️ Unknown rate limit policy '{}', allowing request", policy);
+                Ok(RateLimitStatus { limit: 0, remaining: 0, reset: Duration::ZERO })
+            }
+        }
+    }
+
+    /// Check the tool policy, honoring per-tool overrides
+    pub fn check_tool(&self, client: &str, tool: &str) -> Result<RateLimitStatus, RateLimitExceeded> {
+        let key = format!("{}|{}", client, tool);
+        let override_name = Self::tool_policy_name(tool);
+        if self.policies.contains_key(&override_name) {
+            self.check(&override_name, &key)
+        } else {
+            self.check(POLICY_TOOL, &key)
+        }
+    }
+
+    /// Number of keys currently tracked by a policy
+    pub fn tracked_keys(&self, policy: &str) -> usize {
+        self.policies.get(policy).map(|p| p.limiter.len()).unwrap_or(0)
+    }
+
+    /// Drop keys whose state is indistinguishable from a fresh bucket
+    pub fn evict_idle(&self) {
+        for policy in self.policies.values() {
+            policy.evict_idle();
+        }
+    }
+
+    /// Periodically evict idle keys for the lifetime of the process
+    pub fn spawn_housekeeping(self: &Arc<Self>) {
+        let limiter = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(limiter.housekeeping);
+            loop {
+                interval.tick().await;
+                limiter.evict_idle();
+                log::debug!(
+                    "🧹 Rate limiter housekeeping: {} http keys, {} tool keys tracked",
+                    limiter.tracked_keys(POLICY_HTTP),
+                    limiter.tracked_keys(POLICY_TOOL)
+                );
+            }
+        });
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(&RateLimitConfig::default())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_rate_limiter_creation() {
-        let limiter = RateLimiter::new();
-        assert!(limiter.check_openai().is_ok());
-        assert!(limiter.check_database().is_ok());
-        assert!(limiter.check_user("test_user").is_ok());
+        let limiter = RateLimiter::default();
+        assert!(limiter.check(POLICY_EMBEDDINGS, GLOBAL_KEY).is_ok());
+        assert!(limiter.check(POLICY_DATABASE, GLOBAL_KEY).is_ok());
+        assert!(limiter.check(POLICY_HTTP, "127.0.0.1").is_ok());
+        assert!(limiter.check_tool("127.0.0.1", "search-governance").is_ok());
     }
-    
+
     #[test]
-    fn test_openai_rate_limit() {
-        let limiter = RateLimiter::new();
-        
+    fn test_embeddings_rate_limit() {
+        let limiter = RateLimiter::default();
+
         // Should allow first 60 requests
         for _ in 0..60 {
-            assert!(limiter.check_openai().is_ok());
+            assert!(limiter.check(POLICY_EMBEDDINGS, GLOBAL_KEY).is_ok());
         }
-        
+
         // 61st request should fail
-        assert!(limiter.check_openai().is_err());
+        let err = limiter.check(POLICY_EMBEDDINGS, GLOBAL_KEY).unwrap_err();
+        assert_eq!(err.policy, POLICY_EMBEDDINGS);
+        assert!(err.retry_after > Duration::ZERO);
     }
-    
+
     #[test]
-    fn test_user_rate_limit() {
-        let limiter = RateLimiter::new();
-        
+    fn test_keyed_rate_limit() {
+        let config = RateLimitConfig {
+            http: PolicySpec::new(100, 3600),
+            ..RateLimitConfig::default()
+        };
+        let limiter = RateLimiter::new(&config);
+
         // Should allow first 100 requests
         for _ in 0..100 {
-            assert!(limiter.check_user("user1").is_ok());
+            assert!(limiter.check(POLICY_HTTP, "user1").is_ok());
         }
-        
+
         // 101st request should fail
-        assert!(limiter.check_user("user1").is_err());
-        
-        // Different user should still work
-        assert!(limiter.check_user("user2").is_ok());
+        assert!(limiter.check(POLICY_HTTP, "user1").is_err());
+
+        // Different key should still work
+        assert!(limiter.check(POLICY_HTTP, "user2").is_ok());
+    }
+
+    #[test]
+    fn test_remaining_is_reported() {
+        let limiter = RateLimiter::default();
+        let first = limiter.check(POLICY_EMBEDDINGS, GLOBAL_KEY).unwrap();
+        let second = limiter.check(POLICY_EMBEDDINGS, GLOBAL_KEY).unwrap();
+        assert_eq!(first.limit, 60);
+        assert_eq!(first.remaining, 59);
+        assert_eq!(second.remaining, 58);
+        assert!(second.reset > first.reset);
+    }
+
+    #[test]
+    fn test_tool_overrides() {
+        let mut config = RateLimitConfig::default();
+        config.tool_overrides.insert("index-documents".to_string(), PolicySpec::new(1, 3600));
+        let limiter = RateLimiter::new(&config);
+
+        assert!(limiter.check_tool("a", "index-documents").is_ok());
+        assert!(limiter.check_tool("a", "index-documents").is_err());
+        assert!(limiter.check_tool("a", "search-governance").is_ok());
+    }
+
+    #[test]
+    fn test_idle_keys_are_evicted() {
+        let config = RateLimitConfig {
+            http: PolicySpec::new(1000, 1),
+            ..RateLimitConfig::default()
+        };
+        let limiter = RateLimiter::new(&config);
+        assert!(limiter.check(POLICY_HTTP, "10.0.0.1").is_ok());
+        assert_eq!(limiter.tracked_keys(POLICY_HTTP), 1);
+
+        std::thread::sleep(Duration::from_millis(20));
+        limiter.evict_idle();
+        assert_eq!(limiter.tracked_keys(POLICY_HTTP), 0);
+    }
+
+    #[test]
+    fn test_policy_spec_parse() {
+        assert_eq!(PolicySpec::parse("300/60"), Some(PolicySpec::new(300, 60)));
+        assert_eq!(PolicySpec::parse("100/1h"), Some(PolicySpec::new(100, 3600)));
+        assert_eq!(PolicySpec::parse("5 / 2m"), Some(PolicySpec::new(5, 120)));
+        assert_eq!(PolicySpec::parse("0/60"), None);
+        assert_eq!(PolicySpec::parse("abc"), None);
     }
 }
@@ -0,0 +1,157 @@
+use crate::core::database::{Database, vector::SearchFilter};
+use anyhow::Result;
+use serde::Deserialize;
+
+/// Markers wrapped around matched terms (markdown bold)
+pub const HIGHLIGHT_OPEN: &str = "**";
+pub const HIGHLIGHT_CLOSE: &str = "**";
+
+const SNIPPET_CHARS: usize = 240;
+
+/// A document matched by the BM25 indexes on `mcp_documentation` or its published editions
+#[derive(Debug, Clone, Deserialize)]
+pub struct FullTextHit {
+    pub doc_id: String,
+    pub name: String,
+    pub title: String,
+    pub sdlc_phase: String,
+    pub project_name: Option<String>,
+    /// Full content with English-analyzer matches highlighted
+    pub highlight: Option<String>,
+    /// Full content with n-gram (Thai) matches highlighted
+    pub highlight_ngram: Option<String>,
+    pub content: Option<String>,
+}
+
+impl FullTextHit {
+    /// Short excerpt around the first highlighted match, or the start of the document
+    pub fn snippet(&self) -> String {
+        [&self.highlight, &self.highlight_ngram]
+            .into_iter()
+            .flatten()
+            .find(|h| h.contains(HIGHLIGHT_OPEN))
+            .map(|h| snippet(h))
+            .unwrap_or_else(|| snippet(self.content.as_deref().unwrap_or_default()))
+    }
+}
+
+fn is_thai(c: char) -> bool {
+    ('\u{0E00}'..='\u{0E7F}').contains(&c)
+}
+
+/// Thai has no spaces between words, so it is indexed as 2–3 character n-grams. SurrealDB only
+/// matches query terms up to the gram size, so Thai words are cut into 3-character pieces
+/// (all of which must match). `None` when the query has no Thai.
+pub fn ngram_query(query: &str) -> Option<String> {
+    let mut pieces = Vec::new();
+    for word in query.to_lowercase().split(|c: char| !(c.is_alphanumeric() || is_thai(c))) {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() < 2 || !chars.iter().any(|c| is_thai(*c)) {
+            continue;
+        }
+        let mut start = 0;
+        while start + 3 < chars.len() {
+            pieces.push(chars[start..start + 3].iter().collect::<String>());
+            start += 3;
+        }
+        // The tail overlaps the previous piece rather than falling below the minimum gram size
+        pieces.push(chars[chars.len().saturating_sub(3)..].iter().collect());
+    }
+    (!pieces.is_empty()).then(|| pieces.join(" "))
+}
+
+/// Collapse whitespace and cut a window around the first highlight
+pub fn snippet(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let chars: Vec<char> = text.chars().collect();
+    let first = text.find(HIGHLIGHT_OPEN).map(|byte| text[..byte].chars().count()).unwrap_or(0);
+
+    let start = first.saturating_sub(SNIPPET_CHARS / 3);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+    let mut out: String = chars[start..end].iter().collect();
+    // Don't leave a dangling marker when the window cuts a highlight in half
+    if out.matches(HIGHLIGHT_OPEN).count() % 2 == 1 {
+        out.push_str(HIGHLIGHT_CLOSE);
+    }
+    if start > 0 {
+        out.insert(0, '…');
+    }
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// BM25 search over title and content (English analyzer) plus the Thai n-gram index, best first.
+/// Searches published editions unless the filter asks for drafts.
+pub async fn search_documents(db: &Database, query: &str, filter: &SearchFilter, limit: usize) -> Result<Vec<FullTextHit>> {
+    let ngram = ngram_query(query);
+
+    let mut matches = vec!["title @0@ $query", "content @1@ $query"];
+    if ngram.is_some() {
+        matches.push("content_ngram @2@ $ngram");
+    }
+    let mut conditions = vec![format!("({})", matches.join(" OR "))];
+    if filter.project.is_some() { conditions.push("project_id.name = $project".to_string()); }
+    if filter.phase.is_some() { conditions.push("sdlc_phase = $phase".to_string()); }
+    conditions.push(if filter.drafts { "status != 'deprecated'" } else { "status = 'published'" }.to_string());
+    let (table, doc_id) = if filter.drafts {
+        ("mcp_documentation", "id")
+    } else {
+        ("mcp_documentation_published", "document")
+    };
+
+    let sql = format!("
+        SELECT type::string({}) AS doc_id, name, title, sdlc_phase, content, project_id.name AS project_name,
+            search::score(0) + search::score(1) + {} AS score,
+            search::highlight($open, $close, 1) AS highlight,
+            {} AS highlight_ngram
+        FROM {}
+        WHERE {}
+        ORDER BY score DESC
+        LIMIT $limit
+    ",
+        doc_id,
+        if ngram.is_some() { "search::score(2)" } else { "0" },
+        if ngram.is_some() { "search::highlight($open, $close, 2)" } else { "NONE" },
+        table,
+        conditions.join(" AND "),
+    );
+
+    let mut q = db.query(sql)
+        .bind(("query", query.to_string()))
+        .bind(("open", HIGHLIGHT_OPEN))
+        .bind(("close", HIGHLIGHT_CLOSE))
+        .bind(("limit", limit as i64));
+    if let Some(ngram) = ngram { q = q.bind(("ngram", ngram)); }
+    if let Some(project) = &filter.project { q = q.bind(("project", project.clone())); }
+    if let Some(phase) = &filter.phase { q = q.bind(("phase", phase.clone())); }
+
+    let mut result = q.await?;
+    let hits: Vec<FullTextHit> = result.take(0)?;
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ngram_query() {
+        assert_eq!(ngram_query("JWT rotation"), None);
+        assert_eq!(ngram_query("ยืนยัน").as_deref(), Some("ยืน ยัน"));
+        assert_eq!(ngram_query("การยืนยัน JWT").as_deref(), Some("การ ยืน ยัน"));
+        // Tail shorter than a gram overlaps the previous piece
+        assert_eq!(ngram_query("ตัวตนใ").as_deref(), Some("ตัว ตนใ"));
+        assert_eq!(ngram_query("ระบบ").as_deref(), Some("ระบ ะบบ"));
+    }
+
+    #[test]
+    fn test_snippet_windows_first_highlight() {
+        let text = format!("{} the **JWT** token {}", "a ".repeat(200), "b ".repeat(200));
+        let s = snippet(&text);
+        assert!(s.starts_with('…') && s.ends_with('…'));
+        assert!(s.contains("the **JWT** token"));
+        assert_eq!(snippet("short\n\ntext"), "short text");
+    }
+}
@@ -0,0 +1,127 @@
+pub mod fulltext;
+pub mod rerank;
+
+use std::collections::HashMap;
+
+/// Usual RRF constant; damps the weight of top ranks so no single list dominates
+pub const RRF_K: f64 = 60.0;
+
+/// MMR trade-off: 1.0 ranks by relevance only, 0.0 by novelty only
+pub const MMR_LAMBDA: f64 = 0.7;
+
+/// Reciprocal rank fusion: each list contributes `1 / (k + rank)` per ID (rank starting at 1).
+/// Returns IDs by descending fused score; ties keep first-seen order.
+pub fn reciprocal_rank_fusion(lists: &[Vec<String>], k: f64) -> Vec<(String, f64)> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    let mut order: Vec<&str> = Vec::new();
+
+    for list in lists {
+        for (rank, id) in list.iter().enumerate() {
+            let score = scores.entry(id.as_str()).or_insert_with(|| {
+                order.push(id.as_str());
+                0.0
+            });
+            *score += 1.0 / (k + rank as f64 + 1.0);
+        }
+    }
+
+    let mut fused: Vec<(String, f64)> = order.into_iter().map(|id| (id.to_string(), scores[id])).collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+    fused
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f64 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom > 0.0 { (dot / denom) as f64 } else { 0.0 }
+}
+
+/// Maximal marginal relevance: repeatedly pick the candidate maximising
+/// `lambda * relevance - (1 - lambda) * max similarity to those already picked`.
+/// Relevance outside [0, 1] (e.g. re-ranker logits) is min-max normalised first, so it stays
+/// on the same scale as cosine similarity. Returns up to `k` candidate indexes in pick order.
+pub fn maximal_marginal_relevance(vectors: &[&[f32]], relevance: &[f64], lambda: f64, k: usize) -> Vec<usize> {
+    let (min, max) = relevance.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), r| (lo.min(*r), hi.max(*r)));
+    let normalised: Vec<f64> = relevance.iter()
+        .map(|r| match (min >= 0.0 && max <= 1.0, max > min) {
+            (true, _) => *r,
+            (false, true) => (r - min) / (max - min),
+            (false, false) => 1.0,
+        })
+        .collect();
+
+    let mut picked: Vec<usize> = Vec::new();
+    let mut remaining: Vec<usize> = (0..vectors.len().min(relevance.len())).collect();
+    while picked.len() < k && !remaining.is_empty() {
+        let mmr = |i: usize| {
+            let redundancy = picked.iter().map(|j| cosine(vectors[i], vectors[*j])).fold(0.0, f64::max);
+            lambda * normalised[i] - (1.0 - lambda) * redundancy
+        };
+        // `remaining` stays in input order, so ties go to the more relevant candidate
+        let (pos, _) = remaining.iter().enumerate()
+            .fold((0, f64::NEG_INFINITY), |best, (pos, i)| {
+                let score = mmr(*i);
+                if score > best.1 { (pos, score) } else { best }
+            });
+        picked.push(remaining.remove(pos));
+    }
+    picked
+}
+
+/// Group ranked items by key, groups ordered by their best (first) item
+pub fn group_by_key<'a>(keys: impl IntoIterator<Item = &'a str>) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, key) in keys.into_iter().enumerate() {
+        let group = *index.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(i);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let bm25 = ids(&["a", "b", "c"]);
+        let vector = ids(&["c", "a", "d"]);
+        let fused = reciprocal_rank_fusion(&[bm25, vector], RRF_K);
+
+        let order: Vec<&str> = fused.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["a", "c", "b", "d"]);
+        assert!((fused[0].1 - (1.0 / 61.0 + 1.0 / 62.0)).abs() < 1e-12);
+        assert!((fused[3].1 - 1.0 / 63.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_maximal_marginal_relevance_prefers_novelty() {
+        // Two near-duplicate chunks of one document and a different, slightly less relevant one
+        let a: &[f32] = &[1.0, 0.0];
+        let a2: &[f32] = &[0.99, 0.01];
+        let b: &[f32] = &[0.0, 1.0];
+        let relevance = [0.9, 0.89, 0.8];
+
+        assert_eq!(maximal_marginal_relevance(&[a, a2, b], &relevance, 1.0, 3), vec![0, 1, 2]);
+        assert_eq!(maximal_marginal_relevance(&[a, a2, b], &relevance, MMR_LAMBDA, 3), vec![0, 2, 1]);
+        assert_eq!(maximal_marginal_relevance(&[a, a2, b], &relevance, MMR_LAMBDA, 1), vec![0]);
+        // Re-ranker logits are rescaled rather than compared with cosine redundancy directly
+        assert_eq!(maximal_marginal_relevance(&[a, a2, b], &[9.0, 8.9, 1.0], MMR_LAMBDA, 3), vec![0, 1, 2]);
+        assert!(maximal_marginal_relevance(&[], &[], MMR_LAMBDA, 3).is_empty());
+    }
+
+    #[test]
+    fn test_group_by_key() {
+        let groups = group_by_key(["doc:a", "doc:b", "doc:a", "doc:c", "doc:b"]);
+        assert_eq!(groups, vec![vec![0, 2], vec![1, 4], vec![3]]);
+    }
+}
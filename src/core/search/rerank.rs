@@ -0,0 +1,125 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Scores (query, document) pairs jointly, e.g. with a cross-encoder
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    fn name(&self) -> &str;
+    /// Relevance of each document to `query`, in input order; higher is better
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f64>>;
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RerankConfig {
+    /// Full endpoint URL, e.g. `http://localhost:7997/rerank`; re-ranking is off when unset
+    pub url: Option<String>,
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+}
+
+pub fn build_reranker(config: &RerankConfig) -> Result<Option<Arc<dyn Reranker>>> {
+    match &config.url {
+        Some(url) => Ok(Some(Arc::new(HttpReranker::new(url, config.model.clone(), config.api_key.clone())?))),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RerankRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    query: &'a str,
+    documents: &'a [String],
+    return_documents: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+/// Scores back in input order
+fn scores_in_order(response: RerankResponse, len: usize) -> Result<Vec<f64>> {
+    let mut scores = vec![None; len];
+    for result in response.results {
+        let slot = scores.get_mut(result.index)
+            .ok_or_else(|| anyhow!("Re-ranker returned index {} for {} documents", result.index, len))?;
+        *slot = Some(result.relevance_score);
+    }
+    scores.into_iter()
+        .collect::<Option<Vec<f64>>>()
+        .ok_or_else(|| anyhow!("Re-ranker did not score every document"))
+}
+
+/// A rerank server speaking the Cohere `/rerank` API (Infinity, vLLM, llama.cpp, LocalAI, Jina, ...)
+pub struct HttpReranker {
+    http_client: reqwest::Client,
+    url: String,
+    model: Option<String>,
+    api_key: Option<String>,
+}
+
+impl HttpReranker {
+    pub fn new(url: &str, model: Option<String>, api_key: Option<String>) -> Result<Self> {
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .connect_timeout(std::time::Duration::from_secs(5))
+            .build()?;
+        Ok(Self { http_client, url: url.to_string(), model, api_key })
+    }
+}
+
+#[async_trait]
+impl Reranker for HttpReranker {
+    fn name(&self) -> &str {
+        self.model.as_deref().unwrap_or("http")
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f64>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut request = self.http_client
+            .post(&self.url)
+            .json(&RerankRequest { model: self.model.as_deref(), query, documents, return_documents: false });
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await
+            .map_err(|e| anyhow!("Re-ranker request error: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unable to read error".to_string());
+            return Err(anyhow!("Re-ranker error ({}): {}", status, error_text));
+        }
+        scores_in_order(response.json().await?, documents.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_in_order() {
+        // Servers return results sorted by relevance, not input order
+        let response: RerankResponse = serde_json::from_str(
+            r#"{"results": [{"index": 1, "relevance_score": 0.9}, {"index": 0, "relevance_score": -0.2}]}"#
+        ).unwrap();
+        assert_eq!(scores_in_order(response, 2).unwrap(), vec![-0.2, 0.9]);
+
+        let partial: RerankResponse = serde_json::from_str(r#"{"results": [{"index": 0, "relevance_score": 1.0}]}"#).unwrap();
+        assert!(scores_in_order(partial, 2).is_err());
+        let out_of_range: RerankResponse = serde_json::from_str(r#"{"results": [{"index": 5, "relevance_score": 1.0}]}"#).unwrap();
+        assert!(scores_in_order(out_of_range, 2).is_err());
+    }
+}
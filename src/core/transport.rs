@@ -1,19 +1,106 @@
 use std::sync::Arc;
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
 use ntex::web;
 use ntex::util::Bytes;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 use serde::Deserialize;
-use crate::core::config::Config;
+use crate::core::config::{Config, ANONYMOUS_PRINCIPAL};
 use crate::core::database::{Database, vector::VectorStore};
+use crate::core::audit::{AuditLog, AuditContext};
 use crate::core::mcp::handler::McpHandler;
-use crate::core::mcp::JsonRpcRequest;
-use crate::core::security::RateLimiter;
+use crate::core::mcp::{ClientInfo, JsonRpcRequest};
+use crate::core::rate_limiter::{RateLimiter, RateLimitExceeded, POLICY_HTTP};
 
 lazy_static::lazy_static! {
     static ref SESSIONS: Mutex<HashMap<String, mpsc::UnboundedSender<Bytes>>> = Mutex::new(HashMap::new());
+    static ref SESSION_CLIENTS: Mutex<SessionClients> = Mutex::new(SessionClients::new(MAX_SESSION_CLIENTS, SESSION_CLIENT_TTL));
+}
+
+/// Sessions unused for this long lose their `clientInfo`
+const SESSION_CLIENT_TTL: Duration = Duration::from_secs(3600);
+const MAX_SESSION_CLIENTS: usize = 10_000;
+
+/// `clientInfo` from each session's `initialize`, used to attribute later requests. Session IDs come
+/// from the caller, so entries expire when idle and the oldest are evicted beyond `capacity`.
+struct SessionClients {
+    clients: HashMap<String, (ClientInfo, Instant)>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl SessionClients {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self { clients: HashMap::new(), capacity, ttl }
+    }
+
+    fn insert(&mut self, session: String, info: ClientInfo, now: Instant) {
+        let ttl = self.ttl;
+        self.clients.retain(|_, (_, seen)| now.saturating_duration_since(*seen) < ttl);
+        if self.clients.len() >= self.capacity && !self.clients.contains_key(&session) {
+            let oldest = self.clients.iter().min_by_key(|(_, (_, seen))| *seen).map(|(s, _)| s.clone());
+            if let Some(oldest) = oldest {
+                self.clients.remove(&oldest);
+            }
+        }
+        self.clients.insert(session, (info, now));
+    }
+
+    fn get(&mut self, session: &str, now: Instant) -> Option<ClientInfo> {
+        let (info, seen) = self.clients.get_mut(session)?;
+        if now.saturating_duration_since(*seen) >= self.ttl {
+            self.clients.remove(session);
+            return None;
+        }
+        *seen = now;
+        Some(info.clone())
+    }
+
+    fn remove(&mut self, session: &str) {
+        self.clients.remove(session);
+    }
+}
+
+/// Caller-supplied correlation ID (`X-Correlation-Id` / `X-Request-Id`), else a fresh UUID
+fn correlation_id(req: &web::HttpRequest) -> String {
+    ["X-Correlation-Id", "X-Request-Id"].iter()
+        .filter_map(|name| req.headers().get(*name))
+        .filter_map(|h| h.to_str().ok())
+        .map(|s| s.trim())
+        .find(|s| !s.is_empty() && s.len() <= 128)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Session from `?session_id=` (SSE transport) or the `Mcp-Session-Id` header
+fn session_id(req: &web::HttpRequest, query: &McpQuery) -> Option<String> {
+    query.session_id.clone().or_else(|| {
+        req.headers().get("Mcp-Session-Id")
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string())
+    })
+}
+
+/// Client identity for this request, remembering it per session when it arrives with `initialize`
+fn client_info(body: &JsonRpcRequest, session_id: Option<&str>) -> Option<ClientInfo> {
+    if body.method == "initialize" {
+        let info = body.params.as_ref()
+            .and_then(|p| p.get("clientInfo"))
+            .and_then(|v| serde_json::from_value::<ClientInfo>(v.clone()).ok());
+        if let (Some(info), Some(session)) = (&info, session_id) {
+            SESSION_CLIENTS.lock().unwrap().insert(session.to_string(), info.clone(), Instant::now());
+        }
+        return info;
+    }
+    session_id.and_then(|s| SESSION_CLIENTS.lock().unwrap().get(s, Instant::now()))
+}
+
+fn forget_session(session_id: &str) -> bool {
+    SESSION_CLIENTS.lock().unwrap().remove(session_id);
+    SESSIONS.lock().unwrap().remove(session_id).is_some()
 }
 
 /// Standardized response wrapper for non-MCP internal endpoints
@@ -25,6 +112,44 @@ fn internal_wrap_response(data: serde_json::Value) -> serde_json::Value {
     })
 }
 
+/// Client address used for rate limiting and the audit log
+fn client_ip(req: &web::HttpRequest, trusted_proxies: &[IpAddr]) -> String {
+    let forwarded = req.headers().get("X-Forwarded-For").and_then(|h| h.to_str().ok());
+    resolve_client_ip(req.peer_addr().map(|addr| addr.ip()), forwarded, trusted_proxies)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// `X-Forwarded-For` is only believed when the socket peer is a trusted proxy. The client is then
+/// the nearest hop that is not a trusted proxy itself; anything further left can be forged.
+fn resolve_client_ip(peer: Option<IpAddr>, forwarded: Option<&str>, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let mut client = peer?;
+    if !trusted_proxies.contains(&client) {
+        return Some(client);
+    }
+    for hop in forwarded.unwrap_or_default().rsplit(',') {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else { break };
+        client = ip;
+        if !trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// 429 response carrying `Retry-After` and `RateLimit-*` headers
+fn rate_limited_response(err: &RateLimitExceeded, id: serde_json::Value) -> web::HttpResponse {
+    let mut response = web::HttpResponse::TooManyRequests();
+    for (name, value) in err.headers() {
+        response.header(name, value);
+    }
+    response.json(&serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": { "code": -32000, "message": err.to_string() },
+        "id": id
+    }))
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("SSE error")]
 pub struct SseError;
@@ -54,12 +179,12 @@ impl futures::Stream for SseStream {
 
 impl Drop for SseStream {
     fn drop(&mut self) {
-        let mut sessions = SESSIONS.lock().unwrap();
-        if sessions.remove(&self.session_id).is_some() {
+        if forget_session(&self.session_id) {
             log::info!("🧹 SSE Session cleaned up: {}", self.session_id);
         }
     }
 }
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_mcp_request(
     req: web::HttpRequest,
     query: web::types::Query<McpQuery>,
@@ -68,47 +193,69 @@ pub async fn handle_mcp_request(
     vector: web::types::State<Arc<VectorStore>>,
     config: web::types::State<Arc<Config>>,
     rate_limiter: web::types::State<Arc<RateLimiter>>,
+    audit: web::types::State<Arc<AuditLog>>,
 ) -> web::HttpResponse {
     // 1. Rate Limiting Check
-    let ip = req.connection_info().host().to_string();
-    if !rate_limiter.check(ip.clone()) {
+    let ip = client_ip(&req, &config.trusted_proxies);
+    if let Err(e) = rate_limiter.check(POLICY_HTTP, &ip) {
         log::warn!("🚫 Rate limit exceeded for IP: {}", ip);
-        return web::HttpResponse::TooManyRequests()
-            .json(&serde_json::json!({
-                "jsonrpc": "2.0",
-                "error": { "code": -32000, "message": "Rate limit exceeded" },
-                "id": null
-            }));
+        return rate_limited_response(&e, serde_json::json!(null));
     }
 
-    // 2. Authentication Check
-    if config.requires_auth() {
-        let auth_header = req.headers().get("Authorization");
-        let is_valid = match auth_header {
-            Some(header) => header.to_str().ok()
-                .filter(|s| s.starts_with("Bearer "))
-                .map(|s| &s[7..])
-                .map(|token| config.validate_api_key(token))
-                .unwrap_or(false),
-            None => false,
-        };
+    // 2. Authentication Check (resolves the calling principal)
+    let principal = if config.requires_auth() {
+        let principal = req.headers().get("Authorization")
+            .and_then(|header| header.to_str().ok())
+            .and_then(|s| s.strip_prefix("Bearer "))
+            .and_then(|token| config.authenticate(token));
         
-        if !is_valid {
-            return web::HttpResponse::Unauthorized()
-                .json(&serde_json::json!({
-                    "jsonrpc": "2.0",
-                    "error": { "code": -32000, "message": "Unauthorized" },
-                    "id": null
-                }));
+        match principal {
+            Some(p) => p,
+            None => {
+                return web::HttpResponse::Unauthorized()
+                    .json(&serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "error": { "code": -32000, "message": "Unauthorized" },
+                        "id": null
+                    }));
+            }
         }
-    }
+    } else {
+        ANONYMOUS_PRINCIPAL.to_string()
+    };
     
-    let handler = McpHandler::new((**db).clone(), (*vector).clone());
     let method = body.method.clone();
-    
     let req_id = body.id.clone().unwrap_or(serde_json::json!(null));
+
+    // 3. Per-tool Rate Limiting
+    if method == "tools/call" {
+        if let Some(tool) = body.params.as_ref().and_then(|p| p.get("name")).and_then(|v| v.as_str()) {
+            if let Err(e) = rate_limiter.check_tool(&ip, tool) {
+                log::warn!("🚫 Tool rate limit exceeded for {} on {}", ip, tool);
+                return rate_limited_response(&e, req_id);
+            }
+        }
+    }
+
+    // 4. Attribution for the audit log
+    let correlation_id = correlation_id(&req);
+    let session_id = session_id(&req, &query);
+    let client = client_info(&body, session_id.as_deref());
+    let context = AuditContext {
+        principal: Some(principal),
+        client_name: client.as_ref().map(|c| c.name.clone()),
+        client_version: client.and_then(|c| c.version),
+        session_id,
+        client_ip: Some(ip),
+        correlation_id: Some(correlation_id.clone()),
+    };
+
+    let handler = McpHandler::new((**db).clone(), (*vector).clone(), (*rate_limiter).clone(), (*audit).clone())
+        .with_context(context)
+        .with_chunking(config.chunking.clone())
+        .with_quotas(config.quotas.clone());
     
-    match handler.handle_request(body.into_inner()).await {
+    let mut response = match handler.handle_request(body.into_inner()).await {
         Ok(Some(response)) => {
             // 1. Push to SSE as secondary stream if session is active (Standard MCP Spec)
             if let Some(session_id) = &query.session_id {
@@ -143,7 +290,12 @@ pub async fn handle_mcp_request(
                 "error": { "code": -32603, "message": e.to_string() }
             }))
         }
+    };
+
+    if let Ok(value) = ntex::http::header::HeaderValue::from_str(&correlation_id) {
+        response.headers_mut().insert(ntex::http::header::HeaderName::from_static("x-correlation-id"), value);
     }
+    response
 }
 
 /// MCP SSE handler for GET requests
@@ -205,8 +357,7 @@ pub async fn handle_mcp_delete(
 ) -> web::HttpResponse {
     let mut removed = false;
     if let Some(session_id) = &query.session_id {
-        let mut sessions = SESSIONS.lock().unwrap();
-        if sessions.remove(session_id).is_some() {
+        if forget_session(session_id) {
             log::info!("🗑️ Removed SSE session: {}", session_id);
             removed = true;
         }
@@ -222,10 +373,11 @@ pub async fn run_http_server(
     port: u16, 
     db: Arc<Database>, 
     vector: Arc<VectorStore>,
+    rate_limiter: Arc<RateLimiter>,
+    audit: Arc<AuditLog>,
     config: Arc<Config>
 ) -> std::io::Result<()> {
-    let rate_limiter = Arc::new(RateLimiter::default());
-    
+
     log::info!("🌐 Starting HTTP/MCP Server on port {}...", port);
     
     web::server(move || {
@@ -236,7 +388,10 @@ pub async fn run_http_server(
                 ntex::http::header::AUTHORIZATION,
                 ntex::http::header::CONTENT_TYPE,
                 ntex::http::header::ACCEPT,
+                ntex::http::header::HeaderName::from_static("x-correlation-id"),
+                ntex::http::header::HeaderName::from_static("mcp-session-id"),
             ])
+            .expose_headers(vec![ntex::http::header::HeaderName::from_static("x-correlation-id")])
             .max_age(3600)
             .finish();
 
@@ -245,6 +400,7 @@ pub async fn run_http_server(
             .state(vector.clone())
             .state(config.clone())
             .state(rate_limiter.clone())
+            .state(audit.clone())
             .wrap(web::middleware::Logger::default())
             .wrap(cors)
             .service(web::resource("/health").to(|| async {
@@ -272,3 +428,43 @@ pub async fn run_http_server(
     .run()
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_for_needs_trusted_proxy() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let proxy = ip("10.0.0.2");
+        let trusted = [proxy];
+
+        // A spoofed header from an untrusted peer is ignored
+        assert_eq!(resolve_client_ip(Some(ip("203.0.113.9")), Some("198.51.100.1"), &trusted), Some(ip("203.0.113.9")));
+        assert_eq!(resolve_client_ip(Some(ip("203.0.113.9")), Some("198.51.100.1"), &[]), Some(ip("203.0.113.9")));
+        // Behind the proxy, the hop it appended wins over whatever the client sent
+        assert_eq!(resolve_client_ip(Some(proxy), Some("198.51.100.1, 203.0.113.9"), &trusted), Some(ip("203.0.113.9")));
+        assert_eq!(resolve_client_ip(Some(proxy), Some("garbage"), &trusted), Some(proxy));
+        assert_eq!(resolve_client_ip(Some(proxy), None, &trusted), Some(proxy));
+    }
+
+    #[test]
+    fn test_session_clients_expire_and_are_capped() {
+        let start = Instant::now();
+        let ttl = Duration::from_secs(60);
+        let client = |name: &str| ClientInfo { name: name.to_string(), version: None };
+        let mut clients = SessionClients::new(2, ttl);
+
+        clients.insert("a".to_string(), client("a"), start);
+        clients.insert("b".to_string(), client("b"), start + Duration::from_secs(1));
+        // Using a session keeps it alive, so "b" is now the oldest and makes room for "c"
+        assert!(clients.get("a", start + Duration::from_secs(2)).is_some());
+        clients.insert("c".to_string(), client("c"), start + Duration::from_secs(3));
+        assert_eq!(clients.clients.len(), 2);
+        assert!(clients.get("b", start + Duration::from_secs(3)).is_none());
+
+        // Idle sessions expire
+        assert!(clients.get("c", start + Duration::from_secs(3) + ttl).is_none());
+        assert_eq!(clients.get("a", start + Duration::from_secs(4)).map(|c| c.name), Some("a".to_string()));
+    }
+}
@@ -10,10 +10,11 @@ async fn main() -> std::io::Result<()> {
     // 1. Load Environment Variables
     dotenvy::dotenv().ok();
     
-    // Initialize logging
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .target(env_logger::Target::Stderr)
-        .init();
+    // Initialize logging (secrets are scrubbed before anything is written)
+    crate::core::redaction::init_logger(
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+            .target(env_logger::Target::Stderr)
+    );
     
     let config = Config::from_env();
     
@@ -24,20 +25,25 @@ async fn main() -> std::io::Result<()> {
         Ok(db) => Arc::new(db),
         Err(e) => {
             log::error!("❌ Failed to connect to SurrealDB: {}", e);
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()));
+            return Err(std::io::Error::other(e.to_string()));
         }
     };
     log::info!("✅ Connected to SurrealDB: {}/{}", config.surreal_ns, config.surreal_db);
     
+    // 2.5. Initialize Rate Limiting Policies
+    let rate_limiter = Arc::new(crate::core::rate_limiter::RateLimiter::new(&config.rate_limits));
+    rate_limiter.spawn_housekeeping();
+
     // 3. Initialize Vector Store (Phase 3)
-    let vector = match crate::core::database::vector::VectorStore::new(&config).await {
+    let vector = match crate::core::database::vector::VectorStore::new(&config, (*db).clone(), rate_limiter.clone()).await {
         Ok(v) => Arc::new(v),
         Err(e) => {
             log::error!("❌ Failed to initialize Vector Store: {}", e);
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()));
+            return Err(std::io::Error::other(e.to_string()));
         }
     };
-    log::info!("✅ Connected to Qdrant: {}", config.qdrant_url);
+    log::info!("🗂️ Vector backend: {}", vector.backend().name());
+    log::info!("🧠 Embeddings: {} ({} dimensions)", vector.embedder().fingerprint(), vector.embedder().dimensions());
 
     // 3.5. Initialize Prometheus Metrics
     crate::core::metrics::init_metrics();
@@ -47,10 +53,25 @@ async fn main() -> std::io::Result<()> {
         log::warn!("⚠️  Migration check failed (non-fatal): {}", e);
     }
 
+    // 4.5. Audit Log: background writer, startup chain verification, periodic checkpoints
+    let audit = Arc::new(crate::core::audit::AuditLog::new((*db).clone(), config.audit.clone()));
+    audit.spawn_startup_verification();
+    audit.spawn_checkpoints();
+    audit.spawn_retention();
+
+    // 4.6. Re-index documents and incidents in the background as they change
+    crate::core::indexing::watcher::DocumentWatcher::new(
+        (*db).clone(), vector.clone(), config.chunking.clone(), config.auto_index.clone(),
+    ).spawn();
+    crate::core::indexing::watcher::IncidentWatcher::new((*db).clone(), vector.clone(), config.auto_index.clone()).spawn();
+
     // 5. Start HTTP Server
     log::info!("🌐 Mode: HTTP only");
     let config = Arc::new(config);
-    transport::run_http_server(config.port, db, vector, config).await?;
+    transport::run_http_server(config.port, db, vector, rate_limiter, audit.clone(), config).await?;
+
+    // 6. Graceful Shutdown: flush queued audit records (spooled if SurrealDB is gone)
+    audit.shutdown().await;
 
     Ok(())
 }
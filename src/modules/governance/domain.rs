@@ -9,8 +9,15 @@ pub struct GovernanceDocument {
     pub name: String,
     #[serde(alias = "mimeType")]
     pub mime_type: String,
+    /// Version of the edition that was read (published or working copy)
+    pub version: Option<i64>,
+    /// draft, in_review, published or deprecated
+    pub status: Option<String>,
+    /// Version of the working copy; a published read only reports whether it is newer
+    pub latest_version: Option<i64>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GovernanceRule {
     pub title: String,
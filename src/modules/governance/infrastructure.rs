@@ -15,14 +15,17 @@ impl SurrealGovernanceRepository {
     }
 }
 
+/// Published editions, with the working copy's version to tell whether newer drafts exist
+const PUBLISHED_FIELDS: &str = "title, content, project_id.name as project_name, sdlc_phase, name, mimeType, version, status, document.version AS latest_version FROM mcp_documentation_published";
+const DRAFT_FIELDS: &str = "title, content, project_id.name as project_name, sdlc_phase, name, mimeType, version, status, version AS latest_version FROM mcp_documentation";
+
 #[async_trait]
 impl GovernanceRepository for SurrealGovernanceRepository {
-    async fn find_document(&self, project: String, phase: String, name: String) -> Result<Option<GovernanceDocument>> {
-        let mut result = self.db.query("
-            SELECT title, content, project_id.name as project_name, sdlc_phase, name, mimeType 
-            FROM mcp_documentation 
+    async fn find_document(&self, project: String, phase: String, name: String, drafts: bool) -> Result<Option<GovernanceDocument>> {
+        let mut result = self.db.query(format!("
+            SELECT {} 
             WHERE project_id.name = $project AND sdlc_phase = $phase AND name = $name
-        ")
+        ", if drafts { DRAFT_FIELDS } else { PUBLISHED_FIELDS }))
         .bind(("project", project))
         .bind(("phase", phase))
         .bind(("name", name))
@@ -32,12 +35,11 @@ impl GovernanceRepository for SurrealGovernanceRepository {
         Ok(doc)
     }
 
-    async fn list_documents(&self, _project: Option<String>) -> Result<Vec<GovernanceDocument>> {
+    async fn list_documents(&self, _project: Option<String>, drafts: bool) -> Result<Vec<GovernanceDocument>> {
         // TODO: Implement project filter if needed
-        let mut result = self.db.query("
-            SELECT title, content, project_id.name as project_name, sdlc_phase, name, mimeType 
-            FROM mcp_documentation
-        ").await?;
+        let mut result = self.db.query(format!("
+            SELECT {}
+        ", if drafts { DRAFT_FIELDS } else { PUBLISHED_FIELDS })).await?;
         
         let docs: Vec<GovernanceDocument> = result.take(0)?;
         Ok(docs)
@@ -4,6 +4,7 @@ use anyhow::Result;
 
 #[async_trait]
 pub trait GovernanceRepository: Send + Sync {
-    async fn find_document(&self, project: String, phase: String, name: String) -> Result<Option<GovernanceDocument>>;
-    async fn list_documents(&self, project: Option<String>) -> Result<Vec<GovernanceDocument>>;
+    /// The published edition, or with `drafts` the latest working copy
+    async fn find_document(&self, project: String, phase: String, name: String, drafts: bool) -> Result<Option<GovernanceDocument>>;
+    async fn list_documents(&self, project: Option<String>, drafts: bool) -> Result<Vec<GovernanceDocument>>;
 }